@@ -0,0 +1,260 @@
+use crate::{
+    params::Params,
+    run::Gate,
+    sim::{mask, Value},
+};
+
+/// the arithmetic parts, in palette order
+pub const PARTS: [Gate; 8] = [
+    Gate::HalfAdder,
+    Gate::FullAdder,
+    Gate::Adder,
+    Gate::Subtractor,
+    Gate::Comparator,
+    Gate::Shifter,
+    Gate::Multiplier,
+    Gate::Alu,
+];
+
+pub const SHIFT_MODES: &[&str] = &[
+    "shift left",
+    "shift right",
+    "arithmetic right",
+    "rotate left",
+    "rotate right",
+];
+
+pub const COMPARE_MODES: &[&str] = &["unsigned", "signed"];
+
+/// what the `op` pin of the alu selects
+pub const ALU_OPS: [&str; 8] = ["add", "sub", "and", "or", "xor", "nor", "slt", "sltu"];
+
+/// names of the input and output pins, top to bottom
+pub fn pin_names(g: Gate) -> Option<(&'static [&'static str], &'static [&'static str])> {
+    Some(match g {
+        Gate::HalfAdder => (&["a", "b"], &["s", "c"]),
+        Gate::FullAdder | Gate::Adder => (&["a", "b", "cin"], &["s", "cout"]),
+        Gate::Subtractor => (&["a", "b", "bin"], &["d", "bout"]),
+        Gate::Comparator => (&["a", "b"], &["lt", "eq", "gt"]),
+        Gate::Shifter => (&["a", "sh"], &["y"]),
+        Gate::Multiplier => (&["a", "b"], &["lo", "hi"]),
+        Gate::Alu => (&["a", "b", "op"], &["y", "c", "z", "v"]),
+        _ => return None,
+    })
+}
+
+/// the value as two's complement
+fn signed(v: u64, width: u8) -> i128 {
+    let v = (v & mask(width)) as i128;
+    if width > 0 && v >> (width - 1) & 1 == 1 {
+        v - (1 << width)
+    } else {
+        v
+    }
+}
+
+/// sum and carry out of `a + b + cin`
+fn add(a: u64, b: u64, cin: bool, width: u8) -> (u64, bool) {
+    let sum = (a & mask(width)) as u128 + (b & mask(width)) as u128 + cin as u128;
+    (sum as u64 & mask(width), sum >> width & 1 == 1)
+}
+
+/// difference and borrow out of `a - b - bin`
+fn sub(a: u64, b: u64, bin: bool, width: u8) -> (u64, bool) {
+    let diff = (a & mask(width)) as i128 - (b & mask(width)) as i128 - bin as i128;
+    (diff as u64 & mask(width), diff < 0)
+}
+
+fn shift(a: u64, by: u64, mode: u8, width: u8) -> u64 {
+    let m = mask(width);
+    let a = a & m;
+    let w = width as u64;
+    // a rotation goes round, a shift by the width or more leaves nothing
+    let turn = if w > 0 { (by % w) as u32 } else { 0 };
+    let by = by.min(64) as u32;
+    match SHIFT_MODES.get(mode as usize).copied() {
+        Some("shift right") => a.checked_shr(by).unwrap_or(0),
+        Some("arithmetic right") => (signed(a, width) >> by) as u64,
+        Some("rotate left") => a << turn | a.checked_shr(width as u32 - turn).unwrap_or(0),
+        Some("rotate right") => a >> turn | a.checked_shl(width as u32 - turn).unwrap_or(0),
+        _ => a.checked_shl(by).unwrap_or(0),
+    }
+}
+
+/// outputs of an arithmetic part, in the order of its output pins
+pub fn eval(g: Gate, params: &Params, inputs: &[Value]) -> Vec<Value> {
+    let width = params.width;
+    let input = |i: usize| inputs.get(i).map(|v| v.bits).unwrap_or(0);
+    let bit = |i: usize| input(i) & 1 == 1;
+    let word = |bits: u64| Value::new(bits, width);
+    match g {
+        Gate::HalfAdder => {
+            let (s, c) = add(input(0), input(1), false, 1);
+            vec![Value::new(s, 1), Value::bit(c)]
+        }
+        Gate::FullAdder => {
+            let (s, c) = add(input(0), input(1), bit(2), 1);
+            vec![Value::new(s, 1), Value::bit(c)]
+        }
+        Gate::Adder => {
+            let (s, c) = add(input(0), input(1), bit(2), width);
+            vec![word(s), Value::bit(c)]
+        }
+        Gate::Subtractor => {
+            let (d, b) = sub(input(0), input(1), bit(2), width);
+            vec![word(d), Value::bit(b)]
+        }
+        Gate::Comparator => {
+            let (a, b) = match COMPARE_MODES.get(params.mode as usize).copied() {
+                Some("signed") => (signed(input(0), width), signed(input(1), width)),
+                _ => (
+                    (input(0) & mask(width)) as i128,
+                    (input(1) & mask(width)) as i128,
+                ),
+            };
+            vec![Value::bit(a < b), Value::bit(a == b), Value::bit(a > b)]
+        }
+        Gate::Shifter => vec![word(shift(input(0), input(1), params.mode, width))],
+        Gate::Multiplier => {
+            let m = mask(width) as u128;
+            let p = (input(0) as u128 & m) * (input(1) as u128 & m);
+            vec![word(p as u64), word((p >> width) as u64)]
+        }
+        Gate::Alu => {
+            let (a, b) = (input(0), input(1));
+            let (mut c, mut v) = (false, false);
+            let y = match ALU_OPS[(input(2) & 7) as usize] {
+                "add" => {
+                    let (s, carry) = add(a, b, false, width);
+                    c = carry;
+                    v = overflowed(a, b, s, width);
+                    s
+                }
+                "sub" => {
+                    let (d, borrow) = sub(a, b, false, width);
+                    c = borrow;
+                    v = overflowed(a, !b, d, width);
+                    d
+                }
+                "and" => a & b,
+                "or" => a | b,
+                "xor" => a ^ b,
+                "nor" => !(a | b),
+                "slt" => (signed(a, width) < signed(b, width)) as u64,
+                _ => ((a & mask(width)) < (b & mask(width))) as u64,
+            };
+            let y = word(y);
+            vec![y, Value::bit(c), Value::bit(y.bits == 0), Value::bit(v)]
+        }
+        _ => vec![],
+    }
+}
+
+/// signed overflow of `a + b = s`: both operands have the same sign and the result does not
+fn overflowed(a: u64, b: u64, s: u64, width: u8) -> bool {
+    if width == 0 {
+        return false;
+    }
+    let sign = |x: u64| x >> (width - 1) & 1;
+    sign(a) == sign(b) && sign(s) != sign(a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::eval_bits;
+
+    fn params(width: u8, mode: u8) -> Params {
+        Params {
+            width,
+            mode,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn adders_carry() {
+        let bit = params(1, 0);
+        assert_eq!(eval_bits(Gate::HalfAdder, &bit, &[1, 1]), vec![0, 1]);
+        assert_eq!(eval_bits(Gate::FullAdder, &bit, &[1, 0, 1]), vec![0, 1]);
+        let byte = params(8, 0);
+        assert_eq!(eval_bits(Gate::Adder, &byte, &[200, 100, 0]), vec![44, 1]);
+        assert_eq!(eval_bits(Gate::Adder, &byte, &[255, 0, 1]), vec![0, 1]);
+        assert_eq!(
+            eval_bits(Gate::Adder, &params(64, 0), &[u64::MAX, 1, 0]),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn subtractor_borrows() {
+        let nibble = params(4, 0);
+        assert_eq!(
+            eval_bits(Gate::Subtractor, &nibble, &[3, 5, 0]),
+            vec![14, 1]
+        );
+        assert_eq!(eval_bits(Gate::Subtractor, &nibble, &[5, 3, 1]), vec![1, 0]);
+    }
+
+    #[test]
+    fn comparator_modes() {
+        // 0xf is 15 unsigned and -1 signed
+        let (unsigned, signed) = (params(4, 0), params(4, 1));
+        assert_eq!(
+            eval_bits(Gate::Comparator, &unsigned, &[0xf, 1]),
+            vec![0, 0, 1]
+        );
+        assert_eq!(
+            eval_bits(Gate::Comparator, &signed, &[0xf, 1]),
+            vec![1, 0, 0]
+        );
+        assert_eq!(eval_bits(Gate::Comparator, &signed, &[7, 7]), vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn shifter_modes() {
+        let shift = |mode: u8, by: u64| eval_bits(Gate::Shifter, &params(8, mode), &[0x81, by])[0];
+        assert_eq!(shift(0, 1), 0x02);
+        assert_eq!(shift(1, 1), 0x40);
+        assert_eq!(shift(2, 1), 0xc0);
+        assert_eq!(shift(3, 1), 0x03);
+        assert_eq!(shift(4, 1), 0xc0);
+        // shifting by the width or more leaves nothing, rotating goes round
+        assert_eq!(shift(0, 8), 0);
+        assert_eq!(shift(1, 200), 0);
+        assert_eq!(shift(2, 200), 0xff);
+        assert_eq!(shift(3, 9), 0x03);
+        assert_eq!(shift(4, 8), 0x81);
+    }
+
+    #[test]
+    fn multiplier_splits_the_product() {
+        assert_eq!(
+            eval_bits(Gate::Multiplier, &params(8, 0), &[200, 3]),
+            vec![600 & 0xff, 600 >> 8]
+        );
+        assert_eq!(
+            eval_bits(Gate::Multiplier, &params(64, 0), &[u64::MAX, 2]),
+            vec![u64::MAX - 1, 1]
+        );
+    }
+
+    #[test]
+    fn alu_flags() {
+        let op = |name: &str| ALU_OPS.iter().position(|&o| o == name).unwrap() as u64;
+        let alu =
+            |a: u64, b: u64, name: &str| eval_bits(Gate::Alu, &params(8, 0), &[a, b, op(name)]);
+        // y, carry, zero, overflow
+        assert_eq!(alu(0x7f, 1, "add"), vec![0x80, 0, 0, 1]);
+        assert_eq!(alu(0xff, 1, "add"), vec![0, 1, 1, 0]);
+        assert_eq!(alu(1, 2, "sub"), vec![0xff, 1, 0, 0]);
+        assert_eq!(alu(0x80, 1, "sub"), vec![0x7f, 0, 0, 1]);
+        assert_eq!(alu(5, 5, "sub"), vec![0, 0, 1, 0]);
+        assert_eq!(alu(0xf0, 0x3c, "and"), vec![0x30, 0, 0, 0]);
+        assert_eq!(alu(0xf0, 0x0f, "or"), vec![0xff, 0, 0, 0]);
+        assert_eq!(alu(0xff, 0xff, "xor"), vec![0, 0, 1, 0]);
+        assert_eq!(alu(0xf0, 0x0f, "nor"), vec![0, 0, 1, 0]);
+        assert_eq!(alu(0xff, 1, "slt"), vec![1, 0, 0, 0]);
+        assert_eq!(alu(0xff, 1, "sltu"), vec![0, 0, 1, 0]);
+    }
+}
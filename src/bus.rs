@@ -0,0 +1,92 @@
+use crate::{params::Params, run::Gate, sim::Value};
+
+/// the parts that can let go of their output, in palette order
+pub const PARTS: [Gate; 2] = [Gate::TriStateBuffer, Gate::ControlledInverter];
+
+pub fn pin_names(g: Gate) -> Option<(&'static [&'static str], &'static [&'static str])> {
+    match g {
+        Gate::TriStateBuffer | Gate::ControlledInverter => Some((&["a", "en"], &["y"])),
+        _ => None,
+    }
+}
+
+/// `y` follows `a`, inverted for the controlled inverter, while `en` is high and floats otherwise.
+/// a floating `en` counts as low
+pub fn eval(g: Gate, params: &Params, inputs: &[Value]) -> Vec<Value> {
+    let input = |i: usize| inputs.get(i).copied().unwrap_or_default();
+    if !input(1).is_high() {
+        return vec![Value::floating(params.width)];
+    }
+    let a = input(0).bits;
+    let y = if g == Gate::ControlledInverter { !a } else { a };
+    vec![Value::new(y, params.width)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        save::CircuitDoc,
+        sim::{FlatCircuit, Sim},
+        subcircuit::Library,
+    };
+
+    /// two tri-state buffers driving `y`, with their own `a` and `en` inputs
+    fn shared_net() -> Sim {
+        let mut doc = CircuitDoc::default();
+        let y = doc.add_gate(Gate::Output, Params::default(), [500.0, -150.0], "y");
+        for (i, top) in [0.0, -300.0].into_iter().enumerate() {
+            let a = doc.add_gate(Gate::Input, Params::default(), [0.0, top], &format!("a{i}"));
+            let en = doc.add_gate(
+                Gate::Input,
+                Params::default(),
+                [0.0, top - 100.0],
+                &format!("en{i}"),
+            );
+            let buffer = doc.add_gate(
+                Gate::TriStateBuffer,
+                Params::default(),
+                [250.0, top - 40.0],
+                "",
+            );
+            doc.connect((a, 0), (buffer, 0));
+            doc.connect((en, 0), (buffer, 1));
+            doc.connect((buffer, 0), (y, 0));
+        }
+        Sim::new(FlatCircuit::flatten(&doc, &Library::default()))
+    }
+
+    /// the value of `y` with the buffers fed `a0`, `en0`, `a1` and `en1`
+    fn drive(inputs: [u64; 4]) -> Value {
+        let mut sim = shared_net();
+        for (name, bits) in ["a0", "en0", "a1", "en1"].into_iter().zip(inputs) {
+            assert!(sim.set_input(name, Value::new(bits, 1)));
+        }
+        assert!(sim.settle(10));
+        sim.output("y").unwrap()
+    }
+
+    #[test]
+    fn one_driver() {
+        for a in 0..2 {
+            assert_eq!(drive([a, 1, 1 - a, 0]), Value::new(a, 1));
+            assert_eq!(drive([1 - a, 0, a, 1]), Value::new(a, 1));
+        }
+    }
+
+    #[test]
+    fn two_drivers_conflict() {
+        let y = drive([1, 1, 0, 1]);
+        assert_eq!(y.conflict, 1);
+        assert!(!y.is_floating());
+        // agreeing does not make it any better
+        assert_eq!(drive([1, 1, 1, 1]).conflict, 1);
+    }
+
+    #[test]
+    fn no_driver_floats() {
+        let y = drive([1, 0, 1, 0]);
+        assert!(y.is_floating());
+        assert_eq!(y.conflict, 0);
+    }
+}
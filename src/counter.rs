@@ -0,0 +1,197 @@
+use crate::{
+    params::Params,
+    run::Gate,
+    sim::{mask, PartState, Value},
+};
+
+/// counters and shift registers, in palette order
+pub const PARTS: [Gate; 3] = [Gate::Counter, Gate::Sipo, Gate::Piso];
+
+pub fn pin_names(g: Gate) -> Option<(&'static [&'static str], &'static [&'static str])> {
+    Some(match g {
+        Gate::Counter => (&["d", "ld", "en", "down", "clk"], &["q", "tc"]),
+        Gate::Sipo => (&["si", "clk"], &["q", "so"]),
+        Gate::Piso => (&["d", "ld", "clk"], &["so"]),
+        _ => return None,
+    })
+}
+
+/// everything happens on the rising edge of `clk`.
+/// a counter loads `d` while `ld` is high, otherwise counts while `en` is high or left open, down while `down` is high.
+/// `tc` is high while enabled on the last count before wrapping round.
+/// the shift registers shift towards the top bit, which comes out of `so`
+pub fn eval(g: Gate, params: &Params, inputs: &[Value], state: &mut PartState) -> Vec<Value> {
+    let width = params.width;
+    let input = |i: usize| inputs.get(i).copied().unwrap_or_default();
+    let clk_pin = match g {
+        Gate::Counter => 4,
+        Gate::Sipo => 1,
+        _ => 2,
+    };
+    let clk = input(clk_pin).is_high();
+    let rising = clk && !state.clock;
+    state.clock = clk;
+    let held = state.value.bits;
+    let top = |bits: u64| Value::bit(width > 0 && bits >> (width - 1) & 1 == 1);
+    match g {
+        Gate::Counter => {
+            let (load, enabled, down) = (
+                input(1).is_high(),
+                input(2).is_high() || input(2).is_floating(),
+                input(3).is_high(),
+            );
+            if rising {
+                let next = if load {
+                    input(0).bits
+                } else if !enabled {
+                    held
+                } else if down {
+                    held.wrapping_sub(1)
+                } else {
+                    held.wrapping_add(1)
+                };
+                state.value = Value::new(next, width);
+            }
+            let last = if down { 0 } else { mask(width) };
+            vec![state.value, Value::bit(enabled && state.value.bits == last)]
+        }
+        Gate::Sipo => {
+            if rising {
+                state.value = Value::new(held << 1 | input(0).bits & 1, width);
+            }
+            vec![state.value, top(state.value.bits)]
+        }
+        Gate::Piso => {
+            if rising {
+                let next = if input(1).is_high() {
+                    input(0).bits
+                } else {
+                    held << 1
+                };
+                state.value = Value::new(next, width);
+            }
+            vec![top(state.value.bits)]
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::eval_bits_on;
+
+    fn width(width: u8) -> Params {
+        Params {
+            width,
+            ..Default::default()
+        }
+    }
+
+    /// the outputs after a rising edge of the clock, which goes last, with `inputs` on the other pins
+    fn tick(g: Gate, params: &Params, state: &mut PartState, inputs: &[u64]) -> Vec<u64> {
+        let low = [inputs, &[0]].concat();
+        eval_bits_on(g, params, state, &low);
+        let high = [inputs, &[1]].concat();
+        eval_bits_on(g, params, state, &high)
+    }
+
+    #[test]
+    fn counter_counts() {
+        let params = width(3);
+        let mut state = PartState::default();
+        // d, ld, en, down
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[5, 1, 1, 0]),
+            [5, 0]
+        );
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[0, 0, 1, 0]),
+            [6, 0]
+        );
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[0, 0, 1, 0]),
+            [7, 1]
+        );
+        // disabled it holds, and does not say it is on the last count
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[0, 0, 0, 0]),
+            [7, 0]
+        );
+        // only on the edge
+        let held = [0, 0, 1, 0, 1];
+        assert_eq!(
+            eval_bits_on(Gate::Counter, &params, &mut state, &held),
+            [7, 1]
+        );
+        // wraps at the width
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[0, 0, 1, 0]),
+            [0, 0]
+        );
+        // and down again, ending on 0
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[0, 0, 1, 1]),
+            [7, 0]
+        );
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[1, 1, 1, 1]),
+            [1, 0]
+        );
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[0, 0, 1, 1]),
+            [0, 1]
+        );
+        // loading beats counting, keeping the bits that fit
+        assert_eq!(
+            tick(Gate::Counter, &params, &mut state, &[12, 1, 1, 0]),
+            [4, 0]
+        );
+    }
+
+    #[test]
+    fn open_enable_counts() {
+        let mut state = PartState::default();
+        let mut step = |clk: bool| {
+            let inputs = [
+                Value::new(0, 4),
+                Value::bit(false),
+                Value::floating(1),
+                Value::bit(false),
+                Value::bit(clk),
+            ];
+            eval(Gate::Counter, &width(4), &inputs, &mut state)[0].bits
+        };
+        step(false);
+        assert_eq!(step(true), 1);
+        step(false);
+        assert_eq!(step(true), 2);
+    }
+
+    #[test]
+    fn serial_in() {
+        let params = width(4);
+        let mut state = PartState::default();
+        let outputs = [1, 0, 1, 1, 0]
+            .iter()
+            .map(|&si| tick(Gate::Sipo, &params, &mut state, &[si]))
+            .collect::<Vec<_>>();
+        // q, and so with its top bit
+        assert_eq!(
+            outputs,
+            [[0b1, 0], [0b10, 0], [0b101, 0], [0b1011, 1], [0b0110, 0]]
+        );
+    }
+
+    #[test]
+    fn serial_out() {
+        let params = width(4);
+        let mut state = PartState::default();
+        // d, ld
+        assert_eq!(tick(Gate::Piso, &params, &mut state, &[0b1101, 1]), [1]);
+        let shifted = (0..4)
+            .map(|_| tick(Gate::Piso, &params, &mut state, &[0b1111, 0])[0])
+            .collect::<Vec<_>>();
+        assert_eq!(shifted, [1, 0, 1, 0]);
+    }
+}
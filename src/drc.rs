@@ -0,0 +1,370 @@
+use bevy::prelude::{
+    App, Camera, Commands, Entity, Local, Plugin, Query, Res, ResMut, Transform, With,
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    netlist::{on_segment, pin_pos, point_key, CircuitNets, Netlist},
+    pin::PinDir,
+    run::{GameState, Gate, Selected, WireSprite},
+    save::CircuitDoc,
+    subcircuit::Library,
+};
+
+pub struct DrcPlugin;
+
+impl Plugin for DrcPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(drc_ui)
+                .into(),
+        );
+    }
+}
+
+/// something on the canvas, by its index in the [`CircuitDoc`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Gate(usize),
+    Wire(usize),
+}
+
+/// a likely wiring mistake
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// an input pin that nothing drives
+    UnconnectedInput { gate: usize, pin: usize },
+    /// a net with more than one output driving it, other than tri-state outputs sharing a bus
+    OutputsShorted { gates: Vec<usize> },
+    /// a wire with an end that touches nothing
+    DanglingWire { wire: usize },
+    /// wires lying on top of each other without being connected
+    OverlappingWires { wires: [usize; 2] },
+    /// a part whose outputs go nowhere
+    UnusedGate { gate: usize },
+}
+
+impl Issue {
+    pub fn target(&self) -> Target {
+        match self {
+            Issue::UnconnectedInput { gate, .. } | Issue::UnusedGate { gate } => {
+                Target::Gate(*gate)
+            }
+            Issue::OutputsShorted { gates } => Target::Gate(gates[0]),
+            Issue::DanglingWire { wire } => Target::Wire(*wire),
+            Issue::OverlappingWires { wires } => Target::Wire(wires[0]),
+        }
+    }
+
+    pub fn message(&self, doc: &CircuitDoc) -> String {
+        let title = |gi: usize| doc.gates[gi].gate.title();
+        match self {
+            Issue::UnconnectedInput { gate, pin } => {
+                format!("{}: input {} is not connected", title(*gate), pin + 1)
+            }
+            Issue::OutputsShorted { gates } => format!(
+                "outputs of {} are connected together",
+                gates
+                    .iter()
+                    .map(|&g| title(g))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Issue::DanglingWire { .. } => "a wire ends on nothing".to_string(),
+            Issue::OverlappingWires { .. } => "two wires overlap without a junction".to_string(),
+            Issue::UnusedGate { gate } => format!("{}: the output goes nowhere", title(*gate)),
+        }
+    }
+}
+
+fn is_tri_state(g: Gate) -> bool {
+    matches!(g, Gate::TriStateBuffer | Gate::ControlledInverter)
+}
+
+/// every issue found in a circuit, parts first and then wires
+pub fn check(doc: &CircuitDoc, library: &Library) -> Vec<Issue> {
+    check_netlist(doc, library, &Netlist::build(doc, library))
+}
+
+pub fn check_netlist(doc: &CircuitDoc, library: &Library, netlist: &Netlist) -> Vec<Issue> {
+    let dirs = doc
+        .gates
+        .iter()
+        .map(|g| library.pins(g).iter().map(|p| p.dir).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let dirs = &dirs;
+    // tunnels only join nets, they neither drive nor read them
+    let pins_on = |n: usize, dir: PinDir| {
+        netlist.nets[n]
+            .pins
+            .iter()
+            .filter(move |&&(gi, pi)| doc.gates[gi].gate != Gate::Tunnel && dirs[gi][pi] == dir)
+            .copied()
+    };
+
+    let mut issues = vec![];
+    for (gi, g) in doc.gates.iter().enumerate() {
+        if g.gate == Gate::Tunnel {
+            continue;
+        }
+        let inputs = dirs[gi]
+            .iter()
+            .enumerate()
+            .filter(|(_, &d)| d == PinDir::In);
+        for (k, (pi, _)) in inputs.enumerate() {
+            if pins_on(netlist.pin_nets[gi][pi], PinDir::Out)
+                .next()
+                .is_none()
+            {
+                issues.push(Issue::UnconnectedInput { gate: gi, pin: k });
+            }
+        }
+    }
+    for n in 0..netlist.nets.len() {
+        let mut drivers = pins_on(n, PinDir::Out)
+            .map(|(gi, _)| gi)
+            .collect::<Vec<_>>();
+        drivers.dedup();
+        let plain = drivers
+            .iter()
+            .filter(|&&gi| !is_tri_state(doc.gates[gi].gate))
+            .count();
+        if drivers.len() > 1 && plain > 0 {
+            issues.push(Issue::OutputsShorted { gates: drivers });
+        }
+    }
+    for (gi, _) in doc.gates.iter().enumerate() {
+        let outputs = dirs[gi]
+            .iter()
+            .enumerate()
+            .filter(|(_, &d)| d == PinDir::Out)
+            .map(|(pi, _)| netlist.pin_nets[gi][pi])
+            .collect::<Vec<_>>();
+        let read = outputs
+            .iter()
+            .any(|&n| pins_on(n, PinDir::In).any(|(other, _)| other != gi));
+        if !outputs.is_empty() && !read {
+            issues.push(Issue::UnusedGate { gate: gi });
+        }
+    }
+
+    let pins = doc
+        .gates
+        .iter()
+        .flat_map(|g| {
+            library
+                .pins(g)
+                .into_iter()
+                .map(|spec| point_key(pin_pos(g, &spec)))
+        })
+        .collect::<Vec<_>>();
+    let nodes = |wi: usize| {
+        doc.wires[wi]
+            .nodes
+            .iter()
+            .map(|&p| point_key(p.into()))
+            .collect::<Vec<_>>()
+    };
+    let wires = (0..doc.wires.len()).map(nodes).collect::<Vec<_>>();
+    for (wi, w) in wires.iter().enumerate() {
+        let ends = [w.first(), w.last()];
+        let dangling = ends.into_iter().flatten().any(|&p| {
+            let on_pin = pins.contains(&p);
+            let on_wire = wires.iter().enumerate().any(|(other, o)| {
+                other != wi
+                    && (o.first() == Some(&p)
+                        || o.last() == Some(&p)
+                        || o.windows(2).any(|s| on_segment(p, s[0], s[1])))
+            });
+            !on_pin && !on_wire
+        });
+        if dangling {
+            issues.push(Issue::DanglingWire { wire: wi });
+        }
+    }
+    for a in 0..wires.len() {
+        for b in a + 1..wires.len() {
+            if netlist.wire_nets[a] == netlist.wire_nets[b] {
+                continue;
+            }
+            let overlap = wires[a].windows(2).any(|s| {
+                wires[b]
+                    .windows(2)
+                    .any(|t| segments_overlap((s[0], s[1]), (t[0], t[1])))
+            });
+            if overlap {
+                issues.push(Issue::OverlappingWires { wires: [a, b] });
+            }
+        }
+    }
+    issues
+}
+
+type Point = (i64, i64);
+
+/// whether two segments lie along the same line and share more than a point
+fn segments_overlap(s: (Point, Point), t: (Point, Point)) -> bool {
+    let ((a, b), (c, d)) = (s, t);
+    if !on_line(c, a, b) || !on_line(d, a, b) || a == b {
+        return false;
+    }
+    // compare positions along the segment's direction
+    let along = |p: Point| (p.0 - a.0) * (b.0 - a.0) + (p.1 - a.1) * (b.1 - a.1);
+    let (lo, hi) = (0, along(b));
+    let (t0, t1) = (along(c).min(along(d)), along(c).max(along(d)));
+    t0.max(lo) < t1.min(hi)
+}
+
+fn on_line(p: Point, a: Point, b: Point) -> bool {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0) == 0
+}
+
+#[derive(Default)]
+struct DrcWindow {
+    doc: CircuitDoc,
+    issues: Vec<Issue>,
+}
+
+/// lists the issues of the circuit on the canvas. clicking one selects it and moves the camera there
+#[allow(clippy::too_many_arguments)]
+fn drc_ui(
+    mut c: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<DrcWindow>,
+    nets: Res<CircuitNets>,
+    library: Res<Library>,
+    selected: Query<Entity, With<Selected>>,
+    wire_sprites: Query<(Entity, &WireSprite)>,
+    mut q_camera: Query<&mut Transform, With<Camera>>,
+) {
+    let window = &mut *window;
+    if window.doc != nets.doc || library.is_changed() {
+        window.doc = nets.doc.clone();
+        window.issues = check_netlist(&nets.doc, &library, &nets.netlist);
+    }
+    let mut focus = None;
+    egui::Window::new("design rules").show(egui_context.ctx_mut(), |ui| {
+        if window.issues.is_empty() {
+            ui.label("no issues");
+        }
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for issue in window.issues.iter() {
+                    if ui.link(issue.message(&window.doc)).clicked() {
+                        focus = Some(issue.target());
+                    }
+                }
+            });
+    });
+
+    let (entities, pos) = match focus {
+        Some(Target::Gate(gi)) => match nets.gates.get(gi) {
+            Some(&e) => (vec![e], nets.doc.gates[gi].pos),
+            None => return,
+        },
+        Some(Target::Wire(wi)) => match nets.wires.get(wi) {
+            Some(&wire) => (
+                wire_sprites
+                    .iter()
+                    .filter(|(_, ws)| ws.wire == wire)
+                    .map(|(e, _)| e)
+                    .collect(),
+                nets.doc.wires[wi]
+                    .nodes
+                    .first()
+                    .copied()
+                    .unwrap_or_default(),
+            ),
+            None => return,
+        },
+        None => return,
+    };
+    selected.iter().for_each(|e| {
+        c.entity(e).remove::<Selected>();
+    });
+    entities.into_iter().for_each(|e| {
+        c.entity(e).insert(Selected);
+    });
+    if let Ok(mut camera) = q_camera.get_single_mut() {
+        camera.translation.x = pos[0];
+        camera.translation.y = pos[1];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        orientation::Orientation,
+        params::Params,
+        save::{GateDoc, WireDoc},
+    };
+
+    fn gate(gate: Gate, pos: [f32; 2]) -> GateDoc {
+        GateDoc {
+            gate,
+            pos,
+            orientation: Orientation::default(),
+            def: None,
+            params: Params::default(),
+            memory: vec![],
+        }
+    }
+
+    fn wire(nodes: &[[f32; 2]]) -> WireDoc {
+        WireDoc {
+            nodes: nodes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn lone_gate() {
+        let doc = CircuitDoc {
+            gates: vec![gate(Gate::And, [0.0, 0.0])],
+            ..Default::default()
+        };
+        assert_eq!(
+            check(&doc, &Library::default()),
+            vec![
+                Issue::UnconnectedInput { gate: 0, pin: 0 },
+                Issue::UnconnectedInput { gate: 0, pin: 1 },
+                Issue::UnusedGate { gate: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn shorted_outputs() {
+        // both ports have their pin on the same point
+        let doc = CircuitDoc {
+            gates: vec![gate(Gate::Input, [0.0, 0.0]), gate(Gate::Input, [0.0, 0.0])],
+            ..Default::default()
+        };
+        let issues = check(&doc, &Library::default());
+        assert!(issues.contains(&Issue::OutputsShorted { gates: vec![0, 1] }));
+    }
+
+    #[test]
+    fn overlapping_wires() {
+        // the middle segments overlap but no end of one wire touches the other
+        let doc = CircuitDoc {
+            wires: vec![
+                wire(&[[0.0, -10.0], [0.0, 0.0], [50.0, 0.0], [50.0, -10.0]]),
+                wire(&[[25.0, 10.0], [25.0, 0.0], [75.0, 0.0], [75.0, 10.0]]),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            check(&doc, &Library::default()),
+            vec![
+                Issue::DanglingWire { wire: 0 },
+                Issue::DanglingWire { wire: 1 },
+                Issue::OverlappingWires { wires: [0, 1] },
+            ]
+        );
+    }
+}
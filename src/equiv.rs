@@ -0,0 +1,865 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{bail, Result};
+use bevy::prelude::{App, Local, Plugin, ResMut};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    pin::PinDir,
+    run::{GameState, Gate},
+    save::CircuitDoc,
+    sim::{mask, FlatCircuit, Sim, Value},
+    subcircuit::Library,
+    truth::{input_bits, port_columns, stateful_parts, Column},
+};
+
+pub struct EquivPlugin;
+
+impl Plugin for EquivPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(equivalence_ui)
+                .into(),
+        );
+    }
+}
+
+/// circuits with at most this many input bits are compared on every input, bigger ones with bdds
+pub const EXHAUSTIVE_BITS: u32 = 12;
+/// a bdd store that grows past this many nodes, or remembered operations, is given up on
+const MAX_BDD_NODES: usize = 1 << 20;
+
+/// inputs on which two circuits differ, with what each gives on every output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Counterexample {
+    pub inputs: Vec<(String, u64)>,
+    /// none where the circuit did not settle
+    pub outputs: Vec<(String, Option<Value>, Option<Value>)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Equivalent,
+    Differ(Counterexample),
+    /// they agree wherever they settle, but on these inputs neither does
+    Inconclusive(Counterexample),
+}
+
+/// compares the circuits in two saved files, see [`check_equivalence`]
+pub fn check_files(a: &Path, b: &Path) -> Result<Verdict> {
+    check_equivalence(&CircuitDoc::read(a)?, &CircuitDoc::read(b)?)
+}
+
+/// whether two circuits give the same outputs for every input. their input and output ports
+/// must have the same names and widths, and neither may remember anything between inputs.
+/// compared on every input, a floating or conflicting output is a value like any other, which only
+/// matches the same. bdds only know high and low, so there an output that depends on a net nothing
+/// drives is an error
+pub fn check_equivalence(a: &CircuitDoc, b: &CircuitDoc) -> Result<Verdict> {
+    let flatten = |doc: &CircuitDoc| {
+        let library = Library {
+            defs: doc.subcircuits.clone(),
+            ..Default::default()
+        };
+        FlatCircuit::flatten(doc, &library)
+    };
+    let (a, b) = (flatten(a), flatten(b));
+    let (inputs, outputs) = port_columns(&a);
+    let sorted = |mut columns: Vec<Column>| {
+        columns.sort_by(|x, y| x.name.cmp(&y.name));
+        columns
+    };
+    let (b_inputs, b_outputs) = port_columns(&b);
+    if sorted(inputs.clone()) != sorted(b_inputs) || sorted(outputs.clone()) != sorted(b_outputs) {
+        bail!("the circuits do not have the same ports");
+    }
+    for circuit in [&a, &b] {
+        let stateful = stateful_parts(circuit);
+        if !stateful.is_empty() {
+            bail!(
+                "only circuits without memory can be compared, not ones with {}",
+                stateful.join(", ")
+            );
+        }
+    }
+    let (a, b) = (Sim::new(a), Sim::new(b));
+    if input_bits(&inputs) <= EXHAUSTIVE_BITS {
+        exhaustive(&a, &b, &inputs, &outputs)
+    } else {
+        symbolic(&a, &b, &inputs, &outputs)
+    }
+}
+
+/// runs both circuits on `inputs` and reads their outputs
+fn compare(a: &Sim, b: &Sim, inputs: &[(String, u64)], outputs: &[Column]) -> Counterexample {
+    let run = |sim: &Sim| {
+        let mut sim = sim.clone();
+        for (name, bits) in inputs {
+            sim.set_input(name, Value::new(*bits, 64));
+        }
+        let settled = sim.settle(sim.circuit.parts.len() + 2);
+        outputs
+            .iter()
+            .map(|c| sim.output(&c.name).filter(|_| settled))
+            .collect::<Vec<_>>()
+    };
+    Counterexample {
+        inputs: inputs.to_vec(),
+        outputs: outputs
+            .iter()
+            .zip(run(a).into_iter().zip(run(b)))
+            .map(|(c, (x, y))| (c.name.clone(), x, y))
+            .collect(),
+    }
+}
+
+fn exhaustive(a: &Sim, b: &Sim, inputs: &[Column], outputs: &[Column]) -> Result<Verdict> {
+    let bits = input_bits(inputs);
+    let mut unsettled = None;
+    for combination in 0..1u64 << bits {
+        let mut shift = bits;
+        let vector = inputs
+            .iter()
+            .map(|c| {
+                shift -= c.width as u32;
+                (c.name.clone(), combination >> shift & mask(c.width))
+            })
+            .collect::<Vec<_>>();
+        let result = compare(a, b, &vector, outputs);
+        if result.outputs.iter().any(|(_, x, y)| x != y) {
+            return Ok(Verdict::Differ(result));
+        }
+        if unsettled.is_none() && result.outputs.iter().any(|(_, x, _)| x.is_none()) {
+            unsettled = Some(result);
+        }
+    }
+    Ok(unsettled.map_or(Verdict::Equivalent, Verdict::Inconclusive))
+}
+
+/// a store of reduced ordered binary decision diagrams. node 0 is false and node 1 true
+struct Bdd {
+    /// variable, low child, high child
+    nodes: Vec<(u32, usize, usize)>,
+    unique: HashMap<(u32, usize, usize), usize>,
+    ites: HashMap<(usize, usize, usize), usize>,
+    /// how many nodes, and remembered operations, it may hold
+    limit: usize,
+}
+
+const FALSE: usize = 0;
+const TRUE: usize = 1;
+
+impl Default for Bdd {
+    fn default() -> Self {
+        Self {
+            nodes: vec![(u32::MAX, FALSE, FALSE), (u32::MAX, TRUE, TRUE)],
+            unique: HashMap::new(),
+            ites: HashMap::new(),
+            limit: MAX_BDD_NODES,
+        }
+    }
+}
+
+impl Bdd {
+    /// fails once the store is full, so a blow up stops where it happens
+    fn node(&mut self, var: u32, lo: usize, hi: usize) -> Result<usize> {
+        if lo == hi {
+            return Ok(lo);
+        }
+        if let Some(&n) = self.unique.get(&(var, lo, hi)) {
+            return Ok(n);
+        }
+        if self.nodes.len() >= self.limit {
+            bail!("the circuits are too big to compare");
+        }
+        let n = self.nodes.len();
+        self.nodes.push((var, lo, hi));
+        self.unique.insert((var, lo, hi), n);
+        Ok(n)
+    }
+
+    fn var(&mut self, var: u32) -> Result<usize> {
+        self.node(var, FALSE, TRUE)
+    }
+
+    /// `f`, with `var` set to `value`
+    fn restrict(&self, f: usize, var: u32, value: bool) -> usize {
+        match self.nodes[f] {
+            (v, lo, hi) if v == var => {
+                if value {
+                    hi
+                } else {
+                    lo
+                }
+            }
+            _ => f,
+        }
+    }
+
+    /// if `f` then `g` else `h`
+    fn ite(&mut self, f: usize, g: usize, h: usize) -> Result<usize> {
+        match (f, g, h) {
+            (TRUE, _, _) => return Ok(g),
+            (FALSE, _, _) => return Ok(h),
+            _ if g == h => return Ok(g),
+            (_, TRUE, FALSE) => return Ok(f),
+            _ => {}
+        }
+        if let Some(&r) = self.ites.get(&(f, g, h)) {
+            return Ok(r);
+        }
+        // the results remembered grow with the work done, so they count against the budget too
+        if self.ites.len() >= self.limit {
+            bail!("the circuits are too big to compare");
+        }
+        let var = [f, g, h]
+            .iter()
+            .map(|&x| self.nodes[x].0)
+            .min()
+            .unwrap_or(0);
+        let lo = {
+            let (f, g, h) = (
+                self.restrict(f, var, false),
+                self.restrict(g, var, false),
+                self.restrict(h, var, false),
+            );
+            self.ite(f, g, h)?
+        };
+        let hi = {
+            let (f, g, h) = (
+                self.restrict(f, var, true),
+                self.restrict(g, var, true),
+                self.restrict(h, var, true),
+            );
+            self.ite(f, g, h)?
+        };
+        let r = self.node(var, lo, hi)?;
+        self.ites.insert((f, g, h), r);
+        Ok(r)
+    }
+
+    fn not(&mut self, f: usize) -> Result<usize> {
+        self.ite(f, FALSE, TRUE)
+    }
+
+    fn and(&mut self, f: usize, g: usize) -> Result<usize> {
+        self.ite(f, g, FALSE)
+    }
+
+    fn or(&mut self, f: usize, g: usize) -> Result<usize> {
+        self.ite(f, TRUE, g)
+    }
+
+    fn xor(&mut self, f: usize, g: usize) -> Result<usize> {
+        let not_g = self.not(g)?;
+        self.ite(f, not_g, g)
+    }
+
+    /// variables that make `f` true, the others being false. none if `f` is never true
+    fn satisfy(&self, mut f: usize) -> Option<Vec<u32>> {
+        if f == FALSE {
+            return None;
+        }
+        let mut set = vec![];
+        while f != TRUE {
+            let (var, lo, hi) = self.nodes[f];
+            if lo != FALSE {
+                f = lo;
+            } else {
+                set.push(var);
+                f = hi;
+            }
+        }
+        Some(set)
+    }
+}
+
+/// the bdd variable of every bit of every input port, by name. bits that meet in one place, like
+/// those of the same weight going into an adder, only keep the bdds small when their variables
+/// are close, so the ports take turns: a0 b0 a1 b1 and so on. a one bit port whose name ends in
+/// a number, like `a3`, takes its turn as bit 3
+fn variable_order(inputs: &[Column]) -> HashMap<String, Vec<u32>> {
+    let weight = |c: &Column, bit: u32| {
+        if c.width > 1 {
+            return bit;
+        }
+        let stem = c.name.trim_end_matches(|ch: char| ch.is_ascii_digit());
+        c.name[stem.len()..].parse().unwrap_or(0)
+    };
+    let mut bits = inputs
+        .iter()
+        .enumerate()
+        .flat_map(|(port, c)| (0..c.width as u32).map(move |bit| (port, bit)))
+        .collect::<Vec<_>>();
+    bits.sort_by_key(|&(port, bit)| (weight(&inputs[port], bit), port));
+    let mut vars = inputs
+        .iter()
+        .map(|c| (c.name.clone(), vec![0; c.width as usize]))
+        .collect::<HashMap<_, _>>();
+    for (var, (port, bit)) in bits.into_iter().enumerate() {
+        if let Some(v) = vars.get_mut(&inputs[port].name) {
+            v[bit as usize] = var as u32;
+        }
+    }
+    vars
+}
+
+/// the bdds of every bit of every net of a circuit, worked out from the parts driving them
+struct Symbolic<'a> {
+    circuit: &'a FlatCircuit,
+    drivers: HashMap<usize, Vec<usize>>,
+    /// the variable of each bit of each input port, by name
+    vars: &'a HashMap<String, Vec<u32>>,
+    parts: HashMap<usize, Vec<Vec<usize>>>,
+    visiting: Vec<usize>,
+}
+
+impl Symbolic<'_> {
+    fn net(&mut self, bdd: &mut Bdd, net: usize, width: u8) -> Result<Vec<usize>> {
+        let (part, out) = match self.drivers.get(&net).map(|d| d.as_slice()) {
+            Some([]) | None => bail!("an output depends on a net that nothing drives"),
+            Some(&[part]) => {
+                let p = &self.circuit.parts[part];
+                let out = p
+                    .pins
+                    .iter()
+                    .zip(p.dirs.iter())
+                    .filter(|(_, &d)| d == PinDir::Out)
+                    .position(|(&n, _)| n == net)
+                    .unwrap_or(0);
+                (part, out)
+            }
+            Some(_) => bail!("a net is driven by more than one part"),
+        };
+        let mut bits = self.part(bdd, part)?.get(out).cloned().unwrap_or_default();
+        bits.resize(width as usize, FALSE);
+        Ok(bits)
+    }
+
+    fn part(&mut self, bdd: &mut Bdd, part: usize) -> Result<Vec<Vec<usize>>> {
+        if let Some(outputs) = self.parts.get(&part) {
+            return Ok(outputs.clone());
+        }
+        if self.visiting.contains(&part) {
+            bail!("the circuit has a feedback loop");
+        }
+        self.visiting.push(part);
+        let circuit = self.circuit;
+        let p = &circuit.parts[part];
+        let width = p.params.width;
+        let input_nets = p
+            .pins
+            .iter()
+            .zip(p.dirs.iter())
+            .filter(|(_, &d)| d == PinDir::In)
+            .map(|(&n, _)| n)
+            .collect::<Vec<_>>();
+        let mut inputs = vec![];
+        for (i, &n) in input_nets.iter().enumerate() {
+            // the select lines of a multiplexer are narrower than its data
+            let w = match p.gate {
+                Gate::Mux if i == input_nets.len() - 1 => p.params.select,
+                Gate::HalfAdder | Gate::FullAdder => 1,
+                Gate::Adder if i == 2 => 1,
+                _ => width,
+            };
+            inputs.push(self.net(bdd, n, w)?);
+        }
+        let outputs = part_bits(bdd, self.circuit, part, self.vars, &inputs)?;
+        self.visiting.pop();
+        self.parts.insert(part, outputs.clone());
+        Ok(outputs)
+    }
+}
+
+/// the bdds of the outputs of a part, from those of its inputs
+fn part_bits(
+    bdd: &mut Bdd,
+    circuit: &FlatCircuit,
+    part: usize,
+    vars: &HashMap<String, Vec<u32>>,
+    inputs: &[Vec<usize>],
+) -> Result<Vec<Vec<usize>>> {
+    let p = &circuit.parts[part];
+    let width = p.params.width as usize;
+    let bitwise = |bdd: &mut Bdd, and: bool, invert: bool| -> Result<Vec<usize>> {
+        (0..width)
+            .map(|i| {
+                let start = if and { TRUE } else { FALSE };
+                let bit = inputs.iter().try_fold(start, |acc, x| {
+                    if and {
+                        bdd.and(acc, x[i])
+                    } else {
+                        bdd.or(acc, x[i])
+                    }
+                })?;
+                if invert {
+                    bdd.not(bit)
+                } else {
+                    Ok(bit)
+                }
+            })
+            .collect()
+    };
+    // a ripple carry adder, the carry out last
+    let add = |bdd: &mut Bdd, a: &[usize], b: &[usize], mut carry: usize| -> Result<_> {
+        let mut sum = vec![];
+        for (&x, &y) in a.iter().zip(b.iter()) {
+            let half = bdd.xor(x, y)?;
+            sum.push(bdd.xor(half, carry)?);
+            let both = bdd.and(x, y)?;
+            let through = bdd.and(half, carry)?;
+            carry = bdd.or(both, through)?;
+        }
+        Ok((sum, carry))
+    };
+    Ok(match p.gate {
+        Gate::Input => {
+            let bits = match circuit.inputs.iter().find(|(_, i)| *i == part) {
+                Some((name, _)) => vars.get(name).cloned().unwrap_or_default(),
+                None => bail!("an input port is not on the top level"),
+            };
+            let mut y = bits
+                .into_iter()
+                .take(width)
+                .map(|v| bdd.var(v))
+                .collect::<Result<Vec<_>>>()?;
+            y.resize(width, FALSE);
+            vec![y]
+        }
+        Gate::Output => vec![],
+        Gate::And => vec![bitwise(bdd, true, false)?],
+        Gate::Or => vec![bitwise(bdd, false, false)?],
+        Gate::Nand => vec![bitwise(bdd, true, true)?],
+        Gate::Nor => vec![bitwise(bdd, false, true)?],
+        Gate::Not => vec![inputs[0]
+            .iter()
+            .map(|&x| bdd.not(x))
+            .collect::<Result<_>>()?],
+        Gate::Mux => {
+            let (data, sel) = inputs.split_at(inputs.len() - 1);
+            let y = (0..width)
+                .map(|i| {
+                    data.iter().enumerate().try_fold(FALSE, |acc, (k, d)| {
+                        // d_k gets through when the select lines spell k
+                        let chosen =
+                            sel[0].iter().enumerate().try_fold(TRUE, |c, (s, &line)| {
+                                let line = if k >> s & 1 == 1 {
+                                    line
+                                } else {
+                                    bdd.not(line)?
+                                };
+                                bdd.and(c, line)
+                            })?;
+                        let through = bdd.and(chosen, d[i])?;
+                        bdd.or(acc, through)
+                    })
+                })
+                .collect::<Result<_>>()?;
+            vec![y]
+        }
+        Gate::HalfAdder => {
+            let (s, c) = add(bdd, &inputs[0], &inputs[1], FALSE)?;
+            vec![s, vec![c]]
+        }
+        Gate::FullAdder | Gate::Adder => {
+            let (s, c) = add(bdd, &inputs[0], &inputs[1], inputs[2][0])?;
+            vec![s, vec![c]]
+        }
+        g => bail!(
+            "a {} cannot be compared on more than {EXHAUSTIVE_BITS} input bits",
+            g.title()
+        ),
+    })
+}
+
+fn symbolic(a: &Sim, b: &Sim, inputs: &[Column], outputs: &[Column]) -> Result<Verdict> {
+    let vars = variable_order(inputs);
+    let mut bdd = Bdd::default();
+    let output_bits = |sim: &Sim, bdd: &mut Bdd| -> Result<Vec<Vec<usize>>> {
+        let circuit = &sim.circuit;
+        let mut s = Symbolic {
+            circuit,
+            drivers: circuit.drivers(),
+            vars: &vars,
+            parts: HashMap::new(),
+            visiting: vec![],
+        };
+        outputs
+            .iter()
+            .map(|c| {
+                let part = circuit
+                    .outputs
+                    .iter()
+                    .find(|(name, _)| *name == c.name)
+                    .map(|(_, part)| *part);
+                match part.and_then(|p| circuit.parts[p].pins.first()) {
+                    Some(&net) => s.net(bdd, net, c.width),
+                    None => bail!("the output {} is not on the top level", c.name),
+                }
+            })
+            .collect()
+    };
+    let xs = output_bits(a, &mut bdd)?;
+    let ys = output_bits(b, &mut bdd)?;
+    for (x, y) in xs.iter().flatten().zip(ys.iter().flatten()) {
+        let differ = bdd.xor(*x, *y)?;
+        if let Some(set) = bdd.satisfy(differ) {
+            let vector = inputs
+                .iter()
+                .map(|c| {
+                    let bits = vars[&c.name]
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, v)| set.contains(v))
+                        .fold(0, |bits, (i, _)| bits | 1 << i);
+                    (c.name.clone(), bits)
+                })
+                .collect::<Vec<_>>();
+            return Ok(Verdict::Differ(compare(a, b, &vector, outputs)));
+        }
+    }
+    Ok(Verdict::Equivalent)
+}
+
+#[derive(Default)]
+struct EquivalenceWindow {
+    reference: String,
+    other: String,
+    result: Option<Result<Verdict, String>>,
+}
+
+fn show_value(v: &Option<Value>) -> String {
+    match v {
+        None => "does not settle".to_string(),
+        Some(v) if v.conflict != 0 => "conflict".to_string(),
+        Some(v) if v.is_floating() => "floating".to_string(),
+        Some(v) => format!("{:#x}", v.bits),
+    }
+}
+
+/// compares two circuit files
+fn equivalence_ui(mut egui_context: ResMut<EguiContext>, mut window: Local<EquivalenceWindow>) {
+    let window = &mut *window;
+    egui::Window::new("equivalence").show(egui_context.ctx_mut(), |ui| {
+        egui::Grid::new("equivalence files").show(ui, |ui| {
+            ui.label("reference");
+            ui.text_edit_singleline(&mut window.reference);
+            ui.end_row();
+            ui.label("circuit");
+            ui.text_edit_singleline(&mut window.other);
+            ui.end_row();
+        });
+        if ui.button("compare").clicked() {
+            window.result = Some(
+                check_files(Path::new(&window.reference), Path::new(&window.other))
+                    .map_err(|e| e.to_string()),
+            );
+        }
+        match &window.result {
+            None => {}
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::YELLOW, e);
+            }
+            Some(Ok(Verdict::Equivalent)) => {
+                ui.colored_label(egui::Color32::GREEN, "equivalent");
+            }
+            Some(Ok(Verdict::Differ(c))) => {
+                ui.colored_label(egui::Color32::RED, "they differ when");
+                for (name, bits) in c.inputs.iter() {
+                    ui.label(format!("{name} = {bits:#x}"));
+                }
+                for (name, x, y) in c.outputs.iter().filter(|(_, x, y)| x != y) {
+                    ui.label(format!(
+                        "{name} is {} in the reference but {}",
+                        show_value(x),
+                        show_value(y)
+                    ));
+                }
+            }
+            Some(Ok(Verdict::Inconclusive(c))) => {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    "they agree wherever they settle, but neither does when",
+                );
+                for (name, bits) in c.inputs.iter() {
+                    ui.label(format!("{name} = {bits:#x}"));
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::Vec2;
+
+    use crate::{
+        expr::Expr,
+        params::Params,
+        synth::{synthesize, GateSet},
+    };
+
+    fn var(name: &str) -> Expr {
+        Expr::Var(name.to_string())
+    }
+
+    fn xor(x: Expr, y: Expr) -> Expr {
+        Expr::Or(vec![
+            Expr::And(vec![x.clone(), !y.clone()]),
+            Expr::And(vec![!x, y]),
+        ])
+    }
+
+    /// a gate level ripple carry adder on one bit ports `a0`, `b0` and so on, the inputs listed
+    /// all the `a`s first. with `broken`, the last carry only comes from the last bits
+    fn ripple_adder(bits: usize, set: GateSet, broken: bool) -> CircuitDoc {
+        let inputs = ["a", "b"]
+            .iter()
+            .flat_map(|x| (0..bits).map(move |i| format!("{x}{i}")))
+            .collect::<Vec<_>>();
+        let mut equations = vec![];
+        let mut carry = Expr::Const(false);
+        for i in 0..bits {
+            let (a, b) = (var(&format!("a{i}")), var(&format!("b{i}")));
+            equations.push((
+                format!("s{i}"),
+                xor(xor(a.clone(), b.clone()), carry.clone()),
+            ));
+            let both = Expr::And(vec![a.clone(), b.clone()]);
+            carry = if broken && i == bits - 1 {
+                both
+            } else {
+                Expr::Or(vec![both, Expr::And(vec![Expr::Or(vec![a, b]), carry])])
+            };
+        }
+        equations.push(("carry".to_string(), carry));
+        synthesize(&inputs, &equations, set, Vec2::ZERO).unwrap()
+    }
+
+    /// an adder part between ports `a`, `b`, `cin`, `s` and `cout`, with `b` going through a not
+    /// when `invert` is set and `a` and `b` swapped when `swap` is
+    fn adder_part(width: u8, swap: bool, invert: bool) -> CircuitDoc {
+        let mut doc = CircuitDoc::default();
+        let a = doc.add_gate(Gate::Input, wide(width), [0.0, 0.0], "a");
+        let b = doc.add_gate(Gate::Input, wide(width), [0.0, -310.0], "b");
+        let cin = doc.add_gate(Gate::Input, wide(1), [0.0, -620.0], "cin");
+        let not = doc.add_gate(Gate::Not, wide(width), [170.0, -470.0], "");
+        let adder = doc.add_gate(Gate::Adder, wide(width), [430.0, -130.0], "");
+        let s = doc.add_gate(Gate::Output, wide(width), [790.0, 50.0], "s");
+        let cout = doc.add_gate(Gate::Output, wide(1), [790.0, -410.0], "cout");
+        let (x, y) = if swap { (b, a) } else { (a, b) };
+        doc.connect((x, 0), (adder, 0));
+        doc.connect((cin, 0), (adder, 2));
+        doc.connect((adder, 0), (s, 0));
+        // the adder's second output is its carry
+        doc.connect((adder, 1), (cout, 0));
+        if invert {
+            doc.connect((y, 0), (not, 0));
+            doc.connect((not, 0), (adder, 1));
+        } else {
+            doc.connect((y, 0), (adder, 1));
+        }
+        doc
+    }
+
+    fn wide(width: u8) -> Params {
+        Params {
+            width,
+            ..Default::default()
+        }
+    }
+
+    /// an input `a` of `width` bits that goes nowhere, and an output `y` that is left open or
+    /// driven by `driver`, which feeds itself. a not that way never settles, an and stays low
+    fn loose_output(width: u8, driver: Option<Gate>) -> CircuitDoc {
+        let mut doc = CircuitDoc::default();
+        doc.add_gate(Gate::Input, wide(width), [0.0, 0.0], "a");
+        let y = doc.add_gate(Gate::Output, wide(1), [400.0, 0.0], "y");
+        if let Some(gate) = driver {
+            let g = doc.add_gate(gate, wide(1), [200.0, -100.0], "");
+            doc.connect((g, 0), (g, 0));
+            doc.connect((g, 0), (y, 0));
+        }
+        doc
+    }
+
+    /// the value a counterexample gives `name`
+    fn input(c: &Counterexample, name: &str) -> u64 {
+        c.inputs.iter().find(|(n, _)| n == name).unwrap().1
+    }
+
+    fn output(c: &Counterexample, name: &str) -> (u64, u64) {
+        let (_, x, y) = c.outputs.iter().find(|(n, _, _)| n == name).unwrap();
+        (x.unwrap().bits, y.unwrap().bits)
+    }
+
+    #[test]
+    fn bdd_operations() {
+        let mut bdd = Bdd::default();
+        let (a, b) = (bdd.var(0).unwrap(), bdd.var(1).unwrap());
+        assert_eq!(bdd.var(0).unwrap(), a);
+        let not_a = bdd.not(a).unwrap();
+        assert_eq!(bdd.and(a, not_a).unwrap(), FALSE);
+        assert_eq!(bdd.or(a, not_a).unwrap(), TRUE);
+        assert_eq!(bdd.not(not_a).unwrap(), a);
+        // the same function built two ways is the same node
+        let x = bdd.xor(a, b).unwrap();
+        let both = bdd.and(a, b).unwrap();
+        let either = bdd.or(a, b).unwrap();
+        let not_both = bdd.not(both).unwrap();
+        assert_eq!(bdd.and(either, not_both).unwrap(), x);
+        assert_eq!(bdd.satisfy(FALSE), None);
+        assert_eq!(bdd.satisfy(TRUE), Some(vec![]));
+        assert_eq!(bdd.satisfy(both), Some(vec![0, 1]));
+        let not_b = bdd.not(b).unwrap();
+        let only_a = bdd.and(a, not_b).unwrap();
+        assert_eq!(bdd.satisfy(only_a), Some(vec![0]));
+    }
+
+    #[test]
+    fn bdd_stops_at_its_limit() {
+        // x0..x9 equal to y0..y9, with all the xs before the ys, needs a node per setting of xs
+        let mut bdd = Bdd {
+            limit: 1000,
+            ..Default::default()
+        };
+        let equal = (0..10).try_fold(TRUE, |acc, i| {
+            let (x, y) = (bdd.var(i)?, bdd.var(10 + i)?);
+            let differ = bdd.xor(x, y)?;
+            let same = bdd.not(differ)?;
+            bdd.and(acc, same)
+        });
+        assert!(equal.is_err());
+        assert!(bdd.nodes.len() <= 1000);
+        assert!(bdd.ites.len() <= 1000);
+    }
+
+    #[test]
+    fn inputs_take_turns() {
+        let column = |name: &str, width| Column {
+            name: name.to_string(),
+            width,
+        };
+        let vars = variable_order(&[column("a", 4), column("b", 4), column("cin", 1)]);
+        assert_eq!(vars["a"], vec![0, 3, 5, 7]);
+        assert_eq!(vars["b"], vec![1, 4, 6, 8]);
+        assert_eq!(vars["cin"], vec![2]);
+        let names = ["x1", "x0", "y0", "y1"].map(|n| column(n, 1));
+        let vars = variable_order(&names);
+        let order = ["x0", "y0", "x1", "y1"].map(|n| vars[n][0]);
+        assert_eq!(order, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn small_circuits_are_compared_on_every_input() {
+        let bits = 4;
+        assert!(2 * bits <= EXHAUSTIVE_BITS as usize);
+        let reference = ripple_adder(bits, GateSet::AndOrNot, false);
+        let same = ripple_adder(bits, GateSet::Nand, false);
+        assert_eq!(
+            check_equivalence(&reference, &same).unwrap(),
+            Verdict::Equivalent
+        );
+        let broken = ripple_adder(bits, GateSet::Nor, true);
+        let Verdict::Differ(c) = check_equivalence(&reference, &broken).unwrap() else {
+            panic!("a broken adder passes");
+        };
+        // only the carry can be wrong, when the last bits carry on a carry from below
+        assert_ne!(output(&c, "carry").0, output(&c, "carry").1);
+        let a = (0..bits).fold(0, |v, i| v | input(&c, &format!("a{i}")) << i);
+        let b = (0..bits).fold(0, |v, i| v | input(&c, &format!("b{i}")) << i);
+        assert_eq!(output(&c, "carry").0, (a + b) >> bits);
+    }
+
+    #[test]
+    fn big_circuits_are_compared_with_bdds() {
+        let bits = 16;
+        assert!(2 * bits > EXHAUSTIVE_BITS as usize);
+        let reference = ripple_adder(bits, GateSet::AndOrNot, false);
+        let same = ripple_adder(bits, GateSet::Nand, false);
+        assert_eq!(
+            check_equivalence(&reference, &same).unwrap(),
+            Verdict::Equivalent
+        );
+        let broken = ripple_adder(bits, GateSet::Nand, true);
+        let Verdict::Differ(c) = check_equivalence(&reference, &broken).unwrap() else {
+            panic!("a broken adder passes");
+        };
+        let (x, y) = output(&c, "carry");
+        assert_ne!(x, y);
+        let a = (0..bits).fold(0, |v, i| v | input(&c, &format!("a{i}")) << i);
+        let b = (0..bits).fold(0, |v, i| v | input(&c, &format!("b{i}")) << i);
+        assert_eq!(x, (a + b) >> bits);
+    }
+
+    #[test]
+    fn wide_adders() {
+        // the 4 bit adders have 9 input bits, few enough to try them all, the 32 bit ones need bdds
+        for width in [4, 32] {
+            let reference = adder_part(width, false, false);
+            assert_eq!(
+                check_equivalence(&reference, &adder_part(width, true, false)).unwrap(),
+                Verdict::Equivalent
+            );
+            let Verdict::Differ(c) =
+                check_equivalence(&reference, &adder_part(width, false, true)).unwrap()
+            else {
+                panic!("an adder of a and not b passes");
+            };
+            let (a, b, cin) = (input(&c, "a"), input(&c, "b"), input(&c, "cin"));
+            let sum = a + b + cin;
+            assert_eq!(output(&c, "s").0, sum & mask(width));
+            assert_eq!(output(&c, "cout").0, sum >> width);
+            let sum = a + (!b & mask(width)) + cin;
+            assert_eq!(output(&c, "s").1, sum & mask(width));
+            assert_eq!(output(&c, "cout").1, sum >> width);
+        }
+    }
+
+    #[test]
+    fn ports_must_match() {
+        let e = check_equivalence(&adder_part(4, false, false), &adder_part(8, false, false));
+        assert!(e.is_err());
+    }
+
+    #[test]
+    fn floating_outputs() {
+        // on every input, floating is a value of its own
+        let small = loose_output(4, None);
+        assert_eq!(
+            check_equivalence(&small, &small).unwrap(),
+            Verdict::Equivalent
+        );
+        let Verdict::Differ(c) =
+            check_equivalence(&small, &loose_output(4, Some(Gate::And))).unwrap()
+        else {
+            panic!("an open output is not low");
+        };
+        let (_, x, y) = &c.outputs[0];
+        assert!(x.unwrap().is_floating());
+        assert_eq!(y.unwrap(), Value::bit(false));
+        // while bdds do not have one for it
+        let big = loose_output(16, None);
+        let e = check_equivalence(&big, &big).unwrap_err();
+        assert!(e.to_string().contains("nothing drives"));
+    }
+
+    #[test]
+    fn unsettled_outputs() {
+        let circuit = loose_output(2, Some(Gate::Not));
+        let Verdict::Inconclusive(c) = check_equivalence(&circuit, &circuit).unwrap() else {
+            panic!("neither circuit settles");
+        };
+        assert_eq!(c.outputs, [("y".to_string(), None, None)]);
+        // settling in one of them is a difference
+        assert!(matches!(
+            check_equivalence(&circuit, &loose_output(2, Some(Gate::And))).unwrap(),
+            Verdict::Differ(_)
+        ));
+    }
+}
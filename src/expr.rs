@@ -0,0 +1,520 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use bevy::prelude::{App, Local, Plugin, Res, ResMut};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    pin::PinDir,
+    run::{GameState, Gate},
+    sim::{FlatCircuit, Simulation},
+};
+
+pub struct ExprPlugin;
+
+impl Plugin for ExprPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(expressions_ui)
+                .into(),
+        );
+    }
+}
+
+/// a boolean expression over named one bit inputs
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Expr {
+    Const(bool),
+    Var(String),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+/// the ways an [`Expr`] can be written down
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Notation {
+    /// `a·b + c'`
+    #[default]
+    Algebraic,
+    /// `a && b || !c`
+    C,
+    /// `a \cdot b + \overline{c}`
+    Latex,
+}
+
+impl Notation {
+    pub const ALL: [Notation; 3] = [Notation::Algebraic, Notation::C, Notation::Latex];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Notation::Algebraic => "algebraic",
+            Notation::C => "C",
+            Notation::Latex => "LaTeX",
+        }
+    }
+}
+
+impl std::ops::Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr::Not(Box::new(self))
+    }
+}
+
+impl Expr {
+    /// how tightly the expression binds, to tell where parentheses go
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(_) => 0,
+            Expr::And(_) => 1,
+            _ => 2,
+        }
+    }
+
+    pub fn to_string(&self, notation: Notation) -> String {
+        let mut s = String::new();
+        self.write(notation, &mut s);
+        s
+    }
+
+    fn write(&self, notation: Notation, s: &mut String) {
+        let child = |e: &Expr, s: &mut String| {
+            if e.precedence() < self.precedence() {
+                s.push('(');
+                e.write(notation, s);
+                s.push(')');
+            } else {
+                e.write(notation, s);
+            }
+        };
+        let join = |es: &[Expr], sep: &str, s: &mut String| {
+            for (i, e) in es.iter().enumerate() {
+                if i > 0 {
+                    s.push_str(sep);
+                }
+                child(e, s);
+            }
+        };
+        match (self, notation) {
+            (Expr::Const(b), _) => s.push(if *b { '1' } else { '0' }),
+            (Expr::Var(name), Notation::Latex) => s.push_str(&name.replace('_', "\\_")),
+            (Expr::Var(name), _) => s.push_str(name),
+            (Expr::Not(e), Notation::Algebraic) => {
+                // a' for a name, (a + b)' for anything bigger
+                match **e {
+                    Expr::Var(_) | Expr::Const(_) | Expr::Not(_) => e.write(notation, s),
+                    _ => {
+                        s.push('(');
+                        e.write(notation, s);
+                        s.push(')');
+                    }
+                }
+                s.push('\'');
+            }
+            (Expr::Not(e), Notation::C) => {
+                s.push('!');
+                child(e, s);
+            }
+            (Expr::Not(e), Notation::Latex) => {
+                s.push_str("\\overline{");
+                e.write(notation, s);
+                s.push('}');
+            }
+            (Expr::And(es), Notation::Algebraic) => join(es, "·", s),
+            (Expr::And(es), Notation::C) => join(es, " && ", s),
+            (Expr::And(es), Notation::Latex) => join(es, " \\cdot ", s),
+            (Expr::Or(es), Notation::C) => join(es, " || ", s),
+            (Expr::Or(es), _) => join(es, " + ", s),
+        }
+    }
+
+    /// the same function written shorter: constants fold away, nested ands and ors flatten,
+    /// double negations cancel, repeated terms go, `a·a'` is 0, `a + a'` is 1, and `a + a·b` is `a`
+    pub fn simplify(&self) -> Expr {
+        match self {
+            Expr::Const(_) | Expr::Var(_) => self.clone(),
+            Expr::Not(e) => match e.simplify() {
+                Expr::Const(b) => Expr::Const(!b),
+                Expr::Not(e) => *e,
+                e => !e,
+            },
+            Expr::And(es) => simplify_terms(es, true),
+            Expr::Or(es) => simplify_terms(es, false),
+        }
+    }
+}
+
+/// simplifies the terms of an and, or of an or when `and` is false
+fn simplify_terms(es: &[Expr], and: bool) -> Expr {
+    let same = |e: &Expr| match e {
+        Expr::And(es) if and => Some(es.clone()),
+        Expr::Or(es) if !and => Some(es.clone()),
+        _ => None,
+    };
+    let mut terms = vec![];
+    for e in es.iter().map(Expr::simplify) {
+        match same(&e) {
+            Some(inner) => terms.extend(inner),
+            None => terms.push(e),
+        }
+    }
+    // false decides an and, true an or
+    if terms.contains(&Expr::Const(!and)) {
+        return Expr::Const(!and);
+    }
+    terms.retain(|e| *e != Expr::Const(and));
+    terms.sort();
+    terms.dedup();
+    if terms.iter().any(|e| terms.contains(&negate(e))) {
+        return Expr::Const(!and);
+    }
+    // absorption: a term of the other kind that has one of our terms in it adds nothing
+    let absorbed = |e: &Expr| match (e, and) {
+        (Expr::Or(inner), true) | (Expr::And(inner), false) => {
+            inner.iter().any(|i| terms.contains(i))
+        }
+        _ => false,
+    };
+    let kept = terms
+        .iter()
+        .filter(|e| !absorbed(e))
+        .cloned()
+        .collect::<Vec<_>>();
+    match kept.len() {
+        0 => Expr::Const(and),
+        1 => kept.into_iter().next().unwrap_or(Expr::Const(and)),
+        _ if and => Expr::And(kept),
+        _ => Expr::Or(kept),
+    }
+}
+
+/// `!e` simplified, for an `e` that already is. simplifying it again would go over the whole
+/// expression once more at every level it is nested in
+fn negate(e: &Expr) -> Expr {
+    match e {
+        Expr::Const(b) => Expr::Const(!b),
+        Expr::Not(e) => (**e).clone(),
+        e => !e.clone(),
+    }
+}
+
+/// an expression with more than this many variables, constants and operators is not worked out.
+/// a net read twice on the way to an output appears twice in its expression, so the size can
+/// double with every level of such gates
+const MAX_EXPR_SIZE: usize = 10_000;
+
+/// the expression of every top level output port, worked out from the gates that drive it.
+/// only one bit and, or, not, nand and nor gates between the input ports and the outputs can be followed
+pub fn output_exprs(circuit: &FlatCircuit) -> Vec<(String, Result<Expr>)> {
+    let mut exprs = NetExprs {
+        circuit,
+        drivers: circuit.drivers(),
+        nets: HashMap::new(),
+        visiting: vec![],
+    };
+    circuit
+        .outputs
+        .iter()
+        .map(|(name, part)| {
+            let expr = match circuit.parts[*part].pins.first() {
+                Some(&net) => exprs.net(net).map(|(e, _)| e),
+                None => Err(anyhow::anyhow!("the port has no pin")),
+            };
+            exprs.visiting.clear();
+            (name.clone(), expr)
+        })
+        .collect()
+}
+
+/// the expressions of the nets of a circuit, each worked out once
+struct NetExprs<'a> {
+    circuit: &'a FlatCircuit,
+    drivers: HashMap<usize, Vec<usize>>,
+    /// the expression of each net done so far, with its size
+    nets: HashMap<usize, (Expr, usize)>,
+    visiting: Vec<usize>,
+}
+
+impl NetExprs<'_> {
+    fn net(&mut self, net: usize) -> Result<(Expr, usize)> {
+        if let Some(done) = self.nets.get(&net) {
+            return Ok(done.clone());
+        }
+        let circuit = self.circuit;
+        let part = match self.drivers.get(&net).map(|d| d.as_slice()) {
+            Some(&[part]) => part,
+            Some([]) | None => bail!("a net is not driven by anything"),
+            Some(_) => bail!("a net is driven by more than one part"),
+        };
+        if self.visiting.contains(&part) {
+            bail!("the circuit has a feedback loop");
+        }
+        let p = &circuit.parts[part];
+        if p.params.width != 1 {
+            bail!("a {} is {} bits wide", p.gate.title(), p.params.width);
+        }
+        self.visiting.push(part);
+        let mut inputs = vec![];
+        let mut size = 1;
+        for (&n, _) in p
+            .pins
+            .iter()
+            .zip(p.dirs.iter())
+            .filter(|(_, &d)| d == PinDir::In)
+        {
+            let (e, s) = self.net(n)?;
+            size += s;
+            if size > MAX_EXPR_SIZE {
+                bail!("the expression is too large, over {MAX_EXPR_SIZE} terms");
+            }
+            inputs.push(e);
+        }
+        let expr = match p.gate {
+            Gate::Input => match circuit.inputs.iter().find(|(_, i)| *i == part) {
+                Some((name, _)) => Expr::Var(name.clone()),
+                None => bail!("an input port is not on the top level"),
+            },
+            Gate::And => Expr::And(inputs),
+            Gate::Or => Expr::Or(inputs),
+            Gate::Nand => !Expr::And(inputs),
+            Gate::Nor => !Expr::Or(inputs),
+            Gate::Not => match inputs.pop() {
+                Some(e) => !e,
+                None => bail!("a not gate has no input"),
+            },
+            g => bail!("a {} has no boolean expression", g.title()),
+        };
+        // a nand or nor is a not over an and or or
+        let size = size + matches!(p.gate, Gate::Nand | Gate::Nor) as usize;
+        self.visiting.pop();
+        self.nets.insert(net, (expr.clone(), size));
+        Ok((expr, size))
+    }
+}
+
+#[derive(Default)]
+struct ExpressionsWindow {
+    notation: Notation,
+    simplify: bool,
+    circuit: FlatCircuit,
+    exprs: Vec<(String, Result<Expr>)>,
+}
+
+/// the expression of every output of the simulated circuit
+fn expressions_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<ExpressionsWindow>,
+    simulation: Res<Simulation>,
+) {
+    let window = &mut *window;
+    if window.circuit != simulation.sim.circuit {
+        window.circuit = simulation.sim.circuit.clone();
+        window.exprs = output_exprs(&window.circuit);
+    }
+    egui::Window::new("expressions").show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("notation")
+                .selected_text(window.notation.name())
+                .show_ui(ui, |ui| {
+                    for n in Notation::ALL {
+                        ui.selectable_value(&mut window.notation, n, n.name());
+                    }
+                });
+            ui.checkbox(&mut window.simplify, "simplify");
+        });
+        if window.exprs.is_empty() {
+            ui.label("the circuit has no output ports");
+        }
+        for (name, expr) in window.exprs.iter() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{name} ="));
+                match expr {
+                    Ok(e) => {
+                        let e = if window.simplify {
+                            e.simplify()
+                        } else {
+                            e.clone()
+                        };
+                        // read only, but the text can be selected and copied
+                        ui.text_edit_singleline(&mut e.to_string(window.notation).as_str());
+                    }
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::YELLOW, e.to_string());
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{params::Params, save::CircuitDoc, sim::FlatPart, subcircuit::Library};
+
+    fn var(name: &str) -> Expr {
+        Expr::Var(name.to_string())
+    }
+
+    /// the value of `e` with the variables in `high` set
+    fn value(e: &Expr, high: &[&str]) -> bool {
+        match e {
+            Expr::Const(b) => *b,
+            Expr::Var(name) => high.contains(&name.as_str()),
+            Expr::Not(e) => !value(e, high),
+            Expr::And(es) => es.iter().all(|e| value(e, high)),
+            Expr::Or(es) => es.iter().any(|e| value(e, high)),
+        }
+    }
+
+    #[test]
+    fn notations() {
+        let e = Expr::Or(vec![
+            Expr::And(vec![var("a"), !var("b")]),
+            !Expr::Or(vec![var("c"), var("d_1")]),
+        ]);
+        assert_eq!(e.to_string(Notation::Algebraic), "a·b' + (c + d_1)'");
+        assert_eq!(e.to_string(Notation::C), "a && !b || !(c || d_1)");
+        assert_eq!(
+            e.to_string(Notation::Latex),
+            "a \\cdot \\overline{b} + \\overline{c + d\\_1}"
+        );
+        let e = Expr::And(vec![Expr::Or(vec![var("a"), var("b")]), !!var("c")]);
+        assert_eq!(e.to_string(Notation::Algebraic), "(a + b)·c''");
+        assert_eq!(e.to_string(Notation::C), "(a || b) && !!c");
+    }
+
+    #[test]
+    fn simplify_rules() {
+        let (a, b) = (var("a"), var("b"));
+        let simplified = |e: Expr| e.simplify();
+        assert_eq!(simplified(Expr::And(vec![a.clone(), Expr::Const(true)])), a);
+        assert_eq!(
+            simplified(Expr::And(vec![a.clone(), Expr::Const(false)])),
+            Expr::Const(false)
+        );
+        assert_eq!(
+            simplified(Expr::Or(vec![a.clone(), !a.clone()])),
+            Expr::Const(true)
+        );
+        assert_eq!(
+            simplified(Expr::And(vec![!a.clone(), a.clone()])),
+            Expr::Const(false)
+        );
+        assert_eq!(simplified(!!a.clone()), a);
+        assert_eq!(simplified(!Expr::Const(false)), Expr::Const(true));
+        assert_eq!(
+            simplified(Expr::Or(vec![
+                a.clone(),
+                Expr::And(vec![a.clone(), b.clone()])
+            ])),
+            a
+        );
+        assert_eq!(
+            simplified(Expr::And(vec![
+                b.clone(),
+                Expr::And(vec![a.clone(), b.clone()])
+            ])),
+            Expr::And(vec![a.clone(), b.clone()])
+        );
+    }
+
+    #[test]
+    fn deep_expressions_simplify_quickly() {
+        // the carry out of a 40 bit ripple adder nests 40 levels deep
+        let carry = (0..40).fold(Expr::Const(false), |carry, i| {
+            let (a, b) = (var(&format!("a{i}")), var(&format!("b{i}")));
+            Expr::Or(vec![
+                Expr::And(vec![a.clone(), b.clone()]),
+                Expr::And(vec![Expr::Or(vec![a, b]), carry]),
+            ])
+        });
+        let simplified = carry.simplify();
+        for high in [&["a0", "b0"][..], &["a38", "b39"], &["a39"], &[]] {
+            assert_eq!(value(&simplified, high), value(&carry, high));
+        }
+        let either = Expr::Or(vec![var("a"), var("b")]);
+        assert_eq!(
+            Expr::And(vec![either.clone(), !either]).simplify(),
+            Expr::Const(false)
+        );
+    }
+
+    #[test]
+    fn half_adder_outputs() {
+        let doc = CircuitDoc::read("tests/circuits/half_adder.ron".as_ref()).unwrap();
+        let circuit = FlatCircuit::flatten(&doc, &Library::default());
+        let exprs = output_exprs(&circuit)
+            .into_iter()
+            .map(|(name, e)| (name, e.unwrap()))
+            .collect::<HashMap<_, _>>();
+        for high in [&[][..], &["a"], &["b"], &["a", "b"]] {
+            assert_eq!(value(&exprs["s"], high), high.len() == 1, "s {high:?}");
+            assert_eq!(value(&exprs["c"], high), high.len() == 2, "c {high:?}");
+        }
+    }
+
+    /// a chain of `stages` xors made of ands, ors and nots, each reading the one before twice:
+    /// `x` goes to `x·b0' + x'·b0`, which goes on to meet `b1`, and so on. the output is `y`
+    fn xor_chain(stages: usize) -> FlatCircuit {
+        let mut circuit = FlatCircuit::default();
+        let mut nets = 0;
+        let mut net = || {
+            nets += 1;
+            nets - 1
+        };
+        let add = |circuit: &mut FlatCircuit, gate: Gate, ins: &[usize], out: Option<usize>| {
+            circuit.parts.push(FlatPart {
+                gate,
+                params: Params::default(),
+                memory: vec![],
+                pins: ins.iter().chain(out.iter()).copied().collect(),
+                dirs: ins
+                    .iter()
+                    .map(|_| PinDir::In)
+                    .chain(out.iter().map(|_| PinDir::Out))
+                    .collect(),
+                scope: 0,
+                gate_index: circuit.parts.len(),
+            });
+            circuit.parts.len() - 1
+        };
+        let mut x = net();
+        let part = add(&mut circuit, Gate::Input, &[], Some(x));
+        circuit.inputs.push(("x".to_string(), part));
+        for i in 0..stages {
+            let (b, not_x, not_b, left, right, y) = (net(), net(), net(), net(), net(), net());
+            let part = add(&mut circuit, Gate::Input, &[], Some(b));
+            circuit.inputs.push((format!("b{i}"), part));
+            add(&mut circuit, Gate::Not, &[x], Some(not_x));
+            add(&mut circuit, Gate::Not, &[b], Some(not_b));
+            add(&mut circuit, Gate::And, &[x, not_b], Some(left));
+            add(&mut circuit, Gate::And, &[not_x, b], Some(right));
+            add(&mut circuit, Gate::Or, &[left, right], Some(y));
+            x = y;
+        }
+        let part = add(&mut circuit, Gate::Output, &[x], None);
+        circuit.outputs.push(("y".to_string(), part));
+        circuit.net_count = nets;
+        circuit
+    }
+
+    #[test]
+    fn shared_nets_are_worked_out_once() {
+        // 2^8 copies of x, well under the limit
+        let exprs = output_exprs(&xor_chain(8));
+        let y = exprs[0].1.as_ref().unwrap();
+        for high in [&["x"][..], &["b0", "b7"], &["x", "b3", "b5"], &[]] {
+            assert_eq!(value(y, high), high.len() % 2 == 1, "{high:?}");
+        }
+        // written out, 60 levels would hold 2^60 copies of x
+        let exprs = output_exprs(&xor_chain(60));
+        let e = exprs[0].1.as_ref().unwrap_err().to_string();
+        assert!(e.contains("too large"), "{e}");
+    }
+}
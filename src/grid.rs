@@ -0,0 +1,160 @@
+use bevy::{
+    prelude::{
+        App, Camera, Commands, Component, Handle, Image, Input, KeyCode, Local,
+        OrthographicProjection, Plugin, Query, Res, ResMut, Resource, Transform, Vec2, Vec3,
+        Visibility, With, Without,
+    },
+    sprite::{Sprite, SpriteBundle},
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::{AppLooplessStateExt, ConditionSet};
+
+use crate::run::GameState;
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GridSettings::default())
+            .add_enter_system(GameState::Playing, spawn_grid)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(draw_grid)
+                    .with_system(grid_settings_ui)
+                    .into(),
+            );
+    }
+}
+
+/// the one place the snapping step lives. everything that places or routes on the canvas reads it from here
+#[derive(Resource, Clone, Debug)]
+pub struct GridSettings {
+    pub pitch: f32,
+    pub show_dots: bool,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            pitch: 5.0,
+            show_dots: true,
+        }
+    }
+}
+
+impl GridSettings {
+    pub fn snap(&self, p: Vec2) -> Vec2 {
+        (p / self.pitch).round() * self.pitch
+    }
+
+    /// snaps unless alt is held, for placing things off the grid
+    pub fn snap_placement(&self, p: Vec2, keys: &Input<KeyCode>) -> Vec2 {
+        if keys.any_pressed([KeyCode::LAlt, KeyCode::RAlt]) {
+            p
+        } else {
+            self.snap(p)
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct GridDots;
+
+/// dots are this many pixels apart in the grid image
+const CELL_PIXELS: u32 = 5;
+/// when zoomed out, only every other dot is drawn until there are at most this many across the screen
+const MAX_DOTS_ACROSS: f32 = 150.0;
+
+fn spawn_grid(mut c: Commands) {
+    c.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(0.0, 0.0, -0.05),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        GridDots,
+    ));
+}
+
+/// the grid lives in world space, so it is only redrawn when the zoom level
+/// or the settings change. panning just moves the sprite along in whole steps
+#[allow(clippy::type_complexity)]
+fn draw_grid(
+    settings: Res<GridSettings>,
+    mut image_store: ResMut<bevy::prelude::Assets<Image>>,
+    q_camera: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    mut grid: Query<
+        (
+            &mut Transform,
+            &mut Handle<Image>,
+            &mut Sprite,
+            &mut Visibility,
+        ),
+        (With<GridDots>, Without<Camera>),
+    >,
+    mut last: Local<Option<(f32, u32, u32)>>,
+) {
+    let (cam, proj) = q_camera.single();
+    let (mut t, mut texture, mut sprite, mut visibility) = grid.single_mut();
+
+    if visibility.is_visible != settings.show_dots {
+        visibility.is_visible = settings.show_dots;
+    }
+    if !settings.show_dots {
+        return;
+    }
+
+    let visible = Vec2::new(proj.right - proj.left, proj.top - proj.bottom) * proj.scale;
+    let mut step = settings.pitch;
+    while visible.x / step > MAX_DOTS_ACROSS {
+        step *= 2.0;
+    }
+    let nx = (visible.x / step).ceil() as u32 + 2;
+    let ny = (visible.y / step).ceil() as u32 + 2;
+
+    if *last != Some((step, nx, ny)) {
+        *last = Some((step, nx, ny));
+
+        let mut img = image::Rgba32FImage::new(nx * CELL_PIXELS, ny * CELL_PIXELS);
+        for x in 0..nx {
+            for y in 0..ny {
+                img.put_pixel(
+                    x * CELL_PIXELS + CELL_PIXELS / 2,
+                    y * CELL_PIXELS + CELL_PIXELS / 2,
+                    image::Rgba([0.35, 0.42, 0.35, 1.]),
+                );
+            }
+        }
+        let img = Image::from_dynamic(img.into(), true);
+        *texture = image_store.add(img);
+        sprite.custom_size = Some(Vec2::new(nx as f32 * step, ny as f32 * step));
+    }
+
+    // each dot sits in the middle of its cell, so the image starts half a step before the first grid point
+    let first = ((cam.translation.truncate() - visible / 2.0) / step).floor() * step;
+    let size = Vec2::new(nx as f32, ny as f32) * step;
+    let centre = first - Vec2::splat(step / 2.0) + size / 2.0;
+    t.translation = centre.extend(t.translation.z);
+}
+
+fn grid_settings_ui(mut egui_context: ResMut<EguiContext>, mut settings: ResMut<GridSettings>) {
+    let mut pitch = settings.pitch;
+    let mut show_dots = settings.show_dots;
+    egui::Window::new("grid").show(egui_context.ctx_mut(), |ui| {
+        ui.add(
+            egui::Slider::new(&mut pitch, 1.0..=50.0)
+                .step_by(1.0)
+                .text("pitch"),
+        );
+        ui.checkbox(&mut show_dots, "show dots");
+        ui.label("hold alt to place without snapping");
+    });
+    // only touch the resource when something changed, so it does not show up as changed every frame
+    if pitch != settings.pitch || show_dots != settings.show_dots {
+        settings.pitch = pitch;
+        settings.show_dots = show_dots;
+    }
+}
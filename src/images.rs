@@ -0,0 +1,100 @@
+use bevy::{
+    prelude::{App, Assets, Handle, HandleUntyped, Image, Plugin, ResMut},
+    reflect::TypeUuid,
+};
+
+pub struct PartImagesPlugin;
+
+impl Plugin for PartImagesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(draw_part_images);
+    }
+}
+
+/// sprites drawn in code instead of loaded from files. the handles are fixed,
+/// so parts can point at them before the images exist
+pub const TUNNEL_IMAGE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Image::TYPE_UUID, 0x7a3c_51e0_94b2_d61f);
+pub const INPUT_IMAGE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Image::TYPE_UUID, 0x7a3c_51e0_94b2_d620);
+pub const OUTPUT_IMAGE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Image::TYPE_UUID, 0x7a3c_51e0_94b2_d621);
+pub const NAND_IMAGE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Image::TYPE_UUID, 0x7a3c_51e0_94b2_d622);
+pub const NOR_IMAGE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Image::TYPE_UUID, 0x7a3c_51e0_94b2_d623);
+/// boxes of every height get the ids after this one
+const BOX_IMAGE_BASE: u64 = 0x7a3c_51e0_94b3_0000;
+/// taller boxes are drawn stretched
+pub const MAX_BOX_ROWS: usize = 64;
+
+/// the sprite of a [`crate::pin::BoxLayout`] with this many rows of pins
+pub fn box_image(rows: usize) -> Handle<Image> {
+    let rows = rows.clamp(1, MAX_BOX_ROWS) as u64;
+    HandleUntyped::weak_from_u64(Image::TYPE_UUID, BOX_IMAGE_BASE + rows).typed()
+}
+
+/// same colours as the sprites in assets/sprites
+const LIGHT: [f32; 4] = [0.67, 0.77, 0.58, 1.0];
+const DARK: [f32; 4] = [0.24, 0.24, 0.24, 1.0];
+const BODY: [f32; 4] = [0.58, 0.62, 0.62, 1.0];
+const BUBBLE: [f32; 4] = [0.96, 0.5, 0.44, 1.0];
+
+/// one char per pixel: '#' light, '+' dark, 'o' the grey of gate bodies, '*' the red of the
+/// bubble on a not gate, anything else transparent
+pub fn pixel_art(rows: &[&str]) -> Image {
+    let w = rows.iter().map(|r| r.len()).max().unwrap_or(0) as u32;
+    let h = rows.len() as u32;
+    let mut img = image::Rgba32FImage::new(w, h);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, ch) in row.chars().enumerate() {
+            let color = match ch {
+                '#' => LIGHT,
+                '+' => DARK,
+                'o' => BODY,
+                '*' => BUBBLE,
+                _ => continue,
+            };
+            img.put_pixel(x as _, y as _, image::Rgba(color));
+        }
+    }
+    Image::from_dynamic(img.into(), true)
+}
+
+fn draw_part_images(mut images: ResMut<Assets<Image>>) {
+    // the point on the left is where the tunnel connects
+    images.set_untracked(TUNNEL_IMAGE, pixel_art(&[".####", "#+++#", ".####"]));
+    // the and and or gate sprites with the bubble of the not gate
+    images.set_untracked(
+        NAND_IMAGE,
+        pixel_art(&[
+            "+++++++++++",
+            "+++++++++++",
+            "+++ooo+++++",
+            "###oooo++++",
+            "+++ooooo+++",
+            "+++ooooo*##",
+            "+++ooooo+++",
+            "###oooo++++",
+            "+++ooo+++++",
+            "+++++++++++",
+            "+++++++++++",
+        ]),
+    );
+    images.set_untracked(
+        NOR_IMAGE,
+        pixel_art(&[
+            "+++++++++++",
+            "+++++++++++",
+            "++ooo++++++",
+            "###oooo++++",
+            "+++ooooo+++",
+            "++++ooooo*#",
+            "+++ooooo+++",
+            "###oooo++++",
+            "++ooo++++++",
+            "+++++++++++",
+            "+++++++++++",
+        ]),
+    );
+}
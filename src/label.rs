@@ -0,0 +1,299 @@
+use bevy::{
+    prelude::{
+        App, ChangeTrackers, Color, Commands, Component, DespawnRecursiveExt, Entity, EventReader,
+        Input, KeyCode, MouseButton, Plugin, Query, Res, ResMut, Resource, Transform, Vec2, With,
+        Without,
+    },
+    sprite::Sprite,
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+    window::ReceivedCharacter,
+};
+use bevy_inspector_egui::bevy_egui::EguiContext;
+use bevy_rapier2d::prelude::{Collider, QueryFilter, RapierContext};
+use iyes_loopless::prelude::{AppLooplessStateExt, ConditionSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    grid::GridSettings,
+    run::{Assets, CanvasCursor, GameState, Gate, Wire, WireNode, WireSprite},
+    tool::Tool,
+};
+
+pub struct LabelPlugin;
+
+impl Plugin for LabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EditingLabel(None))
+            .insert_resource(EguiTyping(false))
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(track_egui_typing)
+                    .with_system(follow_targets)
+                    .with_system(sync_label_text)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_in_state(Tool::Label)
+                    .with_system(label_clicked)
+                    .with_system(type_into_label)
+                    .into(),
+            )
+            .add_exit_system(Tool::Label, finish_editing);
+    }
+}
+
+/// a piece of text on the canvas. on a wire it also names the net the wire belongs to
+#[derive(Component, Clone, Debug)]
+pub struct Label {
+    pub text: String,
+}
+
+/// keeps a label next to a gate or a wire.
+/// for wires the offset is from the wire's first node, as the wire sprite can be rebuilt
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Attached {
+    pub to: Entity,
+    pub offset: Vec2,
+}
+
+/// what a saved label was attached to, as an index into the gates or wires of the document
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelOn {
+    Free,
+    Gate(usize),
+    Wire(usize),
+}
+
+/// the label that is currently receiving typed characters
+#[derive(Resource)]
+pub struct EditingLabel(pub Option<Entity>);
+
+/// whether a text field in one of the egui windows has the keyboard
+#[derive(Resource)]
+pub struct EguiTyping(pub bool);
+
+/// keyboard shortcuts on the canvas are off while text is being typed somewhere
+pub fn keyboard_free(editing: Res<EditingLabel>, typing: Res<EguiTyping>) -> bool {
+    editing.0.is_none() && !typing.0
+}
+
+fn track_egui_typing(mut egui_context: ResMut<EguiContext>, mut typing: ResMut<EguiTyping>) {
+    let wants = egui_context.ctx_mut().wants_keyboard_input();
+    if typing.0 != wants {
+        typing.0 = wants;
+    }
+}
+
+const FONT_SIZE: f32 = 10.0;
+
+/// new labels on a gate go just above it
+pub fn gate_label_offset(size: Vec2) -> Vec2 {
+    Vec2::new(0.0, size.y / 2.0 + 7.5)
+}
+
+fn label_collider(text: &str) -> Collider {
+    // a rough box around the text, good enough to click on it
+    let width = (text.chars().count().max(1) as f32) * FONT_SIZE * 0.3;
+    Collider::cuboid(width, FONT_SIZE * 0.6)
+}
+
+pub fn spawn_label(
+    c: &mut Commands,
+    assets: &Assets,
+    text: String,
+    pos: Vec2,
+    attached: Option<Attached>,
+) -> Entity {
+    let mut e = c.spawn((
+        Text2dBundle {
+            text: Text {
+                alignment: TextAlignment::CENTER,
+                ..Text::from_section(
+                    text.clone(),
+                    TextStyle {
+                        font: assets.font.clone(),
+                        font_size: FONT_SIZE,
+                        color: Color::rgb(0.9, 0.85, 0.7),
+                    },
+                )
+            },
+            transform: Transform::from_translation(pos.extend(1.0)),
+            ..Default::default()
+        },
+        label_collider(&text),
+        Label { text },
+    ));
+    if let Some(attached) = attached {
+        e.insert(attached);
+    }
+    e.id()
+}
+
+/// where the thing a label is attached to sits on the canvas, none if it is gone
+pub fn anchor_pos(
+    to: Entity,
+    gates: &Query<&Transform, (With<Gate>, Without<Label>)>,
+    wires: &Query<&Wire>,
+    nodes: &Query<&Transform, (With<WireNode>, Without<Label>)>,
+) -> Option<Vec2> {
+    if let Ok(t) = gates.get(to) {
+        return Some(t.translation.truncate());
+    }
+    let first = *wires.get(to).ok()?.nodes.first()?;
+    nodes.get(first).ok().map(|t| t.translation.truncate())
+}
+
+fn follow_targets(
+    mut c: Commands,
+    mut labels: Query<(Entity, &Attached, &mut Transform), With<Label>>,
+    gates: Query<&Transform, (With<Gate>, Without<Label>)>,
+    wires: Query<&Wire>,
+    nodes: Query<&Transform, (With<WireNode>, Without<Label>)>,
+) {
+    for (e, a, mut t) in labels.iter_mut() {
+        match anchor_pos(a.to, &gates, &wires, &nodes) {
+            Some(p) => {
+                let p = (p + a.offset).extend(t.translation.z);
+                if t.translation != p {
+                    t.translation = p;
+                }
+            }
+            None => c.entity(e).despawn_recursive(),
+        }
+    }
+}
+
+fn sync_label_text(
+    mut c: Commands,
+    editing: Res<EditingLabel>,
+    mut labels: Query<(Entity, &Label, ChangeTrackers<Label>, &mut Text)>,
+) {
+    for (e, label, tracker, mut text) in labels.iter_mut() {
+        // the cursor mark comes and goes when editing starts or stops
+        if !tracker.is_changed() && !editing.is_changed() {
+            continue;
+        }
+        let shown = if editing.0 == Some(e) {
+            format!("{}_", label.text)
+        } else {
+            label.text.clone()
+        };
+        text.sections[0].value = shown;
+        c.entity(e).insert(label_collider(&label.text));
+    }
+}
+
+/// labels left empty are not worth keeping
+fn stop_editing(c: &mut Commands, editing: &mut EditingLabel, labels: &Query<&Label>) {
+    if let Some(e) = editing.0.take() {
+        if labels.get(e).map(|l| l.text.is_empty()).unwrap_or(false) {
+            c.entity(e).despawn_recursive();
+        }
+    }
+}
+
+fn finish_editing(mut c: Commands, mut editing: ResMut<EditingLabel>, labels: Query<&Label>) {
+    stop_editing(&mut c, &mut editing, &labels);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn label_clicked(
+    mut c: Commands,
+    mou: Res<Input<MouseButton>>,
+    rapier_context: Res<RapierContext>,
+    cursor: CanvasCursor,
+    assets: Res<Assets>,
+    grid: Res<GridSettings>,
+    mut editing: ResMut<EditingLabel>,
+    labels: Query<&Label>,
+    gates: Query<&Transform, (With<Gate>, Without<Label>)>,
+    gate_sprites: Query<&Sprite, With<Gate>>,
+    wire_sprites: Query<&WireSprite>,
+    wires: Query<&Wire>,
+    nodes: Query<&Transform, (With<WireNode>, Without<Label>)>,
+) {
+    if !mou.just_pressed(MouseButton::Left) || cursor.over_ui() {
+        return;
+    }
+    let world_pos = match cursor.world_pos() {
+        Some(p) => p,
+        None => return,
+    };
+
+    stop_editing(&mut c, &mut editing, &labels);
+
+    let mut hits = vec![];
+    rapier_context.intersections_with_point(world_pos, QueryFilter::default(), |e| {
+        hits.push(e);
+        true
+    });
+
+    // clicking a label edits it, labels sit on top of what they are attached to
+    if let Some(&e) = hits.iter().find(|&&e| labels.get(e).is_ok()) {
+        editing.0 = Some(e);
+        return;
+    }
+
+    let attached = hits.iter().find_map(|&e| {
+        if let Ok(sprite) = gate_sprites.get(e) {
+            Some(Attached {
+                to: e,
+                offset: gate_label_offset(sprite.custom_size.unwrap_or_default()),
+            })
+        } else {
+            let wire = wire_sprites.get(e).ok()?.wire;
+            let anchor = anchor_pos(wire, &gates, &wires, &nodes)?;
+            Some(Attached {
+                to: wire,
+                offset: grid.snap(world_pos) - anchor,
+            })
+        }
+    });
+    let pos = match attached {
+        Some(a) => anchor_pos(a.to, &gates, &wires, &nodes).unwrap_or_default() + a.offset,
+        None => grid.snap(world_pos),
+    };
+    editing.0 = Some(spawn_label(&mut c, &assets, String::new(), pos, attached));
+}
+
+fn type_into_label(
+    mut c: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut editing: ResMut<EditingLabel>,
+    mut labels: Query<&mut Label>,
+) {
+    let e = match editing.0 {
+        Some(e) => e,
+        None => {
+            chars.clear();
+            return;
+        }
+    };
+    let mut label = match labels.get_mut(e) {
+        Ok(l) => l,
+        Err(_) => {
+            editing.0 = None;
+            return;
+        }
+    };
+
+    for ch in chars.iter() {
+        if !ch.char.is_control() {
+            label.text.push(ch.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        label.text.pop();
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        let empty = label.text.is_empty();
+        editing.0 = None;
+        if empty {
+            c.entity(e).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::{AddAsset, App, Handle, Plugin, Res, ResMut, Resource},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    netlist::CircuitNets,
+    run::{Assets, GameState},
+    save::CircuitDoc,
+    subcircuit::Library,
+    view::View,
+};
+
+pub struct LibraryPlugin;
+
+impl Plugin for LibraryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ComponentLibrary>()
+            .init_asset_loader::<ComponentLibraryLoader>()
+            .insert_resource(LibraryFiles {
+                import: "library.gatelib".to_string(),
+                export: ComponentLibrary {
+                    name: "library".to_string(),
+                    version: 1,
+                    defs: BTreeMap::new(),
+                },
+                opened: vec![],
+            })
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(library_ui)
+                    .into(),
+            );
+    }
+}
+
+/// subcircuit definitions kept in a file of their own, to be imported into any circuit.
+/// the ones listed in [`Assets`] from `assets/libraries` are loaded along with the other assets
+#[derive(Serialize, Deserialize, TypeUuid, Clone, Debug, PartialEq)]
+#[uuid = "5b0f7c52-3d0e-4a8e-9a3b-6f1c2d7e9a41"]
+pub struct ComponentLibrary {
+    pub name: String,
+    /// goes up with every release, so circuits using an older one can be updated
+    pub version: u32,
+    pub defs: BTreeMap<String, CircuitDoc>,
+}
+
+impl ComponentLibrary {
+    pub fn read(path: &Path) -> Result<Self> {
+        let s = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&s)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, s)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ComponentLibraryLoader;
+
+impl AssetLoader for ComponentLibraryLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let lib: ComponentLibrary = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(lib));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gatelib"]
+    }
+}
+
+/// what the library window reads from and writes to
+#[derive(Resource)]
+pub struct LibraryFiles {
+    pub import: String,
+    /// the name and version the definitions get exported under, and where to
+    pub export: ComponentLibrary,
+    /// libraries imported from a file, kept so they stay listed
+    pub opened: Vec<Handle<ComponentLibrary>>,
+}
+
+fn library_ui(
+    mut egui_context: ResMut<EguiContext>,
+    assets: Res<Assets>,
+    mut libs: ResMut<bevy::prelude::Assets<ComponentLibrary>>,
+    mut library: ResMut<Library>,
+    mut files: ResMut<LibraryFiles>,
+    view: Res<View>,
+    nets: Res<CircuitNets>,
+) {
+    let mut import = None;
+    let mut read = None;
+    let mut export = None;
+    egui::Window::new("component libraries").show(egui_context.ctx_mut(), |ui| {
+        let bundled = assets
+            .libraries
+            .iter()
+            .map(|h| h.id())
+            .collect::<HashSet<_>>();
+        let mut known = libs.iter().collect::<Vec<_>>();
+        known.sort_by(|(_, a), (_, b)| (&a.name, a.version).cmp(&(&b.name, b.version)));
+        for (id, lib) in known {
+            ui.horizontal(|ui| {
+                let from = if bundled.contains(&id) {
+                    ""
+                } else {
+                    ", from a file"
+                };
+                ui.label(format!(
+                    "{} v{}, {} definitions{from}",
+                    lib.name,
+                    lib.version,
+                    lib.defs.len()
+                ));
+                match library.imported.get(&lib.name) {
+                    None => {
+                        if ui.small_button("import").clicked() {
+                            import = Some(lib.clone());
+                        }
+                    }
+                    Some(i) if i.version < lib.version => {
+                        ui.label(format!("v{} in use", i.version));
+                        if ui.small_button("update").clicked() {
+                            import = Some(lib.clone());
+                        }
+                    }
+                    Some(i) if i.version > lib.version => {
+                        ui.label(format!("v{} in use", i.version));
+                    }
+                    Some(_) => {
+                        ui.label("in use");
+                    }
+                }
+            });
+        }
+        for (name, i) in library.imported.iter() {
+            let available = libs.iter().any(|(_, lib)| lib.name == *name);
+            if !available {
+                ui.label(format!(
+                    "{name} v{} in use, library file not found",
+                    i.version
+                ));
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut files.import);
+            if ui.button("import file").clicked() {
+                read = Some(PathBuf::from(&files.import));
+            }
+        });
+
+        ui.separator();
+        let LibraryFiles { export: lib, .. } = &mut *files;
+        ui.horizontal(|ui| {
+            ui.label("name");
+            ui.text_edit_singleline(&mut lib.name);
+            ui.add(egui::DragValue::new(&mut lib.version).prefix("v"));
+        });
+        ui.label(format!(
+            "exports every definition in the circuit to {}.gatelib",
+            lib.name
+        ));
+        if ui
+            .add_enabled(!library.defs.is_empty(), egui::Button::new("export"))
+            .clicked()
+        {
+            export = Some(ComponentLibrary {
+                defs: library.defs.clone(),
+                ..lib.clone()
+            });
+        }
+    });
+
+    if let Some(path) = read {
+        match ComponentLibrary::read(&path) {
+            Ok(lib) => {
+                import = Some(lib.clone());
+                // listed from now on, so later versions of the file can be told apart
+                files.opened.push(libs.add(lib));
+            }
+            Err(e) => bevy::prelude::error!("could not read component library: {e:?}"),
+        }
+    }
+    if let Some(lib) = import {
+        let placed = view
+            .levels
+            .iter()
+            .map(|l| &l.doc)
+            .chain([&nets.doc])
+            .collect::<Vec<_>>();
+        library.import(&lib, &placed);
+    }
+    if let Some(lib) = export {
+        let path = PathBuf::from(format!("{}.gatelib", lib.name));
+        match lib.write(&path) {
+            Ok(()) => bevy::prelude::info!("exported {} v{} to {path:?}", lib.name, lib.version),
+            Err(e) => bevy::prelude::error!("could not export component library: {e:?}"),
+        }
+    }
+}
@@ -1,4 +1,34 @@
+pub mod arith;
+pub mod bus;
+pub mod counter;
+pub mod drc;
+pub mod equiv;
+pub mod expr;
+pub mod grid;
+pub mod images;
+pub mod label;
+pub mod library;
+pub mod memory;
+pub mod minimize;
+pub mod netlist;
+pub mod orientation;
+pub mod params;
+pub mod pin;
+pub mod plexer;
 pub mod run;
+pub mod save;
+pub mod sim;
+pub mod stats;
+pub mod subcircuit;
+pub mod synth;
+pub mod timing;
+pub mod tool;
+pub mod truth;
+pub mod tunnel;
+pub mod vcd;
+pub mod vectors;
+pub mod view;
+pub mod wave;
 
 use bevy::prelude::App;
 
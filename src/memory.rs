@@ -0,0 +1,405 @@
+use std::{ops::RangeInclusive, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::{
+    App, Commands, Component, Entity, Local, Plugin, Query, Res, ResMut, With, Without,
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    netlist::CircuitNets,
+    params::Params,
+    run::{GameState, Gate, Selected, UnPlaced},
+    sim::{mask, CanvasScope, PartState, Simulation, Value},
+};
+
+pub struct MemoryPlugin;
+
+impl Plugin for MemoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(hex_editor_ui)
+                .into(),
+        );
+    }
+}
+
+/// the memory parts, in palette order
+pub const PARTS: [Gate; 2] = [Gate::Ram, Gate::Rom];
+
+/// address lines a memory can have
+pub const ADDRESS_BITS: RangeInclusive<u8> = 1..=16;
+
+/// the contents of a rom, or what a ram holds when the simulation starts. words past the end are zero
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Memory(pub Vec<u64>);
+
+pub fn pin_names(g: Gate) -> Option<(&'static [&'static str], &'static [&'static str])> {
+    Some(match g {
+        Gate::Ram => (&["a", "d", "we", "clk"], &["q"]),
+        Gate::Rom => (&["a"], &["q"]),
+        _ => return None,
+    })
+}
+
+/// the words a memory part starts the simulation with
+pub fn initial_contents(params: &Params, contents: &[u64]) -> Vec<u64> {
+    let mut words = contents
+        .iter()
+        .map(|w| w & mask(params.width))
+        .collect::<Vec<_>>();
+    words.resize(1 << params.address, 0);
+    words
+}
+
+/// a ram writes `d` on the rising edge of `clk` while `we` is high. both read all the time
+pub fn eval(g: Gate, params: &Params, inputs: &[Value], state: &mut PartState) -> Vec<Value> {
+    let input = |i: usize| inputs.get(i).copied().unwrap_or_default();
+    let address = (input(0).bits & mask(params.address)) as usize;
+    if g == Gate::Ram {
+        let clk = input(3).is_high();
+        if clk && !state.clock && input(2).is_high() {
+            if let Some(word) = state.memory.get_mut(address) {
+                *word = input(1).bits & mask(params.width);
+            }
+        }
+        state.clock = clk;
+    }
+    let word = state.memory.get(address).copied().unwrap_or(0);
+    vec![Value::new(word, params.width)]
+}
+
+/// reads at most `size` words of memory contents from a file. intel hex is recognised by its
+/// leading colon, `.bin` files are raw bytes, anything else is hex words separated by white space
+pub fn read_contents(path: &Path, width: u8, size: usize) -> Result<Vec<u64>> {
+    let bytes = std::fs::read(path)?;
+    let is_bin = path.extension().is_some_and(|e| e == "bin");
+    let mut words = match std::str::from_utf8(&bytes) {
+        Ok(text) if !is_bin && text.trim_start().starts_with(':') => {
+            let len = size * bytes_per_word(width);
+            words_from_bytes(&parse_intel_hex(text, len)?, width)
+        }
+        Ok(text) if !is_bin => parse_hex_words(text, size)?,
+        _ => words_from_bytes(&bytes, width),
+    };
+    words.truncate(size);
+    Ok(words)
+}
+
+fn bytes_per_word(width: u8) -> usize {
+    (width as usize).div_ceil(8).max(1)
+}
+
+/// little endian words of as many bytes as `width` needs
+pub fn words_from_bytes(bytes: &[u8], width: u8) -> Vec<u64> {
+    bytes
+        .chunks(bytes_per_word(width))
+        .map(|chunk| chunk.iter().rev().fold(0, |word, &b| word << 8 | b as u64) & mask(width))
+        .collect()
+}
+
+/// the first `len` bytes of an intel hex file, from address zero up. gaps are zero,
+/// and data past `len` is left out
+pub fn parse_intel_hex(text: &str, len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut base = 0usize;
+    for (n, line) in text.lines().map(str::trim).enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let record = line
+            .strip_prefix(':')
+            .ok_or_else(|| anyhow!("line {}: no leading colon", n + 1))?;
+        let digits = record
+            .chars()
+            .map(|c| {
+                c.to_digit(16)
+                    .ok_or_else(|| anyhow!("line {}: {c:?} is not a hex digit", n + 1))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if digits.len() % 2 != 0 {
+            bail!("line {}: an odd number of hex digits", n + 1);
+        }
+        let record = digits
+            .chunks(2)
+            .map(|pair| (pair[0] << 4 | pair[1]) as u8)
+            .collect::<Vec<_>>();
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            bail!("line {}: wrong record length", n + 1);
+        }
+        if record.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) != 0 {
+            bail!("line {}: bad checksum", n + 1);
+        }
+        let data = &record[4..record.len() - 1];
+        let offset = (record[1] as usize) << 8 | record[2] as usize;
+        match record[3] {
+            0 => {
+                let at = base + offset;
+                let end = (at + data.len()).min(len);
+                if at < end {
+                    if bytes.len() < end {
+                        bytes.resize(end, 0);
+                    }
+                    bytes[at..end].copy_from_slice(&data[..end - at]);
+                }
+            }
+            1 => break,
+            2 if data.len() == 2 => base = ((data[0] as usize) << 8 | data[1] as usize) << 4,
+            4 if data.len() == 2 => base = ((data[0] as usize) << 8 | data[1] as usize) << 16,
+            // start addresses mean nothing to a memory
+            3 | 5 => {}
+            t => bail!("line {}: unknown record type {t}", n + 1),
+        }
+    }
+    Ok(bytes)
+}
+
+/// hex words separated by white space. `4*ff` stands for four words of ff, `#` starts a comment,
+/// and a `v2.0 raw` header line is skipped. words past the first `size` are left out
+pub fn parse_hex_words(text: &str, size: usize) -> Result<Vec<u64>> {
+    let mut words = vec![];
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line == "v2.0 raw" {
+            continue;
+        }
+        for token in line.split_whitespace() {
+            let (count, word) = match token.split_once('*') {
+                Some((count, word)) => (count.parse::<usize>()?, word),
+                None => (1, token),
+            };
+            let word = word.trim_start_matches("0x");
+            let word = u64::from_str_radix(word, 16).map_err(|e| anyhow!("{token:?}: {e}"))?;
+            words.extend(std::iter::repeat_n(word, count.min(size - words.len())));
+        }
+    }
+    Ok(words)
+}
+
+const ROW_WORDS: usize = 8;
+const PAGE_ROWS: usize = 16;
+
+#[derive(Default)]
+struct HexEditor {
+    page: usize,
+    cursor: usize,
+    text: String,
+    file: String,
+    /// show what the simulation holds instead of the stored contents
+    live: bool,
+}
+
+/// the contents of the selected memory, a page of words at a time
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn hex_editor_ui(
+    mut c: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut editor: Local<HexEditor>,
+    simulation: Res<Simulation>,
+    nets: Res<CircuitNets>,
+    canvas_scope: Res<CanvasScope>,
+    mut selected: Query<
+        (Entity, &Gate, &Params, Option<&mut Memory>),
+        (With<Selected>, Without<UnPlaced>),
+    >,
+) {
+    let (e, g, params, memory) = match selected.get_single_mut() {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    if !PARTS.contains(g) {
+        return;
+    }
+    let size = 1usize << params.address;
+    let digits = (params.width as usize).div_ceil(4);
+    let stored = memory.as_ref().map(|m| m.0.clone()).unwrap_or_default();
+    let live = canvas_scope
+        .0
+        .and_then(|scope| {
+            let gi = nets.gates.iter().position(|&g| g == e)?;
+            let part = (*simulation.sim.circuit.scopes.get(scope)?.parts.get(gi)?)?;
+            Some(simulation.sim.states.get(part)?.memory.clone())
+        })
+        .filter(|_| editor.live && *g == Gate::Ram);
+    let words = live.as_ref().unwrap_or(&stored);
+
+    let mut write = None;
+    let mut load = None;
+    let mut clear = false;
+    egui::Window::new("memory").show(egui_context.ctx_mut(), |ui| {
+        let editor = &mut *editor;
+        ui.label(format!("{} words of {} bits", size, params.width));
+        if *g == Gate::Ram {
+            ui.checkbox(&mut editor.live, "show the simulation's contents");
+        }
+        let pages = size.div_ceil(ROW_WORDS * PAGE_ROWS);
+        editor.page = editor.page.min(pages - 1);
+        if pages > 1 {
+            ui.add(egui::Slider::new(&mut editor.page, 0..=pages - 1).text("page"));
+        }
+        egui::Grid::new("hex").show(ui, |ui| {
+            let first = editor.page * ROW_WORDS * PAGE_ROWS;
+            for row in (first..size.min(first + ROW_WORDS * PAGE_ROWS)).step_by(ROW_WORDS) {
+                ui.monospace(format!("{row:04x}"));
+                for a in row..size.min(row + ROW_WORDS) {
+                    let word = words.get(a).copied().unwrap_or(0);
+                    let text = format!("{word:0digits$x}");
+                    if ui
+                        .selectable_label(a == editor.cursor, egui::RichText::new(text).monospace())
+                        .clicked()
+                    {
+                        editor.cursor = a;
+                        editor.text = format!("{word:x}");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        if live.is_none() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{:04x}", editor.cursor));
+                let edit = ui.text_edit_singleline(&mut editor.text);
+                let entered = edit.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+                if entered || ui.button("set").clicked() {
+                    match u64::from_str_radix(editor.text.trim(), 16) {
+                        Ok(word) => write = Some((editor.cursor, word)),
+                        Err(_) => editor.text.clear(),
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut editor.file);
+                if ui.button("load file").clicked() {
+                    load = Some(editor.file.clone());
+                }
+                clear = ui.button("clear").clicked();
+            });
+            ui.label("binary (.bin), intel hex or hex words");
+        }
+    });
+
+    let mut contents = stored;
+    if let Some((a, word)) = write {
+        if contents.len() <= a {
+            contents.resize(a + 1, 0);
+        }
+        contents[a] = word & mask(params.width);
+    }
+    if let Some(file) = load {
+        match read_contents(Path::new(&file), params.width, size) {
+            Ok(words) => contents = words,
+            Err(e) => bevy::prelude::error!("could not load memory contents: {e:?}"),
+        }
+    }
+    if clear {
+        contents.clear();
+    }
+    // trailing zeros need not be stored
+    while contents.last() == Some(&0) {
+        contents.pop();
+    }
+    match memory {
+        Some(mut m) if m.0 != contents => m.0 = contents,
+        None if !contents.is_empty() => {
+            c.entity(e).insert(Memory(contents));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// an intel hex record with its checksum
+    fn record(kind: u8, offset: u16, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8, (offset >> 8) as u8, offset as u8, kind];
+        bytes.extend_from_slice(data);
+        let sum = bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        bytes.push(sum.wrapping_neg());
+        let hex = bytes.iter().map(|b| format!("{b:02X}")).collect::<String>();
+        format!(":{hex}\n")
+    }
+
+    const EOF: &str = ":00000001FF\n";
+
+    #[test]
+    fn intel_hex_data_and_gaps() {
+        let text = record(0, 0, &[1, 2]) + &record(0, 4, &[5]) + EOF;
+        assert_eq!(parse_intel_hex(&text, 16).unwrap(), vec![1, 2, 0, 0, 5]);
+        // nothing after the end of file record counts
+        let text = text + &record(0, 8, &[9]);
+        assert_eq!(parse_intel_hex(&text, 16).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn intel_hex_checksum() {
+        let mut text = record(0, 0, &[1, 2, 3]);
+        text.replace_range(text.len() - 3.., "00\n");
+        let e = parse_intel_hex(&text, 16).unwrap_err().to_string();
+        assert!(e.contains("bad checksum"), "{e}");
+        assert!(parse_intel_hex(":0100000001\n", 16).is_err());
+        assert!(parse_intel_hex("0000000001FF\n", 16).is_err());
+    }
+
+    #[test]
+    fn intel_hex_digits() {
+        // no panic on characters of more than one byte, wherever they are
+        for text in [":10€0000001FF\n", ":0000€0001FF\n", ":€\n", ":00000001F€\n"] {
+            let e = parse_intel_hex(text, 16).unwrap_err().to_string();
+            assert!(e.contains("'€' is not a hex digit"), "{e}");
+        }
+        let e = parse_intel_hex(":+1000001FF\n", 16)
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("'+' is not a hex digit"), "{e}");
+        // a record cut in the middle of a byte
+        let mut text = record(0, 0, &[1, 2]);
+        text.insert(text.len() - 1, 'F');
+        let e = parse_intel_hex(&text, 16).unwrap_err().to_string();
+        assert!(e.contains("odd number of hex digits"), "{e}");
+        assert!(parse_intel_hex(":00000001F\n", 16).is_err());
+    }
+
+    #[test]
+    fn intel_hex_bases() {
+        // a segment base is the value times 16, a linear base the value times 65536
+        let text = record(2, 0, &[0, 1]) + &record(0, 2, &[7]) + EOF;
+        assert_eq!(parse_intel_hex(&text, 32).unwrap()[18], 7);
+        let text = record(4, 0, &[0, 1]) + &record(0, 3, &[8]) + EOF;
+        assert_eq!(parse_intel_hex(&text, 1 << 17).unwrap()[0x10003], 8);
+    }
+
+    #[test]
+    fn intel_hex_past_the_end_is_left_out() {
+        let text = record(0, 2, &[1, 2, 3, 4]) + EOF;
+        assert_eq!(parse_intel_hex(&text, 4).unwrap(), vec![0, 0, 1, 2]);
+        // an image for a microcontroller's flash, far above a small memory
+        let text = record(4, 0, &[0x08, 0]) + &record(0, 0, &[1, 2]) + EOF;
+        assert_eq!(parse_intel_hex(&text, 256).unwrap(), Vec::<u8>::new());
+        let text = ":02000004FFFFFC\n".to_string() + &record(0, 0xfff0, &[1]) + EOF;
+        assert_eq!(parse_intel_hex(&text, 256).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn hex_words_repeat_up_to_the_size() {
+        let text = "v2.0 raw\n1 2 # two words\n3*ff 0x10";
+        assert_eq!(
+            parse_hex_words(text, 16).unwrap(),
+            vec![1, 2, 0xff, 0xff, 0xff, 0x10]
+        );
+        assert_eq!(parse_hex_words("1000000000*ff", 4).unwrap(), vec![0xff; 4]);
+    }
+
+    #[test]
+    fn words_are_little_endian() {
+        assert_eq!(
+            words_from_bytes(&[0x34, 0x12, 0x78], 16),
+            vec![0x1234, 0x78]
+        );
+        assert_eq!(words_from_bytes(&[0xff, 0x01], 4), vec![0xf, 0x1]);
+    }
+}
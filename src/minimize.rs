@@ -0,0 +1,834 @@
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Result};
+use bevy::prelude::{App, Local, Plugin, Res, ResMut};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    expr::{Expr, Notation},
+    run::GameState,
+    sim::{Sim, Simulation},
+    truth::{port_columns, TruthTable},
+};
+
+pub struct MinimizePlugin;
+
+impl Plugin for MinimizePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(minimize_ui)
+                .into(),
+        );
+    }
+}
+
+/// the most inputs a function can have here
+pub const MAX_VARS: usize = 8;
+/// the most inputs a karnaugh map is drawn for
+pub const MAX_MAP_VARS: usize = 6;
+
+/// what a function gives for one combination of its inputs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cell {
+    #[default]
+    Zero,
+    One,
+    /// either will do
+    DontCare,
+}
+
+impl Cell {
+    /// what a click on a karnaugh map cell turns it into
+    fn next(self) -> Cell {
+        match self {
+            Cell::Zero => Cell::One,
+            Cell::One => Cell::DontCare,
+            Cell::DontCare => Cell::Zero,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Cell::Zero => "0",
+            Cell::One => "1",
+            Cell::DontCare => "X",
+        }
+    }
+}
+
+/// a product of literals, or a sum of them in a product of sums. bit `i` of a minterm is
+/// variable `n - 1 - i`, so the first variable is the most significant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Implicant {
+    pub bits: u32,
+    /// the variables left out
+    pub free: u32,
+}
+
+impl Implicant {
+    pub fn covers(&self, minterm: u32) -> bool {
+        minterm & !self.free == self.bits
+    }
+
+    pub fn literals(&self, n: usize) -> usize {
+        n - (self.free & mask(n)).count_ones() as usize
+    }
+
+    /// the literals of the product, a literal being the variable and whether it is true
+    fn literal_list(&self, n: usize) -> impl Iterator<Item = (usize, bool)> + '_ {
+        (0..n)
+            .map(move |i| (i, 1 << (n - 1 - i)))
+            .filter(|(_, bit)| self.free & bit == 0)
+            .map(|(i, bit)| (i, self.bits & bit != 0))
+    }
+}
+
+fn mask(n: usize) -> u32 {
+    ((1u64 << n) - 1) as u32
+}
+
+/// every implicant of the function true on `terms` that cannot be made any bigger
+pub fn prime_implicants(terms: &[u32]) -> Vec<Implicant> {
+    let mut current = terms
+        .iter()
+        .map(|&bits| Implicant { bits, free: 0 })
+        .collect::<BTreeSet<_>>();
+    let mut primes = BTreeSet::new();
+    while !current.is_empty() {
+        let mut next = BTreeSet::new();
+        let mut merged = BTreeSet::new();
+        let list = current.iter().collect::<Vec<_>>();
+        for (i, a) in list.iter().enumerate() {
+            for b in list[i + 1..].iter() {
+                let differ = a.bits ^ b.bits;
+                if a.free == b.free && differ.count_ones() == 1 {
+                    next.insert(Implicant {
+                        bits: a.bits & b.bits,
+                        free: a.free | differ,
+                    });
+                    merged.insert(**a);
+                    merged.insert(**b);
+                }
+            }
+        }
+        primes.extend(current.difference(&merged).copied());
+        current = next;
+    }
+    primes.into_iter().collect()
+}
+
+/// a set of the `ones` handed to [`minimal_cover`], one bit for each by its index
+type Bits = Vec<u64>;
+
+fn is_empty(a: &Bits) -> bool {
+    a.iter().all(|&w| w == 0)
+}
+
+fn is_subset(a: &Bits, b: &Bits) -> bool {
+    a.iter().zip(b.iter()).all(|(x, y)| x & !y == 0)
+}
+
+fn has(a: &Bits, i: usize) -> bool {
+    a[i / 64] >> (i % 64) & 1 == 1
+}
+
+fn count_in(a: &Bits, left: &Bits) -> u32 {
+    a.iter()
+        .zip(left.iter())
+        .map(|(x, y)| (x & y).count_ones())
+        .sum()
+}
+
+/// how many choices the search for the smallest cover makes before it settles for the best so far
+const COVER_STEPS: usize = 20_000;
+
+/// the fewest of `primes` that cover every one of `ones`, and of those the ones with the fewest
+/// literals, with whether it is sure to be the smallest. primes that alone cover a minterm are
+/// taken, primes that cover no more than a prime as short and minterms that come along with
+/// another one are left out, and what remains is searched starting from a greedy cover
+pub fn minimal_cover(n: usize, primes: &[Implicant], ones: &[u32]) -> (Vec<Implicant>, bool) {
+    let words = ones.len().div_ceil(64);
+    let bits = |p: &Implicant| {
+        let mut b = vec![0u64; words];
+        for (i, &m) in ones.iter().enumerate() {
+            if p.covers(m) {
+                b[i / 64] |= 1 << (i % 64);
+            }
+        }
+        b
+    };
+    let mut rows = primes.iter().map(|p| (*p, bits(p))).collect::<Vec<_>>();
+    // an implicant with every variable free covers them all
+    let mut left = bits(&Implicant {
+        bits: 0,
+        free: u32::MAX,
+    });
+    let mut chosen = vec![];
+    loop {
+        for (_, b) in rows.iter_mut() {
+            b.iter_mut().zip(left.iter()).for_each(|(x, y)| *x &= y);
+        }
+        rows.retain(|(_, b)| !is_empty(b));
+        let minterms = (0..ones.len())
+            .filter(|&i| has(&left, i))
+            .collect::<Vec<_>>();
+        let columns = minterms
+            .iter()
+            .map(|&i| {
+                let mut c = vec![0u64; rows.len().div_ceil(64)];
+                for (r, (_, b)) in rows.iter().enumerate() {
+                    if has(b, i) {
+                        c[r / 64] |= 1 << (r % 64);
+                    }
+                }
+                c
+            })
+            .collect::<Vec<_>>();
+
+        let essential = columns
+            .iter()
+            .position(|c| c.iter().map(|w| w.count_ones()).sum::<u32>() == 1);
+        if let Some(k) = essential {
+            let r = (0..rows.len()).find(|&r| has(&columns[k], r)).unwrap_or(0);
+            let (p, b) = rows.remove(r);
+            left.iter_mut().zip(b.iter()).for_each(|(x, y)| *x &= !y);
+            chosen.push(p);
+            continue;
+        }
+        let dominated_row = (0..rows.len()).find(|&r| {
+            (0..rows.len()).any(|o| {
+                o != r
+                    && rows[o].0.literals(n) <= rows[r].0.literals(n)
+                    && is_subset(&rows[r].1, &rows[o].1)
+            })
+        });
+        if let Some(r) = dominated_row {
+            rows.remove(r);
+            continue;
+        }
+        // whatever covers the other minterm covers this one too
+        let dominated_column = (0..columns.len())
+            .find(|&k| (0..columns.len()).any(|o| o != k && is_subset(&columns[o], &columns[k])));
+        if let Some(k) = dominated_column {
+            let i = minterms[k];
+            left[i / 64] &= !(1 << (i % 64));
+            continue;
+        }
+        break;
+    }
+    if is_empty(&left) {
+        chosen.sort();
+        return (chosen, true);
+    }
+
+    // what remains covers every minterm at least twice over, and is searched
+    fn cost(n: usize, rows: &[(Implicant, Bits)], cover: &[usize]) -> (usize, usize) {
+        let literals = cover.iter().map(|&r| rows[r].0.literals(n)).sum::<usize>();
+        (cover.len(), literals)
+    }
+    fn search(
+        n: usize,
+        rows: &[(Implicant, Bits)],
+        covering: &[Vec<usize>],
+        left: &Bits,
+        chosen: &mut Vec<usize>,
+        best: &mut Vec<usize>,
+        steps: &mut usize,
+    ) {
+        let (terms, literals) = cost(n, rows, chosen);
+        let hardest = (0..covering.len())
+            .filter(|&i| has(left, i))
+            .min_by_key(|&i| covering[i].len());
+        let i = match hardest {
+            Some(i) => i,
+            None => {
+                if (terms, literals) < cost(n, rows, best) {
+                    *best = chosen.clone();
+                }
+                return;
+            }
+        };
+        // at least one more term is needed
+        if (terms + 1, literals) >= cost(n, rows, best) || *steps >= COVER_STEPS {
+            return;
+        }
+        *steps += 1;
+        for &r in covering[i].iter() {
+            let rest = left
+                .iter()
+                .zip(rows[r].1.iter())
+                .map(|(x, y)| x & !y)
+                .collect();
+            chosen.push(r);
+            search(n, rows, covering, &rest, chosen, best, steps);
+            chosen.pop();
+        }
+    }
+
+    let covering = (0..ones.len())
+        .map(|i| (0..rows.len()).filter(|&r| has(&rows[r].1, i)).collect())
+        .collect::<Vec<Vec<_>>>();
+    // the prime covering the most of what is left each time, to bound the search
+    let mut best = vec![];
+    let mut rest = left.clone();
+    while let Some(r) = (0..rows.len())
+        .filter(|&r| count_in(&rows[r].1, &rest) > 0)
+        .max_by_key(|&r| {
+            let literals = std::cmp::Reverse(rows[r].0.literals(n));
+            (count_in(&rows[r].1, &rest), literals)
+        })
+    {
+        rest.iter_mut()
+            .zip(rows[r].1.iter())
+            .for_each(|(x, y)| *x &= !y);
+        best.push(r);
+    }
+    let mut steps = 0;
+    search(
+        n,
+        &rows,
+        &covering,
+        &left,
+        &mut vec![],
+        &mut best,
+        &mut steps,
+    );
+    chosen.extend(best.iter().map(|&r| rows[r].0));
+    chosen.sort();
+    (chosen, steps < COVER_STEPS)
+}
+
+/// a function made as small as it gets, both ways round
+#[derive(Clone, Debug, PartialEq)]
+pub struct Minimized {
+    /// the products of the sum of products
+    pub products: Vec<Implicant>,
+    /// the sums of the product of sums. they cover the zeros of the function
+    pub sums: Vec<Implicant>,
+    pub sop: Expr,
+    pub pos: Expr,
+    /// false when the search gave up and a smaller one might exist
+    pub exact: bool,
+}
+
+/// minimizes the function of `names` whose value for minterm `m` is `cells[m]`
+pub fn minimize(names: &[String], cells: &[Cell]) -> Minimized {
+    let n = names.len();
+    let minterms = |c: Cell| {
+        (0..cells.len() as u32)
+            .filter(|&m| cells[m as usize] == c)
+            .collect::<Vec<_>>()
+    };
+    let (ones, zeros, dont_care) = (
+        minterms(Cell::One),
+        minterms(Cell::Zero),
+        minterms(Cell::DontCare),
+    );
+    let cover = |terms: &[u32]| {
+        let with_dont_care = terms
+            .iter()
+            .chain(dont_care.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        minimal_cover(n, &prime_implicants(&with_dont_care), terms)
+    };
+    let (products, exact_products) = cover(&ones);
+    let (sums, exact_sums) = cover(&zeros);
+
+    let var = |i: usize, on: bool| {
+        let v = Expr::Var(names[i].clone());
+        if on {
+            v
+        } else {
+            !v
+        }
+    };
+    let join = |mut es: Vec<Expr>, and: bool| match es.len() {
+        0 => Expr::Const(and),
+        1 => es.remove(0),
+        _ if and => Expr::And(es),
+        _ => Expr::Or(es),
+    };
+    let sop = join(
+        products
+            .iter()
+            .map(|p| join(p.literal_list(n).map(|(i, on)| var(i, on)).collect(), true))
+            .collect(),
+        false,
+    );
+    // a sum is false exactly on the zeros it covers, so its literals are the other way round
+    let pos = join(
+        sums.iter()
+            .map(|s| {
+                join(
+                    s.literal_list(n).map(|(i, on)| var(i, !on)).collect(),
+                    false,
+                )
+            })
+            .collect(),
+        true,
+    );
+    Minimized {
+        products,
+        sums,
+        sop,
+        pos,
+        exact: exact_products && exact_sums,
+    }
+}
+
+/// the function an output of a circuit computes, over all its inputs
+pub fn circuit_function(sim: &Sim, output: &str) -> Result<(Vec<String>, Vec<Cell>)> {
+    let (inputs, outputs) = port_columns(&sim.circuit);
+    let out = match outputs.iter().find(|c| c.name == output) {
+        Some(out) => out.clone(),
+        None => bail!("no output {output:?}"),
+    };
+    if out.width != 1 || inputs.iter().any(|c| c.width != 1) {
+        bail!("only circuits with one bit ports can be minimized");
+    }
+    if inputs.len() > MAX_VARS {
+        bail!(
+            "{} inputs, at most {MAX_VARS} can be minimized",
+            inputs.len()
+        );
+    }
+    let table = TruthTable::build(sim, &inputs, &[out]);
+    let cells = table
+        .rows
+        .iter()
+        .map(|row| match row.outputs[0] {
+            Some(v) if v.is_high() => Cell::One,
+            Some(_) => Cell::Zero,
+            // a circuit that does not settle could be either
+            None => Cell::DontCare,
+        })
+        .collect();
+    Ok((inputs.into_iter().map(|c| c.name).collect(), cells))
+}
+
+/// the bits of `i` in gray code, so neighbouring rows and columns differ in one variable
+fn gray(i: usize) -> usize {
+    i ^ (i >> 1)
+}
+
+/// how the variables of a karnaugh map are split. a map is drawn for every value of the first
+/// `maps` variables, with `rows` variables down the side and `cols` along the top
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct MapShape {
+    maps: usize,
+    rows: usize,
+    cols: usize,
+}
+
+impl MapShape {
+    fn new(n: usize) -> Self {
+        let maps = n.saturating_sub(4);
+        let m = n - maps;
+        Self {
+            maps,
+            rows: m / 2,
+            cols: m - m / 2,
+        }
+    }
+
+    fn minterm(&self, map: usize, row: usize, col: usize) -> usize {
+        (map << (self.rows + self.cols)) | (gray(row) << self.cols) | gray(col)
+    }
+
+    /// the runs of neighbouring rows, or columns, that `p` covers in one map,
+    /// with the wrap round the edge split in two
+    fn runs(&self, p: &Implicant, map: usize, along_rows: bool) -> Vec<(usize, usize)> {
+        let count = 1 << if along_rows { self.rows } else { self.cols };
+        let inside = |i: usize| {
+            // any minterm in the line will do, with the other axis free
+            (0..1 << if along_rows { self.cols } else { self.rows }).any(|j| {
+                let (r, c) = if along_rows { (i, j) } else { (j, i) };
+                p.covers(self.minterm(map, r, c) as u32)
+            })
+        };
+        let mut runs = vec![];
+        let mut start = None;
+        for i in 0..=count {
+            match (i < count && inside(i), start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    runs.push((s, i));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        runs
+    }
+}
+
+const GROUP_COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(230, 90, 80),
+    egui::Color32::from_rgb(90, 160, 230),
+    egui::Color32::from_rgb(110, 200, 110),
+    egui::Color32::from_rgb(230, 180, 60),
+    egui::Color32::from_rgb(190, 110, 220),
+    egui::Color32::from_rgb(80, 210, 200),
+];
+
+const CELL: f32 = 28.0;
+
+/// draws the maps of the function, with `groups` ringed. returns the minterm clicked on
+fn karnaugh_map(
+    ui: &mut egui::Ui,
+    names: &[String],
+    cells: &[Cell],
+    groups: &[Implicant],
+) -> Option<usize> {
+    let shape = MapShape::new(names.len());
+    let (rows, cols) = (1 << shape.rows, 1 << shape.cols);
+    let label = |i: usize, bits: usize| match bits {
+        0 => String::new(),
+        _ => format!("{:0w$b}", gray(i), w = bits),
+    };
+    let row_vars = &names[shape.maps..shape.maps + shape.rows];
+    let col_vars = &names[shape.maps + shape.rows..];
+    let mut clicked = None;
+    ui.horizontal_wrapped(|ui| {
+        for map in 0..1 << shape.maps {
+            ui.vertical(|ui| {
+                if shape.maps > 0 {
+                    ui.label(format!(
+                        "{} = {:0w$b}",
+                        names[..shape.maps].join(""),
+                        map,
+                        w = shape.maps
+                    ));
+                }
+                ui.label(format!("{} \\ {}", row_vars.join(""), col_vars.join("")));
+                let size = egui::vec2(CELL * (cols + 1) as f32, CELL * (rows + 1) as f32);
+                let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+                let painter = ui.painter_at(rect);
+                let cell_rect = |r: usize, c: usize| {
+                    egui::Rect::from_min_size(
+                        rect.min + egui::vec2(CELL * (c + 1) as f32, CELL * (r + 1) as f32),
+                        egui::vec2(CELL, CELL),
+                    )
+                };
+                let text = |pos: egui::Pos2, s: String, color: egui::Color32| {
+                    painter.text(
+                        pos,
+                        egui::Align2::CENTER_CENTER,
+                        s,
+                        egui::FontId::monospace(12.0),
+                        color,
+                    );
+                };
+                for c in 0..cols {
+                    let r = cell_rect(0, c).translate(egui::vec2(0.0, -CELL));
+                    text(r.center(), label(c, shape.cols), egui::Color32::GRAY);
+                }
+                for r in 0..rows {
+                    let rr = cell_rect(r, 0).translate(egui::vec2(-CELL, 0.0));
+                    text(rr.center(), label(r, shape.rows), egui::Color32::GRAY);
+                    for c in 0..cols {
+                        let m = shape.minterm(map, r, c);
+                        let cr = cell_rect(r, c);
+                        painter.rect_stroke(
+                            cr,
+                            0.0,
+                            egui::Stroke::new(1.0, egui::Color32::DARK_GRAY),
+                        );
+                        text(
+                            cr.center(),
+                            cells[m].symbol().to_string(),
+                            egui::Color32::WHITE,
+                        );
+                    }
+                }
+                for (k, g) in groups.iter().enumerate() {
+                    let color = GROUP_COLORS[k % GROUP_COLORS.len()];
+                    // groups are inset by different amounts so overlapping ones stay apart
+                    let inset = 2.0 + (k % 4) as f32 * 2.0;
+                    for &(r0, r1) in shape.runs(g, map, true).iter() {
+                        for &(c0, c1) in shape.runs(g, map, false).iter() {
+                            let r = cell_rect(r0, c0).union(cell_rect(r1 - 1, c1 - 1));
+                            painter.rect_stroke(
+                                r.shrink(inset),
+                                6.0,
+                                egui::Stroke::new(2.0, color),
+                            );
+                        }
+                    }
+                }
+                if let Some(pos) = response
+                    .interact_pointer_pos()
+                    .filter(|_| response.clicked())
+                {
+                    let at = (pos - rect.min) / CELL;
+                    let (r, c) = (at.y as usize, at.x as usize);
+                    if (1..=rows).contains(&r) && (1..=cols).contains(&c) {
+                        clicked = Some(shape.minterm(map, r - 1, c - 1));
+                    }
+                }
+            });
+        }
+    });
+    clicked
+}
+
+struct MinimizeWindow {
+    /// names of the variables, separated by spaces or commas
+    vars: String,
+    names: Vec<String>,
+    cells: Vec<Cell>,
+    output: String,
+    notation: Notation,
+    result: Option<(Vec<String>, Vec<Cell>, Minimized)>,
+    error: Option<String>,
+}
+
+impl Default for MinimizeWindow {
+    fn default() -> Self {
+        Self {
+            vars: "a b c d".to_string(),
+            names: vec![],
+            cells: vec![],
+            output: String::new(),
+            notation: Notation::Algebraic,
+            result: None,
+            error: None,
+        }
+    }
+}
+
+/// fills in a function by hand or from the circuit, and shows it minimized
+fn minimize_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<MinimizeWindow>,
+    simulation: Res<Simulation>,
+) {
+    let window = &mut *window;
+    let names = window
+        .vars
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .take(MAX_VARS)
+        .collect::<Vec<_>>();
+    if names.len() != window.names.len() {
+        window.cells = vec![Cell::Zero; 1 << names.len()];
+    }
+    window.names = names;
+    let (_, outputs) = port_columns(&simulation.sim.circuit);
+
+    egui::Window::new("minimize").show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("variables");
+            ui.text_edit_singleline(&mut window.vars);
+        });
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("minimize output")
+                .selected_text(&window.output)
+                .show_ui(ui, |ui| {
+                    for c in outputs.iter() {
+                        ui.selectable_value(&mut window.output, c.name.clone(), &c.name);
+                    }
+                });
+            if ui.button("from circuit").clicked() {
+                match circuit_function(&simulation.sim, &window.output) {
+                    Ok((names, cells)) => {
+                        window.vars = names.join(" ");
+                        window.names = names;
+                        window.cells = cells;
+                        window.error = None;
+                    }
+                    Err(e) => window.error = Some(e.to_string()),
+                }
+            }
+        });
+        if let Some(e) = &window.error {
+            ui.colored_label(egui::Color32::YELLOW, e);
+        }
+
+        let stale = window
+            .result
+            .as_ref()
+            .is_none_or(|(names, cells, _)| *names != window.names || *cells != window.cells);
+        if stale {
+            let m = minimize(&window.names, &window.cells);
+            window.result = Some((window.names.clone(), window.cells.clone(), m));
+        }
+        let m = match &window.result {
+            Some((_, _, m)) => m.clone(),
+            None => return,
+        };
+
+        if window.names.len() <= MAX_MAP_VARS {
+            ui.label("click a cell to switch it between 0, 1 and X, don't care");
+            if let Some(m) = karnaugh_map(ui, &window.names, &window.cells, &m.products) {
+                window.cells[m] = window.cells[m].next();
+            }
+        } else {
+            ui.label(format!(
+                "karnaugh maps are drawn for up to {MAX_MAP_VARS} variables"
+            ));
+        }
+        egui::ComboBox::from_label("notation")
+            .selected_text(window.notation.name())
+            .show_ui(ui, |ui| {
+                for n in Notation::ALL {
+                    ui.selectable_value(&mut window.notation, n, n.name());
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.label("sum of products");
+            ui.text_edit_singleline(&mut m.sop.to_string(window.notation).as_str());
+        });
+        ui.horizontal(|ui| {
+            ui.label("product of sums");
+            ui.text_edit_singleline(&mut m.pos.to_string(window.notation).as_str());
+        });
+        if !m.exact {
+            ui.label("the search took too long, there may be a smaller form");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a small random number generator, so the tests see the same functions every time
+    fn random(seed: &mut u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        *seed >> 33
+    }
+
+    fn names(n: usize) -> Vec<String> {
+        ["a", "b", "c", "d", "e", "f", "g", "h"][..n]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn random_cells(n: usize, seed: &mut u64) -> Vec<Cell> {
+        (0..1 << n)
+            .map(|_| match random(seed) % 8 {
+                0 => Cell::DontCare,
+                1..=3 => Cell::One,
+                _ => Cell::Zero,
+            })
+            .collect()
+    }
+
+    /// products that are true on every one and on no zero
+    fn check_cover(n: usize, cells: &[Cell], products: &[Implicant]) {
+        for (m, &cell) in cells.iter().enumerate() {
+            let covered = products.iter().any(|p| p.covers(m as u32));
+            match cell {
+                Cell::One => assert!(covered, "{m} is left out"),
+                Cell::Zero => assert!(!covered, "{m} is covered"),
+                Cell::DontCare => {}
+            }
+        }
+        assert!(products
+            .iter()
+            .all(|p| p.bits & p.free == 0 && p.literals(n) <= n));
+    }
+
+    #[test]
+    fn primes_of_a_small_function() {
+        // a'b' + bc over a b c, true on 000 001 011 111, can also be covered by a'c
+        let primes = prime_implicants(&[0, 1, 3, 7]);
+        assert_eq!(
+            primes,
+            vec![
+                Implicant { bits: 0, free: 1 },
+                Implicant { bits: 1, free: 2 },
+                Implicant { bits: 3, free: 4 },
+            ]
+        );
+        assert_eq!(prime_implicants(&[]), vec![]);
+        assert_eq!(
+            prime_implicants(&[0, 1, 2, 3]),
+            vec![Implicant { bits: 0, free: 3 }]
+        );
+    }
+
+    #[test]
+    fn dont_cares_make_it_smaller() {
+        // ones at 1 3 7 11 15 and don't cares at 0 2 5 give a'd + cd
+        let mut cells = vec![Cell::Zero; 16];
+        for m in [1, 3, 7, 11, 15] {
+            cells[m] = Cell::One;
+        }
+        for m in [0, 2, 5] {
+            cells[m] = Cell::DontCare;
+        }
+        let m = minimize(&names(4), &cells);
+        assert_eq!(m.sop.to_string(Notation::Algebraic), "a'·d + c·d");
+        assert!(m.exact);
+        check_cover(4, &cells, &m.products);
+    }
+
+    #[test]
+    fn constant_functions() {
+        let m = minimize(&names(3), &[Cell::Zero; 8]);
+        assert_eq!((m.sop, m.pos), (Expr::Const(false), Expr::Const(false)));
+        assert!(m.products.is_empty());
+        let m = minimize(&names(3), &[Cell::One; 8]);
+        assert_eq!((m.sop, m.pos), (Expr::Const(true), Expr::Const(true)));
+        assert!(m.sums.is_empty());
+        let m = minimize(&names(2), &[Cell::DontCare; 4]);
+        assert_eq!(m.sop, Expr::Const(false));
+    }
+
+    #[test]
+    fn covers_are_as_small_as_every_other() {
+        let mut seed = 1;
+        for _ in 0..50 {
+            let n = 4;
+            let cells = random_cells(n, &mut seed);
+            let ones = (0..16).filter(|&m| cells[m as usize] == Cell::One);
+            let care = (0..16).filter(|&m| cells[m as usize] != Cell::Zero);
+            let (ones, care) = (ones.collect::<Vec<_>>(), care.collect::<Vec<_>>());
+            let primes = prime_implicants(&care);
+            let (cover, exact) = minimal_cover(n, &primes, &ones);
+            assert!(exact);
+            check_cover(n, &cells, &cover);
+            // every subset of the primes that covers the ones is no smaller
+            let cost = |c: &[Implicant]| (c.len(), c.iter().map(|p| p.literals(n)).sum::<usize>());
+            for subset in 0u32..1 << primes.len() {
+                let c = (0..primes.len())
+                    .filter(|&i| subset >> i & 1 == 1)
+                    .map(|i| primes[i])
+                    .collect::<Vec<_>>();
+                if ones.iter().all(|&m| c.iter().any(|p| p.covers(m))) {
+                    assert!(cost(&cover) <= cost(&c), "{cover:?} {c:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn eight_variables_in_good_time() {
+        let mut seed = 8;
+        for _ in 0..10 {
+            let cells = random_cells(MAX_VARS, &mut seed);
+            let m = minimize(&names(MAX_VARS), &cells);
+            check_cover(MAX_VARS, &cells, &m.products);
+            // the sums cover the zeros the same way
+            let flipped = cells
+                .iter()
+                .map(|&c| match c {
+                    Cell::One => Cell::Zero,
+                    Cell::Zero => Cell::One,
+                    c => c,
+                })
+                .collect::<Vec<_>>();
+            check_cover(MAX_VARS, &flipped, &m.sums);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use bevy::prelude::{App, Entity, Plugin, Res, ResMut, Resource, Vec2};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    label::LabelOn,
+    pin::PinSpec,
+    run::{GameState, Gate},
+    save::{CircuitDoc, CircuitQuery, GateDoc},
+    subcircuit::Library,
+};
+
+pub struct NetlistPlugin;
+
+impl Plugin for NetlistPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CircuitNets::default()).add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(rebuild_nets)
+                .into(),
+        );
+    }
+}
+
+/// a set of pins and wires that are connected to each other
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Net {
+    /// names given by labels on its wires and by its tunnels
+    pub names: Vec<String>,
+    /// gate and pin index of every pin on the net
+    pub pins: Vec<(usize, usize)>,
+    pub wires: Vec<usize>,
+}
+
+impl Net {
+    pub fn name(&self) -> Option<&str> {
+        self.names.first().map(|s| s.as_str())
+    }
+}
+
+/// how the gates of a [`CircuitDoc`] are connected. gates and wires are referred to by their index in the doc
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Netlist {
+    pub nets: Vec<Net>,
+    /// the net of every pin, by gate then pin index
+    pub pin_nets: Vec<Vec<usize>>,
+    /// the net of every wire
+    pub wire_nets: Vec<usize>,
+}
+
+impl Netlist {
+    /// pins and wire ends on the same point are connected, and so is anything ending on a wire segment.
+    /// wires that merely cross do not connect. tunnels with the same name end up on one net
+    pub fn build(doc: &CircuitDoc, library: &Library) -> Self {
+        let mut items = UnionFind::default();
+        // the points where things can connect, with the items sitting on them
+        let mut points: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+
+        let pin_items = doc
+            .gates
+            .iter()
+            .map(|g| {
+                library
+                    .pins(g)
+                    .iter()
+                    .map(|spec| {
+                        let i = items.add();
+                        points
+                            .entry(point_key(pin_pos(g, spec)))
+                            .or_default()
+                            .push(i);
+                        i
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let wire_items = doc
+            .wires
+            .iter()
+            .map(|w| {
+                let i = items.add();
+                for &p in [w.nodes.first(), w.nodes.last()].into_iter().flatten() {
+                    points.entry(point_key(p.into())).or_default().push(i);
+                }
+                i
+            })
+            .collect::<Vec<_>>();
+
+        for here in points.values() {
+            for pair in here.windows(2) {
+                items.union(pair[0], pair[1]);
+            }
+        }
+        for (w, &i) in doc.wires.iter().zip(wire_items.iter()) {
+            for seg in w.nodes.windows(2) {
+                let (a, b) = (point_key(seg[0].into()), point_key(seg[1].into()));
+                for (&p, here) in points.iter() {
+                    if on_segment(p, a, b) {
+                        items.union(i, here[0]);
+                    }
+                }
+            }
+        }
+
+        let mut tunnels: HashMap<&str, usize> = HashMap::new();
+        for (gi, g) in doc.gates.iter().enumerate() {
+            if g.gate != Gate::Tunnel {
+                continue;
+            }
+            if let Some(name) = label_text(doc, LabelOn::Gate(gi)) {
+                match tunnels.entry(name) {
+                    Entry::Occupied(o) => items.union(*o.get(), pin_items[gi][0]),
+                    Entry::Vacant(v) => {
+                        v.insert(pin_items[gi][0]);
+                    }
+                }
+            }
+        }
+
+        let mut netlist = Netlist::default();
+        let mut index = HashMap::new();
+        let mut net_of = |item: usize, nets: &mut Vec<Net>| {
+            *index.entry(items.find(item)).or_insert_with(|| {
+                nets.push(Net::default());
+                nets.len() - 1
+            })
+        };
+        for (gi, pins) in pin_items.iter().enumerate() {
+            let pins = pins
+                .iter()
+                .enumerate()
+                .map(|(pi, &item)| {
+                    let n = net_of(item, &mut netlist.nets);
+                    netlist.nets[n].pins.push((gi, pi));
+                    n
+                })
+                .collect();
+            netlist.pin_nets.push(pins);
+        }
+        for (wi, &item) in wire_items.iter().enumerate() {
+            let n = net_of(item, &mut netlist.nets);
+            netlist.nets[n].wires.push(wi);
+            netlist.wire_nets.push(n);
+        }
+
+        for wi in 0..doc.wires.len() {
+            if let Some(name) = label_text(doc, LabelOn::Wire(wi)) {
+                netlist.add_name(netlist.wire_nets[wi], name);
+            }
+        }
+        for (name, &item) in tunnels.iter() {
+            let n = net_of(item, &mut netlist.nets);
+            netlist.add_name(n, name);
+        }
+        netlist
+    }
+
+    fn add_name(&mut self, net: usize, name: &str) {
+        let names = &mut self.nets[net].names;
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+}
+
+/// where a pin of a gate ends up on the canvas
+pub fn pin_pos(g: &GateDoc, spec: &PinSpec) -> Vec2 {
+    Vec2::from(g.pos) + g.orientation.apply(spec.offset)
+}
+
+/// the text of the first non empty label on a gate or wire
+pub fn label_text(doc: &CircuitDoc, on: LabelOn) -> Option<&str> {
+    doc.labels
+        .iter()
+        .filter(|l| l.on == on)
+        .map(|l| l.text.trim())
+        .find(|t| !t.is_empty())
+}
+
+/// positions are compared on a fine integer grid, so rotated pins still meet wire ends
+pub fn point_key(p: Vec2) -> (i64, i64) {
+    let p = (p * 8.0).round();
+    (p.x as i64, p.y as i64)
+}
+
+pub fn on_segment(p: (i64, i64), a: (i64, i64), b: (i64, i64)) -> bool {
+    let cross = (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+    cross == 0
+        && a.0.min(b.0) <= p.0
+        && p.0 <= a.0.max(b.0)
+        && a.1.min(b.1) <= p.1
+        && p.1 <= a.1.max(b.1)
+}
+
+/// groups of connected things, by index
+#[derive(Default)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    pub fn add(&mut self) -> usize {
+        self.parent.push(self.parent.len());
+        self.parent.len() - 1
+    }
+
+    pub fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    pub fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a] = b;
+    }
+}
+
+/// the nets of what is on the canvas, kept up to date as the circuit is edited
+#[derive(Resource, Default)]
+pub struct CircuitNets {
+    pub doc: CircuitDoc,
+    pub netlist: Netlist,
+    /// the entities behind the gates and wires of `doc`
+    pub gates: Vec<Entity>,
+    pub wires: Vec<Entity>,
+}
+
+impl CircuitNets {
+    pub fn pin_net(&self, gate: Entity, pin: usize) -> Option<&Net> {
+        let gi = self.gates.iter().position(|&e| e == gate)?;
+        let n = *self.netlist.pin_nets.get(gi)?.get(pin)?;
+        self.netlist.nets.get(n)
+    }
+
+    pub fn wire_net(&self, wire: Entity) -> Option<&Net> {
+        let wi = self.wires.iter().position(|&e| e == wire)?;
+        self.netlist.nets.get(self.netlist.wire_nets[wi])
+    }
+}
+
+fn rebuild_nets(circuit: CircuitQuery, library: Res<Library>, mut nets: ResMut<CircuitNets>) {
+    let doc = CircuitDoc::collect(&circuit);
+    let gates = circuit.gate_entities();
+    let wires = circuit.wire_entities();
+    if doc == nets.doc && gates == nets.gates && wires == nets.wires && !library.is_changed() {
+        return;
+    }
+    nets.netlist = Netlist::build(&doc, &library);
+    nets.doc = doc;
+    nets.gates = gates;
+    nets.wires = wires;
+}
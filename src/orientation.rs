@@ -0,0 +1,108 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    prelude::{
+        App, Changed, Children, Component, Input, KeyCode, Or, Plugin, Quat, Query, Res, Transform,
+        Vec2, With, Without,
+    },
+    sprite::Sprite,
+};
+use iyes_loopless::prelude::ConditionSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    label::keyboard_free,
+    pin::Pin,
+    run::{GameState, Gate, Selected, UnPlaced},
+};
+
+pub struct OrientationPlugin;
+
+impl Plugin for OrientationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .run_if(keyboard_free)
+                .with_system(rotate_gates)
+                .into(),
+        )
+        .add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(apply_orientation)
+                .into(),
+        );
+    }
+}
+
+/// how a gate is turned on the canvas.
+/// mirroring happens in the gate's own space, before the rotation
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Orientation {
+    /// number of 90° counter-clockwise turns
+    pub turns: u8,
+    pub mirrored: bool,
+}
+
+impl Orientation {
+    pub fn rotate(&mut self) {
+        self.turns = (self.turns + 1) % 4;
+    }
+
+    pub fn mirror(&mut self) {
+        self.mirrored = !self.mirrored;
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_z(self.turns as f32 * FRAC_PI_2)
+    }
+
+    /// mirrors a point given in the gate's space. the rotation is left to the gate's transform
+    pub fn mirror_local(&self, v: Vec2) -> Vec2 {
+        if self.mirrored {
+            Vec2::new(-v.x, v.y)
+        } else {
+            v
+        }
+    }
+
+    /// maps a point in the gate's space to an offset from the gate's position on the canvas
+    pub fn apply(&self, v: Vec2) -> Vec2 {
+        self.rotation()
+            .mul_vec3(self.mirror_local(v).extend(0.0))
+            .truncate()
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn rotate_gates(
+    keys: Res<Input<KeyCode>>,
+    mut gates: Query<&mut Orientation, Or<(With<UnPlaced>, With<Selected>)>>,
+) {
+    if keys.just_pressed(KeyCode::R) {
+        gates.iter_mut().for_each(|mut o| o.rotate());
+    }
+    if keys.just_pressed(KeyCode::F) {
+        gates.iter_mut().for_each(|mut o| o.mirror());
+    }
+}
+
+/// the collider and the pins are attached to the gate's transform, so they turn along with the sprite
+#[allow(clippy::type_complexity)]
+fn apply_orientation(
+    mut gates: Query<(&Orientation, &mut Transform, &mut Sprite, &Children), Changed<Orientation>>,
+    mut pins: Query<(&Pin, &mut Transform), Without<Gate>>,
+) {
+    for (o, mut t, mut sprite, children) in gates.iter_mut() {
+        t.rotation = o.rotation();
+        sprite.flip_x = o.mirrored;
+
+        for &child in children.iter() {
+            if let Ok((pin, mut pt)) = pins.get_mut(child) {
+                let offset = o.mirror_local(pin.offset);
+                pt.translation = offset.extend(pt.translation.z);
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::ops::RangeInclusive;
+
+use bevy::{
+    prelude::{
+        App, BuildChildren, ChangeTrackers, Children, Commands, Component, DespawnRecursiveExt,
+        Entity, Handle, Image, Plugin, Query, Res, ResMut, With, Without,
+    },
+    sprite::Sprite,
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use bevy_rapier2d::prelude::Collider;
+use iyes_loopless::prelude::ConditionSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    orientation::Orientation,
+    run::{spawn_part_children, Assets, GameState, Gate, Selected, UnPlaced},
+    sim::Value,
+    subcircuit::Instance,
+};
+
+pub struct ParamsPlugin;
+
+impl Plugin for ParamsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(rebuild_parts)
+                .with_system(properties_ui)
+                .into(),
+        );
+    }
+}
+
+/// the settings of a part that comes in different sizes. what each one means is up to the part,
+/// see [`Gate::param_kinds`]
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Params {
+    /// bits on each data pin
+    pub width: u8,
+    /// number of inputs, for parts that take any number
+    pub inputs: u8,
+    /// what the part holds when the simulation starts
+    pub initial: u64,
+    /// which of the ways of working listed in [`ParamKinds::modes`]
+    pub mode: u8,
+    /// number of select lines, for parts that pick one of several
+    pub select: u8,
+    /// number of address lines of a memory
+    pub address: u8,
+    /// how long the part takes to answer a change on its inputs, in nanoseconds
+    pub delay: u8,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            width: 1,
+            inputs: 2,
+            initial: 0,
+            mode: 0,
+            select: 1,
+            address: 4,
+            delay: 1,
+        }
+    }
+}
+
+impl Params {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn initial_value(&self) -> Value {
+        Value::new(self.initial, self.width)
+    }
+}
+
+/// which of the [`Params`] a part takes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParamKinds {
+    pub width: bool,
+    pub inputs: Option<RangeInclusive<u8>>,
+    pub initial: bool,
+    /// names of the modes to pick from, if the part has any
+    pub modes: &'static [&'static str],
+    pub select: Option<RangeInclusive<u8>>,
+    pub address: Option<RangeInclusive<u8>>,
+    pub delay: bool,
+}
+
+impl ParamKinds {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+pub const MAX_WIDTH: u8 = 64;
+pub const MAX_DELAY: u8 = 100;
+
+/// the sprite, collider and pins of a part follow its parameters.
+/// box parts also redo their pin names when turned
+#[allow(clippy::type_complexity)]
+fn rebuild_parts(
+    mut c: Commands,
+    assets: Res<Assets>,
+    mut parts: Query<
+        (
+            Entity,
+            &Gate,
+            &Params,
+            ChangeTrackers<Params>,
+            &Orientation,
+            ChangeTrackers<Orientation>,
+            &mut Handle<Image>,
+            &mut Sprite,
+            Option<&Children>,
+        ),
+        Without<Instance>,
+    >,
+) {
+    for (e, g, params, params_changed, o, o_changed, mut texture, mut sprite, children) in
+        parts.iter_mut()
+    {
+        // freshly spawned parts were built with the right parameters already
+        let rebuild = (params_changed.is_changed() && !params_changed.is_added())
+            || (o_changed.is_changed() && !o_changed.is_added() && g.layout(params).is_some());
+        if !rebuild {
+            continue;
+        }
+        let size = g.size(params);
+        *texture = assets.gate_image(*g, params);
+        sprite.custom_size = Some(size);
+
+        children
+            .into_iter()
+            .flatten()
+            .for_each(|&child| c.entity(child).despawn_recursive());
+        c.entity(e)
+            .insert(Collider::cuboid(size.x / 2.0, size.y / 2.0))
+            .with_children(|p| spawn_part_children(p, &assets, *g, params, *o));
+    }
+}
+
+/// the parameters of the selected part
+#[allow(clippy::type_complexity)]
+fn properties_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut selected: Query<(&Gate, &mut Params), (With<Selected>, Without<UnPlaced>)>,
+) {
+    let (g, mut params) = match selected.get_single_mut() {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let kinds = g.param_kinds();
+    if kinds.is_empty() {
+        return;
+    }
+    let mut edited = *params;
+    egui::Window::new("properties").show(egui_context.ctx_mut(), |ui| {
+        ui.label(g.title());
+        if kinds.width {
+            ui.add(egui::Slider::new(&mut edited.width, 1..=MAX_WIDTH).text("width"));
+        }
+        if let Some(range) = kinds.inputs {
+            ui.add(egui::Slider::new(&mut edited.inputs, range).text("inputs"));
+        }
+        if let Some(range) = kinds.select {
+            ui.add(egui::Slider::new(&mut edited.select, range).text("select bits"));
+        }
+        if let Some(range) = kinds.address {
+            ui.add(egui::Slider::new(&mut edited.address, range).text("address bits"));
+        }
+        if kinds.initial {
+            ui.horizontal(|ui| {
+                ui.label("initial");
+                ui.add(egui::DragValue::new(&mut edited.initial));
+                ui.label(format!("{:#x}", edited.initial));
+            });
+        }
+        if kinds.delay {
+            ui.add(egui::Slider::new(&mut edited.delay, 0..=MAX_DELAY).text("delay (ns)"));
+        }
+        if !kinds.modes.is_empty() {
+            let current = kinds
+                .modes
+                .get(edited.mode as usize)
+                .copied()
+                .unwrap_or("?");
+            egui::ComboBox::from_label("mode")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (i, mode) in kinds.modes.iter().enumerate() {
+                        ui.selectable_value(&mut edited.mode, i as u8, *mode);
+                    }
+                });
+        }
+    });
+    edited.initial = edited.initial_value().bits;
+    if edited != *params {
+        *params = edited;
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::{
+    prelude::{ChildBuilder, Color, Component, Handle, Transform, Vec2, Vec3},
+    sprite::{Sprite, SpriteBundle},
+    text::{Font, HorizontalAlign, Text, Text2dBundle, TextAlignment, TextStyle, VerticalAlign},
+};
+
+use crate::orientation::Orientation;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinDir {
+    In,
+    Out,
+}
+
+/// where a pin sits on an unrotated gate, relative to its centre
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PinSpec {
+    pub offset: Vec2,
+    pub dir: PinDir,
+}
+
+impl PinSpec {
+    pub fn input(x: f32, y: f32) -> Self {
+        Self {
+            offset: Vec2::new(x, y),
+            dir: PinDir::In,
+        }
+    }
+
+    pub fn output(x: f32, y: f32) -> Self {
+        Self {
+            offset: Vec2::new(x, y),
+            dir: PinDir::Out,
+        }
+    }
+}
+
+/// a connection point of a gate.
+/// pins are children of the gate so they follow it around when it is dragged or rotated
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Pin {
+    pub index: usize,
+    pub dir: PinDir,
+    /// where the pin sits on the unturned gate
+    pub offset: Vec2,
+}
+
+pub fn spawn_pins(p: &mut ChildBuilder, pins: &[PinSpec], orientation: Orientation) {
+    for (index, spec) in pins.iter().enumerate() {
+        let offset = orientation.mirror_local(spec.offset);
+        p.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.8, 0.6, 0.3),
+                    custom_size: Some(Vec2::splat(4.0)),
+                    ..Default::default()
+                },
+                transform: Transform {
+                    translation: Vec3::new(offset.x, offset.y, 0.1),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Pin {
+                index,
+                dir: spec.dir,
+                offset: spec.offset,
+            },
+        ));
+    }
+}
+
+/// a rectangular part with inputs down the left side and outputs down the right
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoxLayout {
+    pub rows: usize,
+    pub size: Vec2,
+    pub pins: Vec<PinSpec>,
+    pub pin_names: Vec<String>,
+}
+
+/// pins are two grid steps apart, like on the built in gates
+const PIN_SPACING: f32 = 10.0;
+const BOX_WIDTH: f32 = 50.0;
+
+impl BoxLayout {
+    pub fn new(inputs: Vec<String>, outputs: Vec<String>) -> Self {
+        let rows = inputs.len().max(outputs.len()).max(1);
+        let top = (rows - 1) as f32 * PIN_SPACING / 2.0;
+        let x = BOX_WIDTH / 2.0;
+        let pins = (0..inputs.len())
+            .map(|i| PinSpec::input(-x, top - i as f32 * PIN_SPACING))
+            .chain((0..outputs.len()).map(|i| PinSpec::output(x, top - i as f32 * PIN_SPACING)))
+            .collect();
+        Self {
+            rows,
+            size: Vec2::new(BOX_WIDTH, (rows + 1) as f32 * PIN_SPACING),
+            pins,
+            pin_names: inputs.into_iter().chain(outputs).collect(),
+        }
+    }
+}
+
+/// the names next to the pins, inside the box
+pub fn spawn_pin_names(
+    p: &mut ChildBuilder,
+    font: Handle<Font>,
+    layout: &BoxLayout,
+    orientation: Orientation,
+) {
+    for (spec, name) in layout.pins.iter().zip(layout.pin_names.iter()) {
+        // names start at the pin and run into the box, whichever side the pin ended up on
+        let inward = orientation.mirror_local(Vec2::new(-spec.offset.x.signum(), 0.0));
+        let offset = orientation.mirror_local(spec.offset) + inward * 4.0;
+        let horizontal = if inward.x > 0.0 {
+            HorizontalAlign::Left
+        } else {
+            HorizontalAlign::Right
+        };
+        p.spawn(Text2dBundle {
+            text: Text {
+                alignment: TextAlignment {
+                    vertical: VerticalAlign::Center,
+                    horizontal,
+                },
+                ..Text::from_section(
+                    name.clone(),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 6.0,
+                        color: Color::rgb(0.9, 0.85, 0.7),
+                    },
+                )
+            },
+            transform: Transform::from_translation(offset.extend(0.2)),
+            ..Default::default()
+        });
+    }
+}
+
+/// the name of a box part, just above it
+pub fn spawn_box_title(p: &mut ChildBuilder, font: Handle<Font>, title: String, size: Vec2) {
+    p.spawn(Text2dBundle {
+        text: Text {
+            alignment: TextAlignment::CENTER,
+            ..Text::from_section(
+                title,
+                TextStyle {
+                    font,
+                    font_size: 8.0,
+                    color: Color::rgb(0.9, 0.85, 0.7),
+                },
+            )
+        },
+        transform: Transform::from_translation(Vec2::new(0.0, size.y / 2.0 + 5.0).extend(0.2)),
+        ..Default::default()
+    });
+}
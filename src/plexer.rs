@@ -0,0 +1,139 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    params::Params,
+    run::Gate,
+    sim::{mask, Value},
+};
+
+/// the selection parts, in palette order
+pub const PARTS: [Gate; 5] = [
+    Gate::Mux,
+    Gate::Demux,
+    Gate::Decoder,
+    Gate::Encoder,
+    Gate::PriorityEncoder,
+];
+
+/// select lines a plexer can have. sixteen data pins is about as tall as a box gets readable
+pub const SELECT_BITS: RangeInclusive<u8> = 1..=4;
+
+fn numbered(prefix: &str, n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("{prefix}{i}")).collect()
+}
+
+/// names of the input and output pins, top to bottom. the select lines come last
+pub fn pin_names(g: Gate, params: &Params) -> Option<(Vec<String>, Vec<String>)> {
+    let n = 1 << params.select;
+    let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    Some(match g {
+        Gate::Mux => {
+            let mut inputs = numbered("d", n);
+            inputs.push("sel".to_string());
+            (inputs, names(&["y"]))
+        }
+        Gate::Demux => (names(&["d", "sel"]), numbered("y", n)),
+        Gate::Decoder => (names(&["sel"]), numbered("y", n)),
+        Gate::Encoder | Gate::PriorityEncoder => (numbered("d", n), names(&["y", "v"])),
+        _ => return None,
+    })
+}
+
+/// outputs of a selection part, in the order of its output pins
+pub fn eval(g: Gate, params: &Params, inputs: &[Value]) -> Vec<Value> {
+    let n = 1 << params.select;
+    let input = |i: usize| inputs.get(i).copied().unwrap_or_default();
+    let selected = |i: usize| (input(i).bits & mask(params.select)) as usize;
+    let word = |bits: u64| Value::new(bits, params.width);
+    match g {
+        Gate::Mux => vec![word(input(selected(n)).bits)],
+        Gate::Demux => {
+            let sel = selected(1);
+            (0..n)
+                .map(|i| word(if i == sel { input(0).bits } else { 0 }))
+                .collect()
+        }
+        Gate::Decoder => {
+            let sel = selected(0);
+            (0..n).map(|i| Value::bit(i == sel)).collect()
+        }
+        Gate::Encoder | Gate::PriorityEncoder => {
+            let high = (0..n).filter(|&i| input(i).is_high());
+            // a plain encoder is only meant for one high input, and ors the numbers of several
+            let y = if g == Gate::Encoder {
+                high.clone().fold(0, |acc, i| acc | i as u64)
+            } else {
+                high.clone().next_back().unwrap_or(0) as u64
+            };
+            vec![Value::new(y, params.select), Value::bit(high.count() > 0)]
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::eval_bits;
+
+    fn params(select: u8, width: u8) -> Params {
+        Params {
+            select,
+            width,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pins_follow_select() {
+        let (inputs, outputs) = pin_names(Gate::Mux, &params(2, 1)).unwrap();
+        assert_eq!(inputs, vec!["d0", "d1", "d2", "d3", "sel"]);
+        assert_eq!(outputs, vec!["y"]);
+        let (inputs, outputs) = pin_names(Gate::Demux, &params(2, 1)).unwrap();
+        assert_eq!((inputs.len(), outputs.len()), (2, 4));
+        assert_eq!(pin_names(Gate::And, &params(2, 1)), None);
+    }
+
+    #[test]
+    fn mux_picks_the_selected_input() {
+        let data = [0x11, 0x22, 0x33, 0x44];
+        for sel in 0..4 {
+            let mut inputs = data.to_vec();
+            inputs.push(sel);
+            assert_eq!(
+                eval_bits(Gate::Mux, &params(2, 8), &inputs),
+                vec![data[sel as usize]]
+            );
+        }
+        // only the select lines there are count, and data is cut to the width
+        assert_eq!(
+            eval_bits(Gate::Mux, &params(2, 4), &[0x11, 0x22, 0x33, 0x44, 5]),
+            vec![0x2]
+        );
+    }
+
+    #[test]
+    fn demux_and_decoder() {
+        assert_eq!(
+            eval_bits(Gate::Demux, &params(2, 8), &[0xab, 2]),
+            vec![0, 0, 0xab, 0]
+        );
+        assert_eq!(
+            eval_bits(Gate::Decoder, &params(2, 1), &[3]),
+            vec![0, 0, 0, 1]
+        );
+        assert_eq!(eval_bits(Gate::Decoder, &params(1, 1), &[0]), vec![1, 0]);
+    }
+
+    #[test]
+    fn encoders() {
+        let encode = |g: Gate, inputs: &[u64]| eval_bits(g, &params(2, 1), inputs);
+        assert_eq!(encode(Gate::Encoder, &[0, 0, 1, 0]), vec![2, 1]);
+        assert_eq!(encode(Gate::Encoder, &[0, 0, 0, 0]), vec![0, 0]);
+        // several high inputs: a plain encoder ors their numbers, a priority encoder takes the highest
+        assert_eq!(encode(Gate::Encoder, &[0, 1, 1, 0]), vec![3, 1]);
+        assert_eq!(encode(Gate::PriorityEncoder, &[1, 1, 0, 0]), vec![1, 1]);
+        assert_eq!(encode(Gate::PriorityEncoder, &[1, 0, 0, 0]), vec![0, 1]);
+        assert_eq!(encode(Gate::PriorityEncoder, &[0, 0, 0, 0]), vec![0, 0]);
+    }
+}
@@ -1,10 +1,15 @@
+use std::ops::RangeInclusive;
+
 use anyhow::Result;
 use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    input::mouse::MouseWheel,
     prelude::{
-        App, AssetServer, BuildChildren, ButtonBundle, Camera, Camera2dBundle, Changed, ClearColor,
-        Color, Commands, Component, Entity, GlobalTransform, Handle, Image, ImageBundle,
-        ImagePlugin, Input, MouseButton, PluginGroup, Query, Res, ResMut, Resource, TextBundle,
-        Transform, Vec2, Vec3, With, Name,
+        Added, App, BuildChildren, ButtonBundle, Camera, Camera2dBundle, Changed, ChildBuilder,
+        ClearColor, Color, Commands, Component, DespawnRecursiveExt, Entity, EventReader,
+        GlobalTransform, Handle, Image, ImageBundle, ImagePlugin, Input, KeyCode, MouseButton,
+        Name, Or, OrthographicProjection, PluginGroup, Query, RemovedComponents, Res, ResMut,
+        Resource, TextBundle, Transform, Vec2, Vec3, With,
     },
     sprite::{Sprite, SpriteBundle},
     text::{Font, Text, TextStyle},
@@ -19,7 +24,41 @@ use bevy::{
 use bevy_asset_loader::prelude::{AssetCollection, LoadingState, LoadingStateAppExt};
 use bevy_inspector_egui::{bevy_egui::EguiSettings, WorldInspectorPlugin};
 use bevy_rapier2d::prelude::{Collider, QueryFilter, RapierContext, RapierPhysicsPlugin};
-use iyes_loopless::prelude::{AppLooplessStateExt, ConditionSet};
+use iyes_loopless::prelude::{AppLooplessStateExt, ConditionSet, NextState};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arith, bus, counter,
+    drc::DrcPlugin,
+    equiv::EquivPlugin,
+    expr::ExprPlugin,
+    grid::{GridPlugin, GridSettings},
+    images::{
+        box_image, PartImagesPlugin, INPUT_IMAGE, NAND_IMAGE, NOR_IMAGE, OUTPUT_IMAGE, TUNNEL_IMAGE,
+    },
+    label::LabelPlugin,
+    library::{ComponentLibrary, LibraryPlugin},
+    memory::{self, MemoryPlugin},
+    minimize::MinimizePlugin,
+    netlist::NetlistPlugin,
+    orientation::{Orientation, OrientationPlugin},
+    params::{ParamKinds, Params, ParamsPlugin},
+    pin::{spawn_box_title, spawn_pin_names, spawn_pins, BoxLayout, PinSpec},
+    plexer,
+    save::SavePlugin,
+    sim::SimPlugin,
+    stats::StatsPlugin,
+    subcircuit::{Instance, SubcircuitPlugin},
+    synth::SynthPlugin,
+    timing::TimingPlugin,
+    tool::{Tool, ToolPlugin, Toolbar},
+    truth::TruthPlugin,
+    tunnel::TunnelPlugin,
+    vcd::VcdPlugin,
+    vectors::VectorsPlugin,
+    view::ViewPlugin,
+    wave::WavePlugin,
+};
 
 pub fn run(mut app: App) -> Result<()> {
     app.insert_resource(ClearColor(Color::rgb(0.25, 0.3, 0.25)))
@@ -46,17 +85,65 @@ pub fn run(mut app: App) -> Result<()> {
         .add_enter_system(GameState::Loading, spawn)
         .add_enter_system(GameState::Playing, spawn_ui)
         // .add_enter_system(GameState::Playing, create_wire_sprite) // ? temp
+        .insert_resource(PlaceKind(Gate::And, None))
         .add_system_set(
             ConditionSet::new()
                 .run_in_state(GameState::Playing)
                 .with_system(spawn_gate)
                 .with_system(handle_unplaced)
-                .with_system(unplace_gate)
-                .with_system(spawn_wires)
                 // .with_system(finalise_wire)
                 .with_system(create_wire_sprite)
+                .with_system(highlight_selected)
+                .with_system(zoom_camera)
+                .into(),
+        )
+        .add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .run_in_state(Tool::Select)
+                .with_system(select_gate)
+                .into(),
+        )
+        .add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .run_in_state(Tool::Place)
+                .with_system(place_gate)
                 .into(),
         )
+        .add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .run_in_state(Tool::Wire)
+                .with_system(spawn_wires)
+                .into(),
+        )
+        .add_exit_system(Tool::Wire, abandon_wire)
+        .add_plugin(PartImagesPlugin)
+        .add_plugin(ToolPlugin)
+        .add_plugin(GridPlugin)
+        .add_plugin(LabelPlugin)
+        .add_plugin(OrientationPlugin)
+        .add_plugin(ParamsPlugin)
+        .add_plugin(MemoryPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(NetlistPlugin)
+        .add_plugin(SubcircuitPlugin)
+        .add_plugin(LibraryPlugin)
+        .add_plugin(SimPlugin)
+        .add_plugin(TunnelPlugin)
+        .add_plugin(ViewPlugin)
+        .add_plugin(TruthPlugin)
+        .add_plugin(ExprPlugin)
+        .add_plugin(SynthPlugin)
+        .add_plugin(MinimizePlugin)
+        .add_plugin(EquivPlugin)
+        .add_plugin(DrcPlugin)
+        .add_plugin(TimingPlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(WavePlugin)
+        .add_plugin(VcdPlugin)
+        .add_plugin(VectorsPlugin)
         .add_plugin(WorldInspectorPlugin::new())
         .insert_resource(EguiSettings {
             scale_factor: 0.5,
@@ -69,25 +156,13 @@ pub fn run(mut app: App) -> Result<()> {
 fn spawn_wires(
     mut c: Commands,
     mou: Res<Input<MouseButton>>,
-    q_camera: Query<(&Camera, &GlobalTransform)>,
-    windows: Res<Windows>,
+    cursor: CanvasCursor,
+    grid: Res<GridSettings>,
     mut wire: ResMut<Wire>,
 ) {
-    let (camera, camera_transform) = q_camera.single();
-    let wnd = windows.get_primary().unwrap();
-    if let Some(p) = wnd.cursor_position() {
-        // get the size of the window
-        let window_size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
-        // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
-        let ndc = (p / window_size) * 2.0 - Vec2::ONE;
-        // matrix for undoing the projection and camera transform
-        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
-        // use it to convert ndc to world-space coordinates
-        let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
-        // reduce it to a 2D value
-        let world_pos: Vec2 = world_pos.truncate();
-
-        let world_pos = (world_pos / 5.0).round() * 5.0;
+    let buttons = [MouseButton::Left, MouseButton::Right];
+    if let Some(world_pos) = cursor.world_pos() {
+        let world_pos = grid.snap(world_pos);
         let wire_bundle = (
             WireNode,
             TransformBundle {
@@ -99,15 +174,14 @@ fn spawn_wires(
             },
         );
 
-        if mou.just_pressed(MouseButton::Right) {
-            let id = c
-                .spawn(wire_bundle)
-                .id();
+        if mou.any_just_pressed(buttons) {
+            if cursor.over_ui() {
+                return;
+            }
+            let id = c.spawn(wire_bundle).id();
             wire.nodes.push(id);
-        } else if mou.just_released(MouseButton::Right) {
-            let id = c
-                .spawn(wire_bundle)
-                .id();
+        } else if mou.any_just_released(buttons) && !wire.nodes.is_empty() {
+            let id = c.spawn(wire_bundle).id();
             wire.nodes.push(id);
 
             let w = std::mem::replace(
@@ -121,6 +195,11 @@ fn spawn_wires(
     }
 }
 
+/// drops a wire that was still being drawn when the wire tool was left
+fn abandon_wire(mut c: Commands, mut wire: ResMut<Wire>) {
+    wire.nodes.drain(..).for_each(|e| c.entity(e).despawn());
+}
+
 fn finalise_wire(
     mut c: Commands,
     q: Query<&Transform, With<WireNode>>,
@@ -200,15 +279,15 @@ fn create_wire_sprite(
         off = (start - stop)/2.0
         pos = (start/5.0).rount()*5.0 + off
         */
-    
+
         let w = (right.x - left.x).abs() as _;
         let h = (right.y - left.y).abs() as _;
         let mut img = image::Rgba32FImage::new(w, h);
-    
+
         for x in 1..1 {
             img.put_pixel(x as _, y, image::Rgba([0.4, 0.5, 0.4, 1.]));
         }
-        
+
         // img.put_pixel(0, 0, image::Rgba([1., 0., 0., 1.]));
         // now create a bevy Image from img as DynamicImage and use in a sprite
         let img = Image::from_dynamic(img.into(), true);
@@ -240,114 +319,128 @@ fn create_wire_sprite(
     mut image_store: ResMut<bevy::prelude::Assets<Image>>, // https://bevy-cheatbook.github.io/assets/data.html
     q: Query<(&Transform, Entity), With<WireNode>>,
     mut wires: Query<(&mut Wire, Entity), With<UnFinalised>>,
+    grid: Res<GridSettings>,
 ) {
+    let pitch = grid.pitch;
     for (mut wire, e) in wires.iter_mut() {
         let old_len = wire.nodes.len();
         // bevy::log::info!("{:?}", wire.nodes.iter().cloned().map(|e| q.get(e).unwrap().0.translation).collect::<Vec<_>>());
 
-        wire.nodes = wire.nodes.iter().cloned().zip(wire.nodes.iter().cloned().skip(1))
-        .map(|(a, b)| {
-            let at = q.get(a).unwrap();
-            let bt = q.get(b).unwrap();
-            if at.0.translation.x as i64 == bt.0.translation.x as i64 || at.0.translation.y as i64 == bt.0.translation.y as i64 {
-                vec![a].into_iter()
-            } else {
-                // dbg!(at.0.translation.x as i64 == bt.0.translation.x as i64, at.0.translation.y as i64 == bt.0.translation.y as i64);
-                let mut new_x = (at.0.translation.x + bt.0.translation.x)/2.0;
-                new_x = (new_x/5.0).round()*5.0;
-                let ce = c.spawn((
-                    WireNode,
-                    TransformBundle {
-                        local: Transform {
-                            translation: Vec3::new(new_x, at.0.translation.y, 0.0),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    },        
-                )).id();
-                let de = c.spawn((
-                    WireNode,
-                    TransformBundle {
-                        local: Transform {
-                            translation: Vec3::new(new_x, bt.0.translation.y, 0.0),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    },        
-                )).id();
-                vec![a, ce, de].into_iter()
-            }
-        }).flatten().chain([wire.nodes.iter().cloned().rev().next().unwrap()]).collect();
+        wire.nodes = wire
+            .nodes
+            .iter()
+            .cloned()
+            .zip(wire.nodes.iter().cloned().skip(1))
+            .map(|(a, b)| {
+                let at = q.get(a).unwrap();
+                let bt = q.get(b).unwrap();
+                if at.0.translation.x as i64 == bt.0.translation.x as i64
+                    || at.0.translation.y as i64 == bt.0.translation.y as i64
+                {
+                    vec![a].into_iter()
+                } else {
+                    // dbg!(at.0.translation.x as i64 == bt.0.translation.x as i64, at.0.translation.y as i64 == bt.0.translation.y as i64);
+                    let mut new_x = (at.0.translation.x + bt.0.translation.x) / 2.0;
+                    new_x = (new_x / pitch).round() * pitch;
+                    let ce = c
+                        .spawn((
+                            WireNode,
+                            TransformBundle {
+                                local: Transform {
+                                    translation: Vec3::new(new_x, at.0.translation.y, 0.0),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                        ))
+                        .id();
+                    let de = c
+                        .spawn((
+                            WireNode,
+                            TransformBundle {
+                                local: Transform {
+                                    translation: Vec3::new(new_x, bt.0.translation.y, 0.0),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                        ))
+                        .id();
+                    vec![a, ce, de].into_iter()
+                }
+            })
+            .flatten()
+            .chain([wire.nodes.iter().cloned().rev().next().unwrap()])
+            .collect();
         if wire.nodes.len() != old_len {
             continue;
         }
         // bevy::log::info!("{:?}", wire.nodes.iter().cloned().map(|e| q.get(e).unwrap().0.translation).collect::<Vec<_>>());
         c.entity(e).remove::<UnFinalised>();
 
-        let (l, r) = wire.nodes.iter().cloned()
-        .map(|e| q.get(e).unwrap().0.translation)
-        // .map(|t| (t.truncate(), t.truncate()))
-        .map(|t| (t, t))
-        .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
-        .unwrap();
-    
-        dbg!(l, r, (r-l)/5.0);
-        let w = ((r.x - l.x)/5.0).round() as u32;
-        let h = ((r.y - l.y)/5.0).round() as u32;
-        if w*h == 0 {
-            bevy::log::error!("wire size zero");
-            continue;
-        }
-        let mut img = image::Rgba32FImage::new(w, h);
+        let (l, r) = wire
+            .nodes
+            .iter()
+            .cloned()
+            .map(|e| q.get(e).unwrap().0.translation)
+            // .map(|t| (t.truncate(), t.truncate()))
+            .map(|t| (t, t))
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+            .unwrap();
+
+        // one pixel per grid point, nodes included on both ends
+        let w = ((r.x - l.x) / pitch).round() as u32;
+        let h = ((r.y - l.y) / pitch).round() as u32;
+        let mut img = image::Rgba32FImage::new(w + 1, h + 1);
         let v = wire.nodes.iter().cloned()
         .map(|e| q.get(e).unwrap().0.translation)
         .map(|t| t-l)
         // .inspect(|t| {dbg!(&t);})
-        .map(|t| (t/5.0).round())
+        .map(|t| (t/pitch).round())
         .map(|t| (t.x as u32, t.y as u32))
         // .inspect(|t| {dbg!(&t);})
         ;
-        
-        v.clone().zip(v.clone().skip(1))
-        .for_each(|(t1, t2)| {
+
+        v.clone().zip(v.clone().skip(1)).for_each(|(t1, t2)| {
             if t1.0 == t2.0 {
                 let x = t1.0;
-                for y in t1.1.min(t2.1)..t1.1.max(t2.1) {
-                    img.put_pixel(x, h-y-1, image::Rgba([0.4, 0.5, 0.4, 1.]));
+                for y in t1.1.min(t2.1)..=t1.1.max(t2.1) {
+                    img.put_pixel(x, h - y, image::Rgba([0.4, 0.5, 0.4, 1.]));
                 }
             } else if t1.1 == t2.1 {
-                let y = t1.1.min(h-1);
-                for x in t1.0.min(t2.0)..t1.0.max(t2.0) {
-                    img.put_pixel(x, h-y-1, image::Rgba([0.4, 0.5, 0.4, 1.]));
+                let y = t1.1;
+                for x in t1.0.min(t2.0)..=t1.0.max(t2.0) {
+                    img.put_pixel(x, h - y, image::Rgba([0.4, 0.5, 0.4, 1.]));
                 }
             } else {
                 unreachable!();
             }
         });
-    
+
         // img.put_pixel(0, 0, image::Rgba([1., 0., 0., 1.]));
         // now create a bevy Image from img as DynamicImage and use in a sprite
         let img = Image::from_dynamic(img.into(), true);
+        let size = Vec2::new(w as f32 + 1.0, h as f32 + 1.0) * pitch;
         c.spawn((
             SpriteBundle {
-                texture: image_store.add(img.into()),
+                texture: image_store.add(img),
                 transform: Transform {
                     // scale: Vec3::splat(4.0),
                     // off = (start - stop)/2.0
-                    // pos = (start/5.0).rount()*5.0 + off            
+                    // pos = (start/5.0).rount()*5.0 + off
                     // translation: ((gleft/5.0).round()*5.0+(gright/5.0).round()*5.0)/2.0,
                     // translation: ((l/5.0).round()*5.0+(r/5.0).round()*5.0)/2.0,
-                    translation: (l/5.0).round()*5.0 + (r-l)/2.0,
+                    translation: (l + r) / 2.0,
                     ..Default::default()
                 },
                 sprite: Sprite {
-                    custom_size: Some(Vec2::new(w as f32 * 10., h as f32 * 10.) / 2.0),
+                    custom_size: Some(size),
                     ..Default::default()
                 },
                 ..Default::default()
             },
-            Gate::Not,
-            Collider::cuboid(w as f32 * 10.0 / 4.0, h as f32 * 10.0 / 4.0),
+            WireSprite { wire: e },
+            Collider::cuboid(size.x / 2.0, size.y / 2.0),
             Name::from("wire"),
         ));
     }
@@ -359,6 +452,12 @@ pub struct WireNode;
 #[derive(Component)]
 pub struct UnFinalised;
 
+/// the rendered image of a finalised wire, pointing back at the wire it shows
+#[derive(Component)]
+pub struct WireSprite {
+    pub wire: Entity,
+}
+
 #[derive(Resource, Component)]
 pub struct Wire {
     pub nodes: Vec<Entity>,
@@ -397,192 +496,355 @@ fn spawn_ui(mut c: Commands, assets: Res<Assets>) {
         GatePalette,
     ))
     .with_children(|p| {
-        for g in [Gate::And, Gate::Or, Gate::Not] {
-            p.spawn((
-                ButtonBundle {
-                    style: Style {
-                        flex_direction: FlexDirection::Column,
-                        size: Size::new(Val::Auto, Val::Percent(100.0)),
-                        // padding: UiRect::all(Val::Px(1.0)),
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
-                        ..Default::default()
-                    },
-                    background_color: Color::NONE.into(),
-                    focus_policy: FocusPolicy::Pass,
-                    ..Default::default()
+        for (heading, gates) in [
+            (
+                "gates",
+                &[Gate::And, Gate::Or, Gate::Not, Gate::Nand, Gate::Nor][..],
+            ),
+            ("wiring", &[Gate::Tunnel, Gate::Input, Gate::Output]),
+            ("bus", &bus::PARTS),
+            ("registers", &[Gate::Register]),
+            ("counters and shifters", &counter::PARTS),
+            ("memory", &memory::PARTS),
+            ("arithmetic", &arith::PARTS),
+            ("plexers", &plexer::PARTS),
+        ] {
+            spawn_palette_heading(p, &assets, heading.to_string());
+            for &g in gates {
+                let image = assets.gate_image(g, &Params::default());
+                spawn_palette_button(p, &assets, g.title(), image).insert(g);
+            }
+        }
+    });
+}
+
+/// a name over a group of palette entries
+pub fn spawn_palette_heading<'w, 's, 'a>(
+    p: &'a mut ChildBuilder<'w, 's, '_>,
+    assets: &Assets,
+    heading: String,
+) -> EntityCommands<'w, 's, 'a> {
+    p.spawn(
+        TextBundle::from_section(
+            heading,
+            TextStyle {
+                font: assets.font.clone(),
+                font_size: 10.0,
+                color: Color::rgb(0.9, 0.85, 0.7),
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::horizontal(Val::Px(10.0)),
+            ..Default::default()
+        }),
+    )
+}
+
+/// a palette entry. clicking it picks up whatever part the caller inserts on the button
+pub fn spawn_palette_button<'w, 's, 'a>(
+    p: &'a mut ChildBuilder<'w, 's, '_>,
+    assets: &Assets,
+    title: String,
+    image: Handle<Image>,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut button = p.spawn(ButtonBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            size: Size::new(Val::Auto, Val::Percent(100.0)),
+            // padding: UiRect::all(Val::Px(1.0)),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        background_color: Color::NONE.into(),
+        focus_policy: FocusPolicy::Pass,
+        ..Default::default()
+    });
+    button.with_children(|p| {
+        p.spawn(TextBundle {
+            text: Text::from_section(
+                title,
+                TextStyle {
+                    font: assets.font.clone(),
+                    color: Color::rgb(0.6, 0.5, 0.4),
+                    font_size: 10.0,
                 },
-                g,
-            ))
-            .with_children(|p| {
-                p.spawn(TextBundle {
-                    text: Text::from_section(
-                        format!("{g:#?} Gate"),
-                        TextStyle {
-                            font: assets.font.clone(),
-                            color: Color::rgb(0.6, 0.5, 0.4),
-                            font_size: 10.0,
-                        },
-                    ),
-                    style: Style {
-                        margin: UiRect {
-                            bottom: Val::Px(5.0),
-                            top: Val::Px(2.0),
-                            ..Default::default()
-                        },
-                        // align_items: AlignItems::Center,
-                        position: UiRect {
-                            // left: Val::Percent(-25.0),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    },
-                    focus_policy: FocusPolicy::Pass,
+            ),
+            style: Style {
+                margin: UiRect {
+                    bottom: Val::Px(5.0),
+                    top: Val::Px(2.0),
                     ..Default::default()
-                });
-                p.spawn(ImageBundle {
-                    style: Style {
-                        size: Size::new(Val::Auto, Val::Percent(100.0)),
-                        ..Default::default()
-                    },
-                    image: assets.gate_image(g).into(),
-                    focus_policy: FocusPolicy::Pass,
+                },
+                // align_items: AlignItems::Center,
+                position: UiRect {
+                    // left: Val::Percent(-25.0),
                     ..Default::default()
-                });
-            });
-        }
+                },
+                ..Default::default()
+            },
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        });
+        p.spawn(ImageBundle {
+            style: Style {
+                size: Size::new(Val::Auto, Val::Percent(100.0)),
+                ..Default::default()
+            },
+            image: image.into(),
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        });
     });
+    button
 }
 
 fn spawn_gate(
     mut c: Commands,
     assets: Res<Assets>,
-    buttons: Query<(&Interaction, &GlobalTransform, &Gate), Changed<Interaction>>,
+    buttons: Query<
+        (&Interaction, &GlobalTransform, &Gate, Option<&Instance>),
+        Changed<Interaction>,
+    >,
 ) {
-    for (button, pos, g) in buttons.iter() {
+    for (button, pos, g, instance) in buttons.iter() {
         match button {
             Interaction::Clicked => {
                 bevy::prelude::info!("spawning");
-                c.spawn((
-                    SpriteBundle {
-                        texture: assets.gate_image(*g).clone(),
-                        transform: Transform {
-                            // scale: Vec3::splat(4.0),
-                            translation: Vec3::new(10000.0, 10000.0, pos.translation().z),
-                            ..Default::default()
-                        },
-                        sprite: Sprite {
-                            custom_size: Some(Vec2::new(110., 110.) / 2.0),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    },
-                    UnPlaced(Vec2::splat(0.0)),
+                c.insert_resource(PlaceKind(*g, instance.cloned()));
+                c.insert_resource(NextState(Tool::Place));
+                let mut e = spawn_gate_entity(
+                    &mut c,
+                    &assets,
                     *g,
-                    Collider::cuboid(110.0 / 4.0, 110.0 / 4.0),
-                ));
+                    Params::default(),
+                    Vec3::new(10000.0, 10000.0, pos.translation().z),
+                    Orientation::default(),
+                );
+                e.insert(UnPlaced(Vec2::splat(0.0)));
+                if let Some(instance) = instance {
+                    e.insert(instance.clone());
+                }
             }
             _ => (),
         }
     }
 }
 
-fn unplace_gate(
+pub fn spawn_gate_entity<'w, 's, 'a>(
+    c: &'a mut Commands<'w, 's>,
+    assets: &Assets,
+    g: Gate,
+    params: Params,
+    translation: Vec3,
+    orientation: Orientation,
+) -> EntityCommands<'w, 's, 'a> {
+    let size = g.size(&params);
+    let mut e = c.spawn((
+        SpriteBundle {
+            texture: assets.gate_image(g, &params),
+            transform: Transform {
+                // scale: Vec3::splat(4.0),
+                translation,
+                ..Default::default()
+            },
+            sprite: Sprite {
+                custom_size: Some(size),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        g,
+        params,
+        orientation,
+        Collider::cuboid(size.x / 2.0, size.y / 2.0),
+    ));
+    e.with_children(|p| spawn_part_children(p, assets, g, &params, orientation));
+    e
+}
+
+/// the pins of a part, and the pin names and title of a box part
+pub fn spawn_part_children(
+    p: &mut ChildBuilder,
+    assets: &Assets,
+    g: Gate,
+    params: &Params,
+    orientation: Orientation,
+) {
+    spawn_pins(p, &g.pins(params), orientation);
+    if let Some(layout) = g.layout(params) {
+        spawn_pin_names(p, assets.font.clone(), &layout, orientation);
+        spawn_box_title(p, assets.font.clone(), g.title(), layout.size);
+    }
+}
+
+/// shift adds to the selection or takes things out of it, without picking anything up
+fn select_gate(
     mut c: Commands,
     rapier_context: Res<RapierContext>,
     mou: Res<Input<MouseButton>>,
-    q_camera: Query<(&Camera, &GlobalTransform)>,
-    windows: Res<Windows>,
-    gates: Query<(&Transform, Entity), With<Gate>>,
+    keys: Res<Input<KeyCode>>,
+    cursor: CanvasCursor,
+    gates: Query<&Transform, With<Gate>>,
+    selected: Query<Entity, With<Selected>>,
 ) {
-    let (camera, camera_transform) = q_camera.single();
-    if mou.just_pressed(MouseButton::Left) {
-        let wnd = windows.get_primary().unwrap();
-        if let Some(p) = wnd.cursor_position() {
-            // get the size of the window
-            let window_size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
-            // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
-            let ndc = (p / window_size) * 2.0 - Vec2::ONE;
-            // matrix for undoing the projection and camera transform
-            let ndc_to_world =
-                camera_transform.compute_matrix() * camera.projection_matrix().inverse();
-            // use it to convert ndc to world-space coordinates
-            let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
-            // reduce it to a 2D value
-            let world_pos: Vec2 = world_pos.truncate();
-
+    if !mou.just_pressed(MouseButton::Left) || cursor.over_ui() {
+        return;
+    }
+    if let Some(world_pos) = cursor.world_pos() {
+        if keys.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
             rapier_context.intersections_with_point(world_pos, QueryFilter::default(), |e| {
-                c.entity(e).insert(UnPlaced(
-                    world_pos
-                        - gates
-                            // .iter()
-                            // .find(|k| k.1 == e)
-                            .get(e)
-                            .unwrap()
-                            .0
-                            .translation
-                            .truncate(),
-                ));
+                if selected.get(e).is_ok() {
+                    c.entity(e).remove::<Selected>();
+                } else {
+                    c.entity(e).insert(Selected);
+                }
                 false
             });
+            return;
+        }
+        selected.iter().for_each(|e| {
+            c.entity(e).remove::<Selected>();
+        });
+
+        rapier_context.intersections_with_point(world_pos, QueryFilter::default(), |e| {
+            // wires can be selected, but only gates get picked up
+            if let Ok(t) = gates.get(e) {
+                c.entity(e)
+                    .insert(UnPlaced(world_pos - t.translation.truncate()));
+            }
+            c.entity(e).insert(Selected);
+            false
+        });
+    }
+}
+
+/// the place tool drops another gate of the last picked kind wherever the canvas is clicked
+#[allow(clippy::too_many_arguments)]
+fn place_gate(
+    mut c: Commands,
+    assets: Res<Assets>,
+    mou: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    cursor: CanvasCursor,
+    grid: Res<GridSettings>,
+    kind: Res<PlaceKind>,
+    unplaced: Query<(), With<UnPlaced>>,
+) {
+    if !mou.just_pressed(MouseButton::Left) || cursor.over_ui() || !unplaced.is_empty() {
+        return;
+    }
+    if let Some(world_pos) = cursor.world_pos() {
+        let world_pos = grid.snap_placement(world_pos, &keys);
+        let mut e = spawn_gate_entity(
+            &mut c,
+            &assets,
+            kind.0,
+            Params::default(),
+            world_pos.extend(0.0),
+            Orientation::default(),
+        );
+        e.insert(UnPlaced(Vec2::splat(0.0)));
+        if let Some(instance) = &kind.1 {
+            e.insert(instance.clone());
         }
     }
 }
 
 fn handle_unplaced(
     mut c: Commands,
-    mut unplaced_gate: Query<(&mut Transform, Entity, &Gate, &UnPlaced)>,
+    mut unplaced_gate: Query<(&mut Transform, Entity, &UnPlaced), With<Gate>>,
     mou: Res<Input<MouseButton>>,
-    windows: Res<Windows>,
-    q_camera: Query<(&Camera, &GlobalTransform)>,
+    keys: Res<Input<KeyCode>>,
+    grid: Res<GridSettings>,
+    cursor: CanvasCursor,
     // buttons: Query<(&Interaction, &Gate)>,
     palette: Query<&Interaction, With<GatePalette>>,
 ) {
-    let (camera, camera_transform) = q_camera.single();
-    if let Ok((mut pos, e, _g, upos)) = unplaced_gate.get_single_mut() {
+    if let Ok((mut pos, e, upos)) = unplaced_gate.get_single_mut() {
         if mou.just_released(MouseButton::Left) {
             bevy::prelude::info!("just released");
             // try to place
             if palette.iter().any(|p| *p == Interaction::Hovered) {
                 // if still in the button, just delete it
-                c.entity(e).despawn();
+                c.entity(e).despawn_recursive();
                 // bevy::prelude::info!("despawning");
             } else {
                 c.entity(e).remove::<UnPlaced>();
                 // bevy::prelude::info!("placed e: {e:?} pos: {:?}", pos.translation.truncate());
             }
         } else if mou.pressed(MouseButton::Left) {
-            let wnd = windows.get_primary().unwrap();
-            if let Some(p) = wnd.cursor_position() {
-                // get the size of the window
-                let window_size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
-                // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
-                let ndc = (p / window_size) * 2.0 - Vec2::ONE;
-                // matrix for undoing the projection and camera transform
-                let ndc_to_world =
-                    camera_transform.compute_matrix() * camera.projection_matrix().inverse();
-                // use it to convert ndc to world-space coordinates
-                let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
-                // reduce it to a 2D value
-                let world_pos: Vec2 = world_pos.truncate();
-
-                let world_pos = world_pos - upos.0;
+            if let Some(world_pos) = cursor.world_pos() {
+                let world_pos = grid.snap_placement(world_pos - upos.0, &keys);
                 // bevy::prelude::info!("upos: {}, e: {:?}", upos.0, e);
                 pos.translation = Vec3::new(world_pos.x, world_pos.y, pos.translation.z);
-
-                pos.translation /= 5.0;
-                pos.translation = Vec3::new(
-                    pos.translation.x.round(),
-                    pos.translation.y.round(),
-                    pos.translation.z,
-                );
-                pos.translation *= 5.0;
-                // bevy::prelude::info!("{}", format!("{:#?}", pos.translation));
             }
         }
     }
 }
 
+/// the mouse as seen by the canvas
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct CanvasCursor<'w, 's> {
+    windows: Res<'w, Windows>,
+    q_camera: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+    ui: Query<'w, 's, &'static Interaction, Or<(With<GatePalette>, With<Toolbar>)>>,
+}
+
+impl<'w, 's> CanvasCursor<'w, 's> {
+    /// cursor position in world coordinates, if it is inside the window
+    pub fn world_pos(&self) -> Option<Vec2> {
+        let (camera, camera_transform) = self.q_camera.single();
+        let wnd = self.windows.get_primary()?;
+        let p = wnd.cursor_position()?;
+        // get the size of the window
+        let window_size = Vec2::new(wnd.width(), wnd.height());
+        // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
+        let ndc = (p / window_size) * 2.0 - Vec2::ONE;
+        // matrix for undoing the projection and camera transform
+        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
+        // use it to convert ndc to world-space coordinates
+        let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
+        // reduce it to a 2D value
+        Some(world_pos.truncate())
+    }
+
+    /// clicks over the palette or the toolbar are not meant for the canvas
+    pub fn over_ui(&self) -> bool {
+        self.ui.iter().any(|i| *i != Interaction::None)
+    }
+}
+
+fn zoom_camera(
+    mut wheel: EventReader<MouseWheel>,
+    mut q_camera: Query<&mut OrthographicProjection, With<Camera>>,
+) {
+    let scroll = wheel.iter().map(|e| e.y).sum::<f32>();
+    if scroll == 0.0 {
+        return;
+    }
+    let mut proj = q_camera.single_mut();
+    proj.scale = (proj.scale * 0.9f32.powf(scroll)).clamp(0.1, 10.0);
+}
+
+fn highlight_selected(
+    mut sprites: Query<&mut Sprite>,
+    added: Query<Entity, Added<Selected>>,
+    removed: RemovedComponents<Selected>,
+) {
+    for e in removed.iter() {
+        if let Ok(mut sprite) = sprites.get_mut(e) {
+            sprite.color = Color::WHITE;
+        }
+    }
+    for e in added.iter() {
+        if let Ok(mut sprite) = sprites.get_mut(e) {
+            sprite.color = SELECTED_TINT;
+        }
+    }
+}
+
 // #[cfg(debug_assertions)]
 
 #[derive(Resource, AssetCollection)]
@@ -595,28 +857,248 @@ pub struct Assets {
     pub not_gate: Handle<Image>,
     #[asset(path = "fonts/VarelaRound-Regular.ttf")]
     pub font: Handle<Font>,
+    // listed one by one, the web build can not load a folder
+    #[asset(paths("libraries/gates.gatelib"), collection(typed))]
+    pub libraries: Vec<Handle<ComponentLibrary>>,
 }
 
 impl Assets {
-    fn gate_image(&self, g: Gate) -> Handle<Image> {
+    pub fn gate_image(&self, g: Gate, params: &Params) -> Handle<Image> {
+        if let Some(layout) = g.layout(params) {
+            return box_image(layout.rows);
+        }
         match g {
             Gate::And => self.and_gate.clone(),
             Gate::Or => self.or_gate.clone(),
             Gate::Not => self.not_gate.clone(),
+            Gate::Nand => NAND_IMAGE.typed(),
+            Gate::Nor => NOR_IMAGE.typed(),
+            Gate::Tunnel => TUNNEL_IMAGE.typed(),
+            Gate::Input => INPUT_IMAGE.typed(),
+            Gate::Output => OUTPUT_IMAGE.typed(),
+            // the real size is only known once the definition is looked up
+            _ => box_image(1),
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, Component, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Component, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Gate {
     And,
     Or,
     Not,
+    Nand,
+    Nor,
+    /// joins every net that has a tunnel with the same name, the name being the tunnel's label
+    Tunnel,
+    /// ports of the circuit, named by their label. on the top level they are switches and lamps,
+    /// in a subcircuit they become the pins of its instances
+    Input,
+    Output,
+    /// an instance of a circuit from the [`crate::subcircuit::Library`], named by its [`Instance`] component
+    Subcircuit,
+    /// takes `d` on the rising edge of `clk`. one bit wide it is a d flip-flop
+    Register,
+    /// the parts of [`crate::arith`]
+    HalfAdder,
+    FullAdder,
+    Adder,
+    Subtractor,
+    Comparator,
+    Shifter,
+    Multiplier,
+    Alu,
+    /// the parts of [`crate::plexer`]
+    Mux,
+    Demux,
+    Decoder,
+    Encoder,
+    PriorityEncoder,
+    /// the parts of [`crate::memory`], with their contents in a [`crate::memory::Memory`]
+    Ram,
+    Rom,
+    /// the parts of [`crate::counter`]
+    Counter,
+    Sipo,
+    Piso,
+    /// the parts of [`crate::bus`]
+    TriStateBuffer,
+    ControlledInverter,
+}
+
+impl Gate {
+    pub fn pins(&self, params: &Params) -> Vec<PinSpec> {
+        if let Some(layout) = self.layout(params) {
+            return layout.pins;
+        }
+        match self {
+            Gate::And | Gate::Or | Gate::Nand | Gate::Nor => gate_inputs(params.inputs)
+                .into_iter()
+                .map(|y| PinSpec::input(-25.0, y))
+                .chain([PinSpec::output(25.0, 0.0)])
+                .collect(),
+            Gate::Not => vec![PinSpec::input(-25.0, 0.0), PinSpec::output(25.0, 0.0)],
+            Gate::Tunnel => vec![PinSpec::input(-10.0, 0.0)],
+            Gate::Input => vec![PinSpec::output(10.0, 0.0)],
+            Gate::Output => vec![PinSpec::input(-10.0, 0.0)],
+            _ => vec![],
+        }
+    }
+
+    /// parts drawn as a box with named pins
+    pub fn layout(&self, params: &Params) -> Option<BoxLayout> {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        if let Some((inputs, outputs)) = arith::pin_names(*self) {
+            return Some(BoxLayout::new(names(inputs), names(outputs)));
+        }
+        if let Some((inputs, outputs)) = bus::pin_names(*self) {
+            return Some(BoxLayout::new(names(inputs), names(outputs)));
+        }
+        if let Some((inputs, outputs)) = counter::pin_names(*self) {
+            return Some(BoxLayout::new(names(inputs), names(outputs)));
+        }
+        if let Some((inputs, outputs)) = memory::pin_names(*self) {
+            return Some(BoxLayout::new(names(inputs), names(outputs)));
+        }
+        if let Some((inputs, outputs)) = plexer::pin_names(*self, params) {
+            return Some(BoxLayout::new(inputs, outputs));
+        }
+        match self {
+            Gate::Register => Some(BoxLayout::new(names(&["d", "clk"]), names(&["q"]))),
+            _ => None,
+        }
+    }
+
+    /// size of the sprite on the canvas, one sprite pixel per grid step
+    pub fn size(&self, params: &Params) -> Vec2 {
+        if let Some(layout) = self.layout(params) {
+            return layout.size;
+        }
+        match self {
+            Gate::And | Gate::Or | Gate::Not | Gate::Nand | Gate::Nor => {
+                Vec2::new(110., 110.) / 2.0
+            }
+            Gate::Tunnel | Gate::Input | Gate::Output => Vec2::new(25.0, 15.0),
+            _ => BoxLayout::new(vec![], vec![]).size,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            Gate::And | Gate::Or | Gate::Not => format!("{self:?} Gate"),
+            Gate::Nand => "NAND Gate".to_string(),
+            Gate::Nor => "NOR Gate".to_string(),
+            Gate::Alu => "ALU".to_string(),
+            Gate::Sipo => "SIPO Register".to_string(),
+            Gate::Piso => "PISO Register".to_string(),
+            Gate::TriStateBuffer => "Tri-state Buffer".to_string(),
+            // HalfAdder becomes Half Adder
+            g => format!("{g:?}").chars().fold(String::new(), |mut s, ch| {
+                if ch.is_uppercase() && !s.is_empty() {
+                    s.push(' ');
+                }
+                s.push(ch);
+                s
+            }),
+        }
+    }
+
+    /// whether the part remembers anything between steps, ports aside
+    pub fn has_state(&self) -> bool {
+        matches!(
+            self,
+            Gate::Register | Gate::Counter | Gate::Sipo | Gate::Piso | Gate::Ram
+        )
+    }
+
+    /// which of the [`Params`] mean something for this part
+    pub fn param_kinds(&self) -> ParamKinds {
+        let kinds = match self {
+            Gate::And | Gate::Or | Gate::Nand | Gate::Nor => ParamKinds {
+                width: true,
+                inputs: Some(GATE_INPUTS),
+                ..Default::default()
+            },
+            Gate::Not | Gate::Output | Gate::TriStateBuffer | Gate::ControlledInverter => {
+                ParamKinds {
+                    width: true,
+                    ..Default::default()
+                }
+            }
+            Gate::Input | Gate::Register | Gate::Counter | Gate::Sipo | Gate::Piso => ParamKinds {
+                width: true,
+                initial: true,
+                ..Default::default()
+            },
+            Gate::Adder | Gate::Subtractor | Gate::Multiplier | Gate::Alu => ParamKinds {
+                width: true,
+                ..Default::default()
+            },
+            Gate::Comparator => ParamKinds {
+                width: true,
+                modes: arith::COMPARE_MODES,
+                ..Default::default()
+            },
+            Gate::Shifter => ParamKinds {
+                width: true,
+                modes: arith::SHIFT_MODES,
+                ..Default::default()
+            },
+            Gate::Mux | Gate::Demux => ParamKinds {
+                width: true,
+                select: Some(plexer::SELECT_BITS),
+                ..Default::default()
+            },
+            Gate::Decoder | Gate::Encoder | Gate::PriorityEncoder => ParamKinds {
+                select: Some(plexer::SELECT_BITS),
+                ..Default::default()
+            },
+            Gate::Ram | Gate::Rom => ParamKinds {
+                width: true,
+                address: Some(memory::ADDRESS_BITS),
+                ..Default::default()
+            },
+            Gate::Tunnel | Gate::Subcircuit | Gate::HalfAdder | Gate::FullAdder => {
+                ParamKinds::default()
+            }
+        };
+        // ports and tunnels are only connections, an instance takes as long as its insides
+        let delay = !matches!(
+            self,
+            Gate::Input | Gate::Output | Gate::Tunnel | Gate::Subcircuit
+        );
+        ParamKinds { delay, ..kinds }
+    }
+}
+
+/// how many inputs an and, or, nand or nor gate can have
+pub const GATE_INPUTS: RangeInclusive<u8> = 2..=5;
+
+/// where the inputs of an and or or gate go. two inputs stay where they always were,
+/// more get packed one pin spacing apart
+fn gate_inputs(n: u8) -> Vec<f32> {
+    match n {
+        0..=2 => vec![10.0, -10.0],
+        n => (0..n)
+            .map(|i| (n - 1) as f32 * 5.0 - i as f32 * 10.0)
+            .collect(),
+    }
 }
 
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub struct UnPlaced(Vec2);
 
+/// the gate last clicked on. keyboard actions like rotation apply to it
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Selected;
+
+pub const SELECTED_TINT: Color = Color::rgb(1.0, 0.8, 0.6);
+
 #[derive(Component)]
 pub struct GatePalette;
+
+/// what the place tool puts down, the last gate picked from the palette
+#[derive(Resource)]
+pub struct PlaceKind(pub Gate, pub Option<Instance>);
@@ -0,0 +1,421 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{
+        App, Commands, DespawnRecursiveExt, Entity, Input, KeyCode, Or, Plugin, Query, Res, ResMut,
+        Resource, Transform, Vec2, Vec3, With, Without,
+    },
+    transform::TransformBundle,
+};
+use iyes_loopless::prelude::ConditionSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    label::{spawn_label, Attached, Label, LabelOn},
+    memory::Memory,
+    orientation::Orientation,
+    params::Params,
+    run::{
+        spawn_gate_entity, Assets, GameState, Gate, UnFinalised, UnPlaced, Wire, WireNode,
+        WireSprite,
+    },
+    subcircuit::{Imported, Instance, Library},
+    vectors::TestVectors,
+    view::View,
+};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CircuitFile(PathBuf::from("circuit.ron")))
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(save_circuit)
+                    .with_system(load_circuit)
+                    .into(),
+            );
+    }
+}
+
+/// where ctrl+s and ctrl+o write and read the circuit
+#[derive(Resource)]
+pub struct CircuitFile(pub PathBuf);
+
+/// everything placed on the canvas, in the form it is stored on disk
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct CircuitDoc {
+    pub gates: Vec<GateDoc>,
+    pub wires: Vec<WireDoc>,
+    #[serde(default)]
+    pub labels: Vec<LabelDoc>,
+    /// the definitions instances in this circuit refer to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub subcircuits: BTreeMap<String, CircuitDoc>,
+    /// the component libraries some of those definitions were imported from
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub libraries: BTreeMap<String, Imported>,
+    /// the test vectors of the circuit, see [`crate::vectors::Vectors`]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tests: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GateDoc {
+    pub gate: Gate,
+    pub pos: [f32; 2],
+    #[serde(default)]
+    pub orientation: Orientation,
+    /// the definition of a [`Gate::Subcircuit`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub def: Option<String>,
+    #[serde(default, skip_serializing_if = "Params::is_default")]
+    pub params: Params,
+    /// the words of a memory part
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub memory: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WireDoc {
+    pub nodes: Vec<[f32; 2]>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LabelDoc {
+    pub text: String,
+    /// position on the canvas, or offset from the target for attached labels
+    pub pos: [f32; 2],
+    pub on: LabelOn,
+}
+
+impl CircuitDoc {
+    pub fn read(path: &Path) -> Result<Self> {
+        let s = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&s)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, s)?;
+        Ok(())
+    }
+
+    pub fn collect(q: &CircuitQuery) -> Self {
+        Self::collect_where(q, |_| true)
+    }
+
+    /// only the gates, wires and free labels `keep` says yes to. labels on kept gates and wires come along
+    pub fn collect_where(q: &CircuitQuery, keep: impl Fn(Entity) -> bool) -> Self {
+        let (gate_entities, gates): (Vec<_>, Vec<_>) = q
+            .sorted_gates()
+            .into_iter()
+            .filter(|(e, _)| keep(*e))
+            .unzip();
+        let (wire_entities, wires): (Vec<_>, Vec<_>) = q
+            .sorted_wires()
+            .into_iter()
+            .filter(|(e, _)| keep(*e))
+            .unzip();
+
+        let mut labels = q
+            .labels
+            .iter()
+            .filter_map(|(e, l, t, a)| {
+                let (on, pos) = match a {
+                    None if keep(e) => (LabelOn::Free, t.translation.truncate()),
+                    None => return None,
+                    Some(a) => {
+                        let on = if let Some(i) = gate_entities.iter().position(|&e| e == a.to) {
+                            LabelOn::Gate(i)
+                        } else {
+                            LabelOn::Wire(wire_entities.iter().position(|&e| e == a.to)?)
+                        };
+                        (on, a.offset)
+                    }
+                };
+                Some(LabelDoc {
+                    text: l.text.clone(),
+                    pos: pos.to_array(),
+                    on,
+                })
+            })
+            .collect::<Vec<_>>();
+        labels.sort_by(|a, b| a.text.cmp(&b.text).then(cmp_pos(a.pos, b.pos)));
+
+        Self {
+            gates,
+            wires,
+            labels,
+            ..Default::default()
+        }
+    }
+
+    /// spawns the circuit in the current canvas. wires go through the usual finalisation
+    pub fn spawn(&self, c: &mut Commands, assets: &Assets) {
+        let gates = self
+            .gates
+            .iter()
+            .map(|g| {
+                let mut e = spawn_gate_entity(
+                    c,
+                    assets,
+                    g.gate,
+                    g.params,
+                    Vec2::from(g.pos).extend(0.0),
+                    g.orientation,
+                );
+                if let Some(def) = &g.def {
+                    e.insert(Instance { def: def.clone() });
+                }
+                if !g.memory.is_empty() {
+                    e.insert(Memory(g.memory.clone()));
+                }
+                e.id()
+            })
+            .collect::<Vec<_>>();
+        let mut wires = vec![];
+        for w in self.wires.iter() {
+            let nodes = w
+                .nodes
+                .iter()
+                .map(|&p| {
+                    c.spawn((
+                        WireNode,
+                        TransformBundle {
+                            local: Transform {
+                                translation: Vec3::new(p[0], p[1], 0.0),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                    ))
+                    .id()
+                })
+                .collect();
+            wires.push(c.spawn((Wire { nodes }, UnFinalised)).id());
+        }
+        for l in self.labels.iter() {
+            let pos = Vec2::from(l.pos);
+            let to = match l.on {
+                LabelOn::Free => None,
+                LabelOn::Gate(i) => gates.get(i).copied(),
+                LabelOn::Wire(i) => wires.get(i).copied(),
+            };
+            // attached labels get moved next to their target once it is around
+            let attached = to.map(|to| Attached { to, offset: pos });
+            spawn_label(c, assets, l.text.clone(), pos, attached);
+        }
+    }
+}
+
+/// building circuits by hand, for tests
+#[cfg(test)]
+impl CircuitDoc {
+    /// puts `gate` at `pos`, with a label when `name` is not empty, and gives its index
+    pub fn add_gate(&mut self, gate: Gate, params: Params, pos: [f32; 2], name: &str) -> usize {
+        self.gates.push(GateDoc {
+            gate,
+            pos,
+            orientation: Orientation::default(),
+            def: None,
+            params,
+            memory: vec![],
+        });
+        let index = self.gates.len() - 1;
+        if !name.is_empty() {
+            self.labels.push(LabelDoc {
+                text: name.to_string(),
+                pos: [0.0, 20.0],
+                on: LabelOn::Gate(index),
+            });
+        }
+        index
+    }
+
+    /// a straight wire from output pin `out` of gate `from` to input pin `input` of gate `to`.
+    /// the gates have to be placed so that it passes no other pin
+    pub fn connect(&mut self, (from, out): (usize, usize), (to, input): (usize, usize)) {
+        let pin = |g: usize, dir: crate::pin::PinDir, n: usize| {
+            let gate = &self.gates[g];
+            let spec = gate.gate.pins(&gate.params)[..]
+                .iter()
+                .filter(|s| s.dir == dir)
+                .nth(n)
+                .copied()
+                .expect("the gate has no such pin");
+            crate::netlist::pin_pos(gate, &spec).into()
+        };
+        let nodes = vec![
+            pin(from, crate::pin::PinDir::Out, out),
+            pin(to, crate::pin::PinDir::In, input),
+        ];
+        self.wires.push(WireDoc { nodes });
+    }
+}
+
+/// everything [`CircuitDoc::collect`] reads from the world
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct CircuitQuery<'w, 's> {
+    gates: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Gate,
+            &'static Transform,
+            &'static Orientation,
+            &'static Params,
+            Option<&'static Instance>,
+            Option<&'static Memory>,
+        ),
+        Without<UnPlaced>,
+    >,
+    wires: Query<'w, 's, (Entity, &'static Wire), Without<UnFinalised>>,
+    nodes: Query<'w, 's, &'static Transform, With<WireNode>>,
+    labels: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Label,
+            &'static Transform,
+            Option<&'static Attached>,
+        ),
+    >,
+}
+
+impl<'w, 's> CircuitQuery<'w, 's> {
+    /// the gates in the order [`CircuitDoc::collect`] lists them
+    pub fn gate_entities(&self) -> Vec<Entity> {
+        self.sorted_gates().into_iter().map(|(e, _)| e).collect()
+    }
+
+    /// the wires in the order [`CircuitDoc::collect`] lists them
+    pub fn wire_entities(&self) -> Vec<Entity> {
+        self.sorted_wires().into_iter().map(|(e, _)| e).collect()
+    }
+
+    /// sorted by position, so spawning a document and collecting it again gives back the same document
+    fn sorted_gates(&self) -> Vec<(Entity, GateDoc)> {
+        let mut gates = self
+            .gates
+            .iter()
+            .map(|(e, g, t, o, params, i, memory)| {
+                let doc = GateDoc {
+                    gate: *g,
+                    pos: t.translation.truncate().to_array(),
+                    orientation: *o,
+                    def: i.map(|i| i.def.clone()),
+                    params: *params,
+                    memory: memory.map(|m| m.0.clone()).unwrap_or_default(),
+                };
+                (e, doc)
+            })
+            .collect::<Vec<_>>();
+        gates.sort_by(|(_, a), (_, b)| cmp_pos(a.pos, b.pos));
+        gates
+    }
+
+    fn sorted_wires(&self) -> Vec<(Entity, WireDoc)> {
+        let mut wires = self
+            .wires
+            .iter()
+            .map(|(e, w)| {
+                let nodes = w
+                    .nodes
+                    .iter()
+                    .filter_map(|&n| self.nodes.get(n).ok())
+                    .map(|t| t.translation.truncate().to_array())
+                    .collect();
+                (e, WireDoc { nodes })
+            })
+            .collect::<Vec<_>>();
+        wires.sort_by(|(_, a), (_, b)| {
+            a.nodes
+                .iter()
+                .zip(b.nodes.iter())
+                .map(|(&p, &q)| cmp_pos(p, q))
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.nodes.len().cmp(&b.nodes.len()))
+        });
+        wires
+    }
+}
+
+fn cmp_pos(a: [f32; 2], b: [f32; 2]) -> Ordering {
+    a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1]))
+}
+
+/// everything that is part of the circuit on the canvas
+pub type OnCanvas = Or<(
+    With<Orientation>,
+    With<Label>,
+    With<WireSprite>,
+    With<Wire>,
+    With<WireNode>,
+)>;
+
+fn ctrl_pressed(keys: &Input<KeyCode>) -> bool {
+    keys.any_pressed([KeyCode::LControl, KeyCode::RControl])
+}
+
+fn save_circuit(
+    keys: Res<Input<KeyCode>>,
+    file: Res<CircuitFile>,
+    library: Res<Library>,
+    view: Res<View>,
+    vectors: Res<TestVectors>,
+    circuit: CircuitQuery,
+) {
+    if !(ctrl_pressed(&keys) && keys.just_pressed(KeyCode::S)) {
+        return;
+    }
+    // inside an instance the canvas shows a definition, the file gets the top level circuit
+    let canvas = CircuitDoc::collect(&circuit);
+    let mut doc = view.root(&canvas).clone();
+    doc.subcircuits = library.defs.clone();
+    doc.libraries = library.imported.clone();
+    doc.tests = vectors.0.clone();
+    match doc.write(&file.0) {
+        Ok(()) => bevy::prelude::info!("saved circuit to {:?}", file.0),
+        Err(e) => bevy::prelude::error!("could not save circuit: {e:?}"),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn load_circuit(
+    mut c: Commands,
+    keys: Res<Input<KeyCode>>,
+    file: Res<CircuitFile>,
+    assets: Res<Assets>,
+    mut library: ResMut<Library>,
+    mut view: ResMut<View>,
+    mut vectors: ResMut<TestVectors>,
+    old: Query<Entity, OnCanvas>,
+) {
+    if !(ctrl_pressed(&keys) && keys.just_pressed(KeyCode::O)) {
+        return;
+    }
+    let doc = match CircuitDoc::read(&file.0) {
+        Ok(doc) => doc,
+        Err(e) => {
+            bevy::prelude::error!("could not load circuit: {e:?}");
+            return;
+        }
+    };
+    old.iter().for_each(|e| c.entity(e).despawn_recursive());
+    view.levels.clear();
+    library.defs = doc.subcircuits.clone();
+    library.imported = doc.libraries.clone();
+    vectors.0 = doc.tests.clone();
+    doc.spawn(&mut c, &assets);
+}
@@ -0,0 +1,735 @@
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::{
+        App, Color, Commands, Component, Entity, Input, MouseButton, Plugin, Query,
+        ReflectComponent, Res, ResMut, Resource, Without,
+    },
+    reflect::Reflect,
+    sprite::Sprite,
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use bevy_rapier2d::prelude::RapierContext;
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    arith, bus, counter, memory,
+    netlist::{CircuitNets, Netlist, UnionFind},
+    params::Params,
+    pin::PinDir,
+    plexer,
+    run::{CanvasCursor, GameState, Gate, Selected, WireSprite},
+    save::CircuitDoc,
+    subcircuit::{ports, Library},
+    tool::{entity_under_cursor, Tool},
+    view::View,
+};
+
+pub struct SimPlugin;
+
+impl Plugin for SimPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Simulation::default())
+            .insert_resource(CanvasScope::default())
+            .register_type::<Held>()
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(rebuild_sim)
+                    .with_system(step_sim)
+                    .with_system(show_values)
+                    .with_system(show_held)
+                    .with_system(sim_ui)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_in_state(Tool::Poke)
+                    .with_system(poke)
+                    .into(),
+            );
+    }
+}
+
+/// what a net carries
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Value {
+    pub bits: u64,
+    pub width: u8,
+    /// bits that nothing drives. they read as low
+    pub float: u64,
+    /// bits that more than one part drives at once
+    pub conflict: u64,
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Self::bit(false)
+    }
+}
+
+impl Value {
+    /// bits above `width` are dropped
+    pub fn new(bits: u64, width: u8) -> Self {
+        Self {
+            bits: bits & mask(width),
+            width,
+            float: 0,
+            conflict: 0,
+        }
+    }
+
+    /// a value that nothing drives
+    pub fn floating(width: u8) -> Self {
+        Self {
+            bits: 0,
+            width,
+            float: mask(width),
+            conflict: 0,
+        }
+    }
+
+    pub fn bit(b: bool) -> Self {
+        Self::new(b as u64, 1)
+    }
+
+    pub fn is_high(&self) -> bool {
+        self.bits != 0
+    }
+
+    pub fn is_floating(&self) -> bool {
+        self.float & mask(self.width) == mask(self.width)
+    }
+
+    /// what a net carries when both `self` and `other` drive it. every bit may have one driver,
+    /// a bit driven by both is a conflict
+    pub fn join(self, other: Value) -> Self {
+        let driven = |v: &Value| !v.float & mask(v.width);
+        let (a, b) = (driven(&self), driven(&other));
+        let width = self.width.max(other.width);
+        Self {
+            bits: self.bits & a | other.bits & b,
+            width,
+            float: !(a | b) & mask(width),
+            conflict: self.conflict | other.conflict | a & b,
+        }
+    }
+}
+
+/// the lowest `width` bits set
+pub fn mask(width: u8) -> u64 {
+    match width {
+        0 => 0,
+        w if w >= 64 => u64::MAX,
+        w => (1 << w) - 1,
+    }
+}
+
+/// a part that does something in the simulation, wherever it sits in the hierarchy
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlatPart {
+    pub gate: Gate,
+    pub params: Params,
+    /// the stored contents of a memory
+    pub memory: Vec<u64>,
+    /// the flat net on every pin
+    pub pins: Vec<usize>,
+    pub dirs: Vec<PinDir>,
+    /// where the part comes from: the scope, and its gate index in the scope's circuit
+    pub scope: usize,
+    pub gate_index: usize,
+}
+
+/// the top level circuit or one subcircuit instance in it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scope {
+    /// gate indices of the instances leading here from the top level
+    pub path: Vec<usize>,
+    pub def: Option<String>,
+    pub netlist: Netlist,
+    /// the flat net of every net in `netlist`
+    pub nets: Vec<usize>,
+    /// the flat part of every gate. ports, tunnels and instances have none
+    pub parts: Vec<Option<usize>>,
+    /// the scope of every instance gate
+    pub instances: Vec<Option<usize>>,
+}
+
+/// a circuit with every subcircuit instance expanded in place. instances of the same definition
+/// get parts and nets of their own, so each keeps its own state
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FlatCircuit {
+    pub parts: Vec<FlatPart>,
+    pub net_count: usize,
+    /// the top level circuit comes first
+    pub scopes: Vec<Scope>,
+    /// the top level ports by name, with their part
+    pub inputs: Vec<(String, usize)>,
+    pub outputs: Vec<(String, usize)>,
+}
+
+impl FlatCircuit {
+    pub fn flatten(doc: &CircuitDoc, library: &Library) -> Self {
+        let mut nets = UnionFind::default();
+        let mut flat = FlatCircuit::default();
+        flat.add_scope(doc, library, vec![], None, None, &mut nets);
+
+        // connected nets become one
+        let mut index = HashMap::new();
+        let mut renumber = |n: &mut usize| {
+            let root = nets.find(*n);
+            let next = index.len();
+            *n = *index.entry(root).or_insert(next);
+        };
+        flat.parts
+            .iter_mut()
+            .for_each(|p| p.pins.iter_mut().for_each(&mut renumber));
+        flat.scopes
+            .iter_mut()
+            .for_each(|s| s.nets.iter_mut().for_each(&mut renumber));
+        flat.net_count = index.len();
+
+        for port in ports(doc) {
+            if let Some(part) = flat.scopes[0].parts[port.gate] {
+                match port.dir {
+                    PinDir::In => flat.inputs.push((port.name, part)),
+                    PinDir::Out => flat.outputs.push((port.name, part)),
+                }
+            }
+        }
+        flat
+    }
+
+    /// the parts driving every flat net
+    pub fn drivers(&self) -> HashMap<usize, Vec<usize>> {
+        let mut drivers: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, part) in self.parts.iter().enumerate() {
+            for (&n, &d) in part.pins.iter().zip(part.dirs.iter()) {
+                if d == PinDir::Out {
+                    drivers.entry(n).or_default().push(i);
+                }
+            }
+        }
+        drivers
+    }
+
+    /// the scope reached by going into the instances on `path` from the top level
+    pub fn scope_at(&self, path: &[usize]) -> Option<usize> {
+        let scope = path
+            .iter()
+            .try_fold(0, |s, &gi| *self.scopes.get(s)?.instances.get(gi)?)?;
+        (scope < self.scopes.len()).then_some(scope)
+    }
+
+    /// `outer` are the nets on the pins of the instance, when this is not the top level
+    fn add_scope(
+        &mut self,
+        doc: &CircuitDoc,
+        library: &Library,
+        path: Vec<usize>,
+        def: Option<String>,
+        outer: Option<&[usize]>,
+        nets: &mut UnionFind,
+    ) -> usize {
+        let netlist = Netlist::build(doc, library);
+        let local = netlist.nets.iter().map(|_| nets.add()).collect::<Vec<_>>();
+        if let Some(outer) = outer {
+            for (port, &o) in ports(doc).iter().zip(outer) {
+                nets.union(local[netlist.pin_nets[port.gate][0]], o);
+            }
+        }
+
+        let scope = self.scopes.len();
+        self.scopes.push(Scope {
+            path: path.clone(),
+            def,
+            netlist: Netlist::default(),
+            nets: local.clone(),
+            parts: vec![None; doc.gates.len()],
+            instances: vec![None; doc.gates.len()],
+        });
+        for (gi, g) in doc.gates.iter().enumerate() {
+            let pins = netlist.pin_nets[gi]
+                .iter()
+                .map(|&n| local[n])
+                .collect::<Vec<_>>();
+            match g.gate {
+                Gate::Tunnel => {}
+                // inside an instance the ports are just where the pins connect
+                Gate::Input | Gate::Output if outer.is_some() => {}
+                Gate::Subcircuit => {
+                    let inner = g
+                        .def
+                        .as_ref()
+                        .and_then(|name| Some((name, library.defs.get(name)?)));
+                    // a definition that contains itself is left empty, like a missing one
+                    let inner = inner.filter(|(name, inner)| !library.reaches(inner, name));
+                    if let Some((name, inner)) = inner {
+                        let mut path = path.clone();
+                        path.push(gi);
+                        let child = self.add_scope(
+                            inner,
+                            library,
+                            path,
+                            Some(name.clone()),
+                            Some(&pins),
+                            nets,
+                        );
+                        self.scopes[scope].instances[gi] = Some(child);
+                    }
+                }
+                _ => {
+                    self.scopes[scope].parts[gi] = Some(self.parts.len());
+                    self.parts.push(FlatPart {
+                        gate: g.gate,
+                        params: g.params,
+                        memory: g.memory.clone(),
+                        pins,
+                        dirs: library.pins(g).iter().map(|p| p.dir).collect(),
+                        scope,
+                        gate_index: gi,
+                    });
+                }
+            }
+        }
+        self.scopes[scope].netlist = netlist;
+        scope
+    }
+}
+
+/// what a part remembers between steps
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PartState {
+    /// the switch of an input port, what an output port last saw, or what a register holds
+    pub value: Value,
+    /// the clock level at the last step, to tell a rising edge
+    pub clock: bool,
+    /// every word of a memory
+    pub memory: Vec<u64>,
+}
+
+/// a recorded net keeps at most this many changes, dropping the oldest half when it has more
+pub const MAX_CHANGES: usize = 100_000;
+
+/// the values some nets took over time, recorded as the simulation steps
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace {
+    /// the flat nets being recorded
+    pub nets: Vec<usize>,
+    /// for every recorded net, the time of each change and the value it changed to
+    pub changes: Vec<Vec<(u64, Value)>>,
+}
+
+/// runs a [`FlatCircuit`] one gate delay at a time
+#[derive(Clone, Debug, Default)]
+pub struct Sim {
+    pub circuit: FlatCircuit,
+    /// the value on every flat net
+    pub values: Vec<Value>,
+    pub states: Vec<PartState>,
+    /// steps taken so far
+    pub time: u64,
+    pub trace: Trace,
+}
+
+impl Sim {
+    pub fn new(circuit: FlatCircuit) -> Self {
+        let states = circuit
+            .parts
+            .iter()
+            .map(|p| PartState {
+                value: p.params.initial_value(),
+                clock: false,
+                memory: match p.gate {
+                    Gate::Ram | Gate::Rom => memory::initial_contents(&p.params, &p.memory),
+                    _ => vec![],
+                },
+            })
+            .collect();
+        Self {
+            values: vec![Value::default(); circuit.net_count],
+            states,
+            circuit,
+            time: 0,
+            trace: Trace::default(),
+        }
+    }
+
+    /// starts recording `nets` from now on, forgetting whatever was recorded before
+    pub fn trace(&mut self, nets: Vec<usize>) {
+        self.trace = Trace {
+            changes: vec![vec![]; nets.len()],
+            nets,
+        };
+        self.record();
+    }
+
+    fn record(&mut self) {
+        let Trace { nets, changes } = &mut self.trace;
+        for (&n, changes) in nets.iter().zip(changes.iter_mut()) {
+            let v = self.values.get(n).copied().unwrap_or_default();
+            if changes.last().map(|&(_, last)| last) != Some(v) {
+                changes.push((self.time, v));
+            }
+            if changes.len() > MAX_CHANGES {
+                changes.drain(..MAX_CHANGES / 2);
+            }
+        }
+    }
+
+    /// every part looks at the nets as they were and drives its outputs. returns whether anything changed
+    pub fn step(&mut self) -> bool {
+        let mut next = vec![Value::floating(1); self.circuit.net_count];
+        for (part, state) in self.circuit.parts.iter().zip(self.states.iter_mut()) {
+            let pins = || part.pins.iter().zip(part.dirs.iter());
+            let inputs = pins()
+                .filter(|(_, &d)| d == PinDir::In)
+                .map(|(&n, _)| self.values[n])
+                .collect::<Vec<_>>();
+            let outputs = eval(part.gate, &part.params, &inputs, state);
+            for ((&n, _), v) in pins().filter(|(_, &d)| d == PinDir::Out).zip(outputs) {
+                next[n] = next[n].join(v);
+            }
+        }
+        self.time += 1;
+        let changed = next != self.values;
+        self.values = next;
+        self.record();
+        changed
+    }
+
+    /// steps until nothing changes any more. false if the circuit still had not settled after `max_steps`
+    pub fn settle(&mut self, max_steps: usize) -> bool {
+        (0..max_steps).any(|_| !self.step())
+    }
+
+    /// the value of a net of the netlist of a scope
+    pub fn net_value(&self, scope: usize, net: usize) -> Option<Value> {
+        let n = *self.circuit.scopes.get(scope)?.nets.get(net)?;
+        self.values.get(n).copied()
+    }
+
+    /// the flat nets that more than one part drives
+    pub fn conflicts(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.values.len()).filter(|&n| self.values[n].conflict != 0)
+    }
+
+    pub fn set_input(&mut self, name: &str, value: Value) -> bool {
+        match self.circuit.inputs.iter().find(|(n, _)| n == name) {
+            Some(&(_, part)) => {
+                self.states[part].value =
+                    Value::new(value.bits, self.circuit.parts[part].params.width);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// a simulation of `circuit` with the input switches of this one, where the names match
+    pub fn rebuilt(&self, circuit: FlatCircuit) -> Sim {
+        let mut sim = Sim::new(circuit);
+        for (name, part) in self.circuit.inputs.iter() {
+            sim.set_input(name, self.states[*part].value);
+        }
+        sim
+    }
+
+    pub fn output(&self, name: &str) -> Option<Value> {
+        let &(_, part) = self.circuit.outputs.iter().find(|(n, _)| n == name)?;
+        let p = &self.circuit.parts[part];
+        self.values.get(*p.pins.first()?).copied()
+    }
+}
+
+fn eval(gate: Gate, params: &Params, inputs: &[Value], state: &mut PartState) -> Vec<Value> {
+    let width = params.width;
+    let input = |i: usize| inputs.get(i).copied().unwrap_or_default();
+    match gate {
+        Gate::And => vec![Value::new(
+            inputs.iter().fold(u64::MAX, |acc, v| acc & v.bits),
+            width,
+        )],
+        Gate::Or => vec![Value::new(
+            inputs.iter().fold(0, |acc, v| acc | v.bits),
+            width,
+        )],
+        Gate::Not => vec![Value::new(!input(0).bits, width)],
+        Gate::Nand => vec![Value::new(
+            !inputs.iter().fold(u64::MAX, |acc, v| acc & v.bits),
+            width,
+        )],
+        Gate::Nor => vec![Value::new(
+            !inputs.iter().fold(0, |acc, v| acc | v.bits),
+            width,
+        )],
+        Gate::Input => vec![state.value],
+        Gate::Output => {
+            state.value = input(0);
+            vec![]
+        }
+        Gate::Register => {
+            let (d, clk) = (input(0), input(1).is_high());
+            if clk && !state.clock {
+                state.value = Value::new(d.bits, width);
+            }
+            state.clock = clk;
+            vec![state.value]
+        }
+        Gate::HalfAdder
+        | Gate::FullAdder
+        | Gate::Adder
+        | Gate::Subtractor
+        | Gate::Comparator
+        | Gate::Shifter
+        | Gate::Multiplier
+        | Gate::Alu => arith::eval(gate, params, inputs),
+        Gate::Mux | Gate::Demux | Gate::Decoder | Gate::Encoder | Gate::PriorityEncoder => {
+            plexer::eval(gate, params, inputs)
+        }
+        Gate::Ram | Gate::Rom => memory::eval(gate, params, inputs, state),
+        Gate::Counter | Gate::Sipo | Gate::Piso => counter::eval(gate, params, inputs, state),
+        Gate::TriStateBuffer | Gate::ControlledInverter => bus::eval(gate, params, inputs),
+        Gate::Tunnel | Gate::Subcircuit => vec![],
+    }
+}
+
+/// the output bits of `gate` on `inputs`, each read as a 64 bit value, for the tests of the parts
+#[cfg(test)]
+pub fn eval_bits(gate: Gate, params: &Params, inputs: &[u64]) -> Vec<u64> {
+    eval_bits_on(gate, params, &mut PartState::default(), inputs)
+}
+
+/// [`eval_bits`] for a part that keeps `state` from one step to the next
+#[cfg(test)]
+pub fn eval_bits_on(
+    gate: Gate,
+    params: &Params,
+    state: &mut PartState,
+    inputs: &[u64],
+) -> Vec<u64> {
+    let inputs = inputs
+        .iter()
+        .map(|&b| Value::new(b, 64))
+        .collect::<Vec<_>>();
+    eval(gate, params, &inputs, state)
+        .iter()
+        .map(|v| v.bits)
+        .collect()
+}
+
+/// the simulation of what is on the canvas
+#[derive(Resource)]
+pub struct Simulation {
+    pub sim: Sim,
+    pub running: bool,
+    pub steps_per_frame: u32,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            sim: Sim::default(),
+            running: true,
+            steps_per_frame: 1,
+        }
+    }
+}
+
+/// the scope of the simulation that the canvas shows. none while the canvas holds a definition
+/// with changes that are not saved, which the simulation knows nothing of
+#[derive(Resource, Default)]
+pub struct CanvasScope(pub Option<usize>);
+
+fn rebuild_sim(
+    nets: Res<CircuitNets>,
+    library: Res<Library>,
+    view: Res<View>,
+    mut simulation: ResMut<Simulation>,
+    mut canvas_scope: ResMut<CanvasScope>,
+) {
+    if !nets.is_changed() && !library.is_changed() && !view.is_changed() {
+        return;
+    }
+    // only the top level circuit is simulated, whatever the canvas shows
+    let circuit = FlatCircuit::flatten(view.root(&nets.doc), &library);
+    if circuit != simulation.sim.circuit {
+        simulation.sim = simulation.sim.rebuilt(circuit);
+    }
+    let scope = match view.def() {
+        None => Some(0),
+        Some(def) if library.defs.get(def) == Some(&nets.doc) => {
+            simulation.sim.circuit.scope_at(&view.path())
+        }
+        Some(_) => None,
+    };
+    if canvas_scope.0 != scope {
+        canvas_scope.0 = scope;
+    }
+}
+
+fn step_sim(mut simulation: ResMut<Simulation>) {
+    let Simulation {
+        sim,
+        running,
+        steps_per_frame,
+    } = &mut *simulation;
+    if *running {
+        for _ in 0..*steps_per_frame {
+            sim.step();
+        }
+    }
+}
+
+const HIGH_TINT: Color = Color::rgb(1.5, 2.0, 1.0);
+const CONFLICT_TINT: Color = Color::rgb(2.5, 0.5, 0.5);
+
+/// wires and ports light up when their net is high, and turn red when it has several drivers
+fn show_values(
+    simulation: Res<Simulation>,
+    nets: Res<CircuitNets>,
+    canvas_scope: Res<CanvasScope>,
+    wire_sprites: Query<(Entity, &WireSprite)>,
+    gates: Query<(Entity, &Gate)>,
+    mut sprites: Query<&mut Sprite, Without<Selected>>,
+) {
+    let top_net = |net: usize| {
+        canvas_scope
+            .0
+            .and_then(|scope| simulation.sim.net_value(scope, net))
+            .unwrap_or_default()
+    };
+    let wires = wire_sprites.iter().filter_map(|(e, ws)| {
+        let wi = nets.wires.iter().position(|&w| w == ws.wire)?;
+        Some((e, top_net(nets.netlist.wire_nets[wi])))
+    });
+    let ports = gates
+        .iter()
+        .filter(|(_, g)| matches!(g, Gate::Input | Gate::Output))
+        .filter_map(|(e, _)| {
+            let gi = nets.gates.iter().position(|&g| g == e)?;
+            Some((e, top_net(*nets.netlist.pin_nets[gi].first()?)))
+        });
+    for (e, v) in wires.chain(ports).collect::<Vec<_>>() {
+        if let Ok(mut sprite) = sprites.get_mut(e) {
+            let color = if v.conflict != 0 {
+                CONFLICT_TINT
+            } else if v.is_high() {
+                HIGH_TINT
+            } else {
+                Color::WHITE
+            };
+            if sprite.color != color {
+                sprite.color = color;
+            }
+        }
+    }
+}
+
+/// what a part with state holds in the simulation, kept on its entity for the inspector
+#[derive(Component, Reflect, Default, Clone, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct Held {
+    pub value: u64,
+    pub width: u8,
+    /// the clock level the part saw last
+    pub clock: bool,
+}
+
+fn show_held(
+    mut c: Commands,
+    simulation: Res<Simulation>,
+    nets: Res<CircuitNets>,
+    canvas_scope: Res<CanvasScope>,
+    mut gates: Query<(&Gate, Option<&mut Held>)>,
+) {
+    let scope = match canvas_scope
+        .0
+        .and_then(|s| simulation.sim.circuit.scopes.get(s))
+    {
+        Some(scope) => scope,
+        None => return,
+    };
+    for (&e, part) in nets.gates.iter().zip(scope.parts.iter()) {
+        let (g, held) = match gates.get_mut(e) {
+            Ok(g) => g,
+            Err(_) => continue,
+        };
+        if !matches!(g, Gate::Register | Gate::Counter | Gate::Sipo | Gate::Piso) {
+            continue;
+        }
+        let state = match part.and_then(|p| simulation.sim.states.get(p)) {
+            Some(state) => state,
+            None => continue,
+        };
+        let now = Held {
+            value: state.value.bits,
+            width: state.value.width,
+            clock: state.clock,
+        };
+        match held {
+            Some(mut held) if *held != now => *held = now,
+            None => {
+                c.entity(e).insert(now);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// flips the switch of a one bit top level input port, and counts a wider one up
+fn poke(
+    mou: Res<Input<MouseButton>>,
+    rapier_context: Res<RapierContext>,
+    cursor: CanvasCursor,
+    nets: Res<CircuitNets>,
+    canvas_scope: Res<CanvasScope>,
+    mut simulation: ResMut<Simulation>,
+) {
+    if !mou.just_pressed(MouseButton::Left) || cursor.over_ui() {
+        return;
+    }
+    let sim = &simulation.sim;
+    let part = entity_under_cursor(&rapier_context, &cursor)
+        .and_then(|e| nets.gates.iter().position(|&g| g == e))
+        .and_then(|gi| *sim.circuit.scopes.get(canvas_scope.0?)?.parts.get(gi)?)
+        .filter(|&part| sim.circuit.parts[part].gate == Gate::Input);
+    if let Some(part) = part {
+        let v = &mut simulation.sim.states[part].value;
+        *v = Value::new(v.bits.wrapping_add(1), v.width);
+    }
+}
+
+fn sim_ui(mut egui_context: ResMut<EguiContext>, mut simulation: ResMut<Simulation>) {
+    let mut running = simulation.running;
+    let mut steps = simulation.steps_per_frame;
+    let mut step = false;
+    let mut reset = false;
+    egui::Window::new("simulation").show(egui_context.ctx_mut(), |ui| {
+        ui.label(format!("time: {}", simulation.sim.time));
+        let conflicts = simulation.sim.conflicts().count();
+        if conflicts > 0 {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("{conflicts} nets driven by more than one part"),
+            );
+        }
+        ui.checkbox(&mut running, "running");
+        ui.add(egui::Slider::new(&mut steps, 1..=100).text("steps per frame"));
+        ui.horizontal(|ui| {
+            step = ui.button("step").clicked();
+            reset = ui.button("reset").clicked();
+        });
+        ui.label("the poke tool flips top level input ports");
+    });
+    if running != simulation.running || steps != simulation.steps_per_frame {
+        simulation.running = running;
+        simulation.steps_per_frame = steps;
+    }
+    if step {
+        simulation.sim.step();
+    }
+    if reset {
+        simulation.sim = Sim::new(simulation.sim.circuit.clone());
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use bevy::prelude::{App, Local, Plugin, Res, ResMut};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    params::Params,
+    pin::PinDir,
+    run::{GameState, Gate},
+    sim::{FlatCircuit, FlatPart, Simulation},
+    timing::{self, part_name, Path},
+};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(stats_ui)
+                .into(),
+        );
+    }
+}
+
+/// what a part is counted as. ports and tunnels are not counted
+fn counted_name(gate: Gate) -> Option<String> {
+    match gate {
+        Gate::Input | Gate::Output | Gate::Tunnel => None,
+        g => Some(g.title()),
+    }
+}
+
+/// how many of each kind of part the circuit has. expanded, the parts inside every instance
+/// count, otherwise each instance counts once under the name of its definition
+pub fn gate_counts(circuit: &FlatCircuit, expanded: bool) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    if expanded {
+        for p in circuit.parts.iter() {
+            if let Some(name) = counted_name(p.gate) {
+                *counts.entry(name).or_default() += 1;
+            }
+        }
+        return counts;
+    }
+    let top = match circuit.scopes.first() {
+        Some(top) => top,
+        None => return counts,
+    };
+    for (part, instance) in top.parts.iter().zip(top.instances.iter()) {
+        let name = match (part, instance) {
+            (Some(p), _) => counted_name(circuit.parts[*p].gate),
+            (_, Some(s)) => {
+                let def = circuit.scopes[*s].def.as_deref().unwrap_or("?");
+                Some(format!("{def} (subcircuit)"))
+            }
+            _ => None,
+        };
+        if let Some(name) = name {
+            *counts.entry(name).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// the gates a change has to go through from an input port or a part with state to an output
+/// port or a part with state, at most
+pub fn depth(circuit: &FlatCircuit) -> Result<Option<Path>> {
+    let levels = |p: &FlatPart| (p.gate.param_kinds().delay && !p.gate.has_state()) as u32;
+    Ok(timing::longest_paths(circuit, levels)?.critical)
+}
+
+/// how many inputs read every net
+pub fn fan_outs(circuit: &FlatCircuit) -> Vec<usize> {
+    let mut fan_outs = vec![0; circuit.net_count];
+    for p in circuit.parts.iter() {
+        for (&n, &d) in p.pins.iter().zip(p.dirs.iter()) {
+            if d == PinDir::In {
+                fan_outs[n] += 1;
+            }
+        }
+    }
+    fan_outs
+}
+
+fn fan_in(part: &FlatPart) -> usize {
+    part.dirs.iter().filter(|&&d| d == PinDir::In).count()
+}
+
+/// a rough count of the transistors of a part built in static cmos: 2 per input of a nand or nor,
+/// 24 per flip-flop, 6 per bit of ram, 28 per full adder, and muxes and decoders out of gates
+pub fn transistors(gate: Gate, params: &Params) -> u64 {
+    let w = params.width as u64;
+    let n = params.inputs as u64;
+    let s = params.select as u64;
+    let lines = 1u64 << s.min(16);
+    // an n input and or or is a nand or nor and an inverter
+    let gate_of = |inputs: u64| 2 * inputs + 2;
+    match gate {
+        Gate::Input | Gate::Output | Gate::Tunnel | Gate::Subcircuit => 0,
+        Gate::Not => 2 * w,
+        Gate::Nand | Gate::Nor => 2 * n * w,
+        Gate::And | Gate::Or => gate_of(n) * w,
+        Gate::TriStateBuffer => 4 * w + 2,
+        Gate::ControlledInverter => 12 * w,
+        Gate::HalfAdder => 18,
+        Gate::FullAdder => 28,
+        Gate::Adder => 28 * w,
+        Gate::Subtractor | Gate::Comparator => 30 * w,
+        Gate::Multiplier => 6 * w * w + 28 * w * w.saturating_sub(1),
+        Gate::Alu => 60 * w,
+        Gate::Shifter => 12 * w * (64 - w.leading_zeros() as u64),
+        Gate::Mux => (lines * gate_of(s + 1) + gate_of(lines)) * w + 2 * s,
+        Gate::Demux => lines * gate_of(s + 1) * w + 2 * s,
+        Gate::Decoder => lines * gate_of(s) + 2 * s,
+        Gate::Encoder => s * gate_of(lines / 2),
+        Gate::PriorityEncoder => 2 * s * gate_of(lines / 2),
+        Gate::Register | Gate::Sipo => 24 * w,
+        Gate::Piso => 36 * w,
+        Gate::Counter => 70 * w,
+        Gate::Ram => {
+            let address = params.address as u64;
+            6 * w * (1 << address.min(32)) + (1 << address.min(32)) * gate_of(address)
+        }
+        Gate::Rom => w * (1 << (params.address as u64).min(32)),
+    }
+}
+
+/// everything about the size and shape of a circuit
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub counts: BTreeMap<String, usize>,
+    pub instance_counts: BTreeMap<String, usize>,
+    pub depth: Option<Result<Option<Path>>>,
+    pub fan_outs: Vec<usize>,
+    pub max_fan_in: usize,
+    pub transistors: u64,
+}
+
+impl Stats {
+    pub fn new(circuit: &FlatCircuit) -> Self {
+        Self {
+            counts: gate_counts(circuit, true),
+            instance_counts: gate_counts(circuit, false),
+            depth: Some(depth(circuit)),
+            fan_outs: fan_outs(circuit),
+            max_fan_in: circuit.parts.iter().map(fan_in).max().unwrap_or(0),
+            transistors: circuit
+                .parts
+                .iter()
+                .map(|p| transistors(p.gate, &p.params))
+                .sum(),
+        }
+    }
+}
+
+struct StatsWindow {
+    expanded: bool,
+    max_fan_out: usize,
+    circuit: FlatCircuit,
+    stats: Stats,
+}
+
+impl Default for StatsWindow {
+    fn default() -> Self {
+        Self {
+            expanded: true,
+            max_fan_out: 4,
+            circuit: FlatCircuit::default(),
+            stats: Stats::default(),
+        }
+    }
+}
+
+/// counts of the simulated circuit, worked out again whenever it changes
+fn stats_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<StatsWindow>,
+    simulation: Res<Simulation>,
+) {
+    let window = &mut *window;
+    if window.stats.depth.is_none() || window.circuit != simulation.sim.circuit {
+        window.circuit = simulation.sim.circuit.clone();
+        window.stats = Stats::new(&window.circuit);
+    }
+    let circuit = &window.circuit;
+    let stats = &window.stats;
+    egui::Window::new("statistics").show(egui_context.ctx_mut(), |ui| {
+        ui.checkbox(&mut window.expanded, "count the insides of subcircuits");
+        let counts = if window.expanded {
+            &stats.counts
+        } else {
+            &stats.instance_counts
+        };
+        egui::Grid::new("gate counts").striped(true).show(ui, |ui| {
+            for (name, count) in counts.iter() {
+                ui.label(name);
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+            ui.label("total");
+            ui.label(counts.values().sum::<usize>().to_string());
+            ui.end_row();
+        });
+        ui.separator();
+        match &stats.depth {
+            Some(Ok(Some(path))) => {
+                ui.label(format!("depth: {} gates", path.delay));
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::YELLOW, format!("depth: {e}"));
+            }
+            _ => {
+                ui.label("depth: 0 gates");
+            }
+        }
+        ui.label(format!("largest fan-in: {}", stats.max_fan_in));
+        ui.label(format!(
+            "largest fan-out: {}",
+            stats.fan_outs.iter().max().unwrap_or(&0)
+        ));
+        ui.label(format!("about {} transistors", stats.transistors));
+        ui.separator();
+        ui.add(egui::Slider::new(&mut window.max_fan_out, 1..=32).text("fan-out limit"));
+        let drivers = circuit.drivers();
+        for (net, &fan_out) in stats.fan_outs.iter().enumerate() {
+            if fan_out <= window.max_fan_out {
+                continue;
+            }
+            let driver = match drivers.get(&net).and_then(|d| d.first()) {
+                Some(&d) => part_name(circuit, d),
+                None => "nothing".to_string(),
+            };
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!("the net driven by {driver} goes to {fan_out} inputs"),
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{save::CircuitDoc, subcircuit::Library};
+
+    /// `s = a'·b + a·b'` and `c = a·b`, out of two nots, three ands and an or
+    fn half_adder() -> CircuitDoc {
+        CircuitDoc::read("tests/circuits/half_adder.ron".as_ref()).unwrap()
+    }
+
+    fn flatten(doc: &CircuitDoc) -> FlatCircuit {
+        let library = Library {
+            defs: doc.subcircuits.clone(),
+            ..Default::default()
+        };
+        FlatCircuit::flatten(doc, &library)
+    }
+
+    /// two half adders and an or, the parts of a full adder, not wired up
+    fn two_instances() -> CircuitDoc {
+        let mut doc = CircuitDoc::default();
+        doc.subcircuits.insert("half".to_string(), half_adder());
+        for y in [0.0, -200.0] {
+            let g = doc.add_gate(Gate::Subcircuit, Params::default(), [0.0, y], "");
+            doc.gates[g].def = Some("half".to_string());
+        }
+        doc.add_gate(Gate::Or, Params::default(), [300.0, -100.0], "");
+        doc
+    }
+
+    fn counts(pairs: &[(&str, usize)]) -> BTreeMap<String, usize> {
+        pairs.iter().map(|&(n, c)| (n.to_string(), c)).collect()
+    }
+
+    #[test]
+    fn counts_leave_out_ports() {
+        let circuit = flatten(&half_adder());
+        let expected = counts(&[("And Gate", 3), ("Not Gate", 2), ("Or Gate", 1)]);
+        assert_eq!(gate_counts(&circuit, true), expected);
+        assert_eq!(gate_counts(&circuit, false), expected);
+    }
+
+    #[test]
+    fn counts_with_subcircuits() {
+        let circuit = flatten(&two_instances());
+        assert_eq!(
+            gate_counts(&circuit, true),
+            counts(&[("And Gate", 6), ("Not Gate", 4), ("Or Gate", 3)])
+        );
+        assert_eq!(
+            gate_counts(&circuit, false),
+            counts(&[("Or Gate", 1), ("half (subcircuit)", 2)])
+        );
+    }
+
+    #[test]
+    fn depth_and_fan_out() {
+        let circuit = flatten(&half_adder());
+        // a not, an and and the or between an input and s
+        let path = depth(&circuit).unwrap().unwrap();
+        assert_eq!(path.delay, 3);
+        assert_eq!(path.parts.len(), 5);
+        // each input goes to a not and two ands, every other net to one input or output
+        let mut fan_outs = fan_outs(&circuit);
+        fan_outs.sort();
+        assert_eq!(fan_outs, [1, 1, 1, 1, 1, 1, 3, 3]);
+    }
+
+    #[test]
+    fn transistor_counts() {
+        let params = |width: u8, inputs: u8| Params {
+            width,
+            inputs,
+            ..Default::default()
+        };
+        assert_eq!(transistors(Gate::Nand, &params(1, 2)), 4);
+        assert_eq!(transistors(Gate::And, &params(1, 3)), 8);
+        assert_eq!(transistors(Gate::Not, &params(8, 2)), 16);
+        assert_eq!(transistors(Gate::Adder, &params(4, 2)), 112);
+        assert_eq!(transistors(Gate::Input, &params(8, 2)), 0);
+        // two nots, three two input ands and an or, in each half adder
+        let total = |circuit: &FlatCircuit| {
+            circuit
+                .parts
+                .iter()
+                .map(|p| transistors(p.gate, &p.params))
+                .sum::<u64>()
+        };
+        assert_eq!(total(&flatten(&half_adder())), 28);
+        assert_eq!(Stats::new(&flatten(&two_instances())).transistors, 62);
+    }
+}
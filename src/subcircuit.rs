@@ -0,0 +1,354 @@
+use std::collections::{BTreeMap, HashSet};
+
+use bevy::{
+    prelude::{
+        Added, App, BuildChildren, Changed, Children, Commands, Component, DespawnRecursiveExt,
+        Entity, Handle, Image, Local, Or, Plugin, Query, Res, ResMut, Resource, With,
+    },
+    sprite::Sprite,
+    ui::Interaction,
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use bevy_rapier2d::prelude::Collider;
+use iyes_loopless::prelude::ConditionSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    images::box_image,
+    label::LabelOn,
+    library::ComponentLibrary,
+    netlist::{label_text, CircuitNets},
+    orientation::Orientation,
+    pin::{spawn_box_title, spawn_pin_names, spawn_pins, BoxLayout, PinDir, PinSpec},
+    run::{
+        spawn_palette_button, spawn_palette_heading, Assets, GameState, Gate, GatePalette,
+        Selected, WireSprite,
+    },
+    save::{CircuitDoc, CircuitQuery, GateDoc},
+    view::View,
+};
+
+pub struct SubcircuitPlugin;
+
+impl Plugin for SubcircuitPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Library::default()).add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(build_instances)
+                .with_system(sync_palette)
+                .with_system(subcircuit_ui)
+                .into(),
+        );
+    }
+}
+
+/// which definition a [`Gate::Subcircuit`] is an instance of
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+pub struct Instance {
+    pub def: String,
+}
+
+/// the circuits that can be placed as subcircuits, by name
+#[derive(Resource, Default, Clone, Debug, PartialEq)]
+pub struct Library {
+    pub defs: BTreeMap<String, CircuitDoc>,
+    /// the component libraries imported into `defs`, by library name
+    pub imported: BTreeMap<String, Imported>,
+}
+
+/// what a circuit took from a [`crate::library::ComponentLibrary`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Imported {
+    pub version: u32,
+    pub defs: Vec<String>,
+}
+
+impl Library {
+    pub fn layout(&self, def: &str) -> Option<BoxLayout> {
+        let (inputs, outputs): (Vec<_>, Vec<_>) = ports(self.defs.get(def)?)
+            .into_iter()
+            .partition(|p| p.dir == PinDir::In);
+        Some(BoxLayout::new(
+            inputs.into_iter().map(|p| p.name).collect(),
+            outputs.into_iter().map(|p| p.name).collect(),
+        ))
+    }
+
+    /// puts every definition of `lib` in the library, replacing the ones with the same name.
+    /// importing a newer version over an older one is how a library update is applied.
+    /// `placed` are the circuits open on the canvas, whose instances keep their definitions
+    pub fn import(&mut self, lib: &ComponentLibrary, placed: &[&CircuitDoc]) {
+        if let Some(old) = self.imported.get(&lib.name) {
+            // definitions dropped by the new version go, unless something still uses them
+            for def in old.defs.iter().filter(|d| !lib.defs.contains_key(*d)) {
+                if !self.instanced(def, placed.iter().copied()) {
+                    self.defs.remove(def);
+                }
+            }
+        }
+        self.defs.extend(
+            lib.defs
+                .iter()
+                .map(|(name, doc)| (name.clone(), doc.clone())),
+        );
+        self.imported.insert(
+            lib.name.clone(),
+            Imported {
+                version: lib.version,
+                defs: lib.defs.keys().cloned().collect(),
+            },
+        );
+    }
+
+    /// whether `doc` has an instance of `def`, directly or inside the definitions it is built from.
+    /// a definition that reaches itself would never end
+    pub fn reaches(&self, doc: &CircuitDoc, def: &str) -> bool {
+        let mut seen = HashSet::new();
+        let mut docs = vec![doc];
+        while let Some(doc) = docs.pop() {
+            for inner in doc.gates.iter().filter_map(|g| g.def.as_deref()) {
+                if inner == def {
+                    return true;
+                }
+                if seen.insert(inner) {
+                    docs.extend(self.defs.get(inner));
+                }
+            }
+        }
+        false
+    }
+
+    /// whether `def` has an instance in any of `docs` or in another definition
+    pub fn instanced<'a>(&self, def: &str, docs: impl IntoIterator<Item = &'a CircuitDoc>) -> bool {
+        let has = |doc: &CircuitDoc| doc.gates.iter().any(|g| g.def.as_deref() == Some(def));
+        docs.into_iter().any(has)
+            || self
+                .defs
+                .iter()
+                .any(|(name, doc)| name.as_str() != def && has(doc))
+    }
+
+    /// the definitions under the palette heading they belong to. the circuit's own come first, without one
+    pub fn groups(&self) -> Vec<(Option<String>, Vec<&String>)> {
+        let from_libraries = self
+            .imported
+            .values()
+            .flat_map(|i| i.defs.iter())
+            .collect::<HashSet<_>>();
+        let mut groups = vec![(
+            None,
+            self.defs
+                .keys()
+                .filter(|d| !from_libraries.contains(d))
+                .collect(),
+        )];
+        for (name, imported) in self.imported.iter() {
+            groups.push((
+                Some(format!("{name} v{}", imported.version)),
+                imported
+                    .defs
+                    .iter()
+                    .filter(|d| self.defs.contains_key(*d))
+                    .collect(),
+            ));
+        }
+        groups
+    }
+
+    /// like [`Gate::pins`], but instances get the pins of their definition
+    pub fn pins(&self, g: &GateDoc) -> Vec<PinSpec> {
+        match (g.gate, &g.def) {
+            (Gate::Subcircuit, Some(def)) => self.layout(def).map(|l| l.pins).unwrap_or_default(),
+            _ => g.gate.pins(&g.params),
+        }
+    }
+}
+
+/// a pin of a subcircuit, seen from the inside
+#[derive(Clone, Debug, PartialEq)]
+pub struct Port {
+    /// the input or output gate in the definition
+    pub gate: usize,
+    pub name: String,
+    pub dir: PinDir,
+}
+
+/// the input ports of a circuit from top to bottom, then its output ports.
+/// an instance has one pin for each, in this order
+pub fn ports(doc: &CircuitDoc) -> Vec<Port> {
+    let mut ports = vec![];
+    for (kind, dir, prefix) in [
+        (Gate::Input, PinDir::In, "in"),
+        (Gate::Output, PinDir::Out, "out"),
+    ] {
+        let mut these = doc
+            .gates
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.gate == kind)
+            .collect::<Vec<_>>();
+        these.sort_by(|(_, a), (_, b)| {
+            b.pos[1]
+                .total_cmp(&a.pos[1])
+                .then(a.pos[0].total_cmp(&b.pos[0]))
+        });
+        for (k, (gate, _)) in these.into_iter().enumerate() {
+            let name = label_text(doc, LabelOn::Gate(gate))
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| format!("{prefix}{k}"));
+            ports.push(Port { gate, name, dir });
+        }
+    }
+    ports
+}
+
+/// instances are drawn as a box with the pins of their definition, which is only known here
+#[allow(clippy::type_complexity)]
+fn build_instances(
+    mut c: Commands,
+    assets: Res<Assets>,
+    library: Res<Library>,
+    mut instances: Query<(
+        Entity,
+        &Instance,
+        &Orientation,
+        &mut Handle<Image>,
+        &mut Sprite,
+        Option<&Children>,
+    )>,
+    changed: Query<(), Or<(Added<Instance>, Changed<Orientation>)>>,
+) {
+    for (e, instance, o, mut texture, mut sprite, children) in instances.iter_mut() {
+        if !library.is_changed() && changed.get(e).is_err() {
+            continue;
+        }
+        let layout = library
+            .layout(&instance.def)
+            .unwrap_or_else(|| BoxLayout::new(vec![], vec![]));
+        *texture = box_image(layout.rows);
+        sprite.custom_size = Some(layout.size);
+
+        children
+            .into_iter()
+            .flatten()
+            .for_each(|&child| c.entity(child).despawn_recursive());
+        c.entity(e)
+            .insert(Collider::cuboid(layout.size.x / 2.0, layout.size.y / 2.0))
+            .with_children(|p| {
+                spawn_pins(p, &layout.pins, *o);
+                spawn_pin_names(p, assets.font.clone(), &layout, *o);
+                spawn_box_title(p, assets.font.clone(), instance.def.clone(), layout.size);
+            });
+    }
+}
+
+/// the name of a component library over its definitions in the palette
+#[derive(Component)]
+pub struct PaletteHeading;
+
+#[allow(clippy::type_complexity)]
+fn sync_palette(
+    mut c: Commands,
+    assets: Res<Assets>,
+    library: Res<Library>,
+    palette: Query<Entity, With<GatePalette>>,
+    buttons: Query<Entity, Or<((With<Instance>, With<Interaction>), With<PaletteHeading>)>>,
+) {
+    if !library.is_changed() {
+        return;
+    }
+    let palette = match palette.get_single() {
+        Ok(p) => p,
+        Err(_) => return,
+    };
+    buttons.iter().for_each(|b| c.entity(b).despawn_recursive());
+    c.entity(palette).with_children(|p| {
+        for (heading, defs) in library.groups() {
+            if let Some(heading) = heading {
+                spawn_palette_heading(p, &assets, heading).insert(PaletteHeading);
+            }
+            for def in defs {
+                let rows = library.layout(def).map(|l| l.rows).unwrap_or(1);
+                spawn_palette_button(p, &assets, def.clone(), box_image(rows))
+                    .insert((Gate::Subcircuit, Instance { def: def.clone() }));
+            }
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn subcircuit_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut name: Local<String>,
+    mut library: ResMut<Library>,
+    view: Res<View>,
+    nets: Res<CircuitNets>,
+    circuit: CircuitQuery,
+    selected: Query<Entity, With<Selected>>,
+    wire_sprites: Query<&WireSprite>,
+) {
+    // a selected wire is its sprite, the document wants the wire itself
+    let keep = selected
+        .iter()
+        .map(|e| wire_sprites.get(e).map(|ws| ws.wire).unwrap_or(e))
+        .collect::<HashSet<_>>();
+
+    egui::Window::new("subcircuit").show(egui_context.ctx_mut(), |ui| {
+        ui.label("shift+click to select, with input and output ports where the pins should be");
+        ui.horizontal(|ui| {
+            ui.label("name");
+            ui.text_edit_singleline(&mut *name);
+        });
+        let name = name.trim();
+        if keep.is_empty() {
+            ui.label("nothing selected");
+        } else {
+            let doc = CircuitDoc::collect_where(&circuit, |e| keep.contains(&e));
+            let ports = ports(&doc);
+            ui.label(format!(
+                "{} gates, {} wires, ports: {}",
+                doc.gates.len(),
+                doc.wires.len(),
+                ports
+                    .iter()
+                    .map(|p| p.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            let recursive = library.reaches(&doc, name);
+            if recursive {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    "the selection has an instance of itself",
+                );
+            } else if library.defs.contains_key(name) {
+                ui.label("replaces the definition with this name");
+            }
+            let ok = !name.is_empty() && !ports.is_empty() && !recursive;
+            if ui
+                .add_enabled(ok, egui::Button::new("save as subcircuit"))
+                .clicked()
+            {
+                library.defs.insert(name.to_string(), doc);
+            }
+        }
+
+        // everything open on the way down to the canvas counts as placed
+        let placed = view.levels.iter().map(|l| &l.doc).chain([&nets.doc]);
+        let mut remove = None;
+        for def in library.defs.keys() {
+            ui.horizontal(|ui| {
+                ui.label(def);
+                if library.instanced(def, placed.clone()) {
+                    ui.add_enabled(false, egui::Button::new("in use").small());
+                } else if ui.small_button("remove").clicked() {
+                    remove = Some(def.clone());
+                }
+            });
+        }
+        if let Some(def) = remove {
+            library.defs.remove(&def);
+        }
+    });
+}
@@ -0,0 +1,771 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::{App, Commands, Local, Plugin, Res, ResMut, Vec2};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    expr::Expr,
+    label::{gate_label_offset, LabelOn},
+    netlist::{pin_pos, CircuitNets},
+    orientation::Orientation,
+    params::Params,
+    pin::PinDir,
+    run::{Assets, GameState, Gate, GATE_INPUTS},
+    save::{CircuitDoc, GateDoc, LabelDoc, WireDoc},
+};
+
+pub struct SynthPlugin;
+
+impl Plugin for SynthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(synthesis_ui)
+                .into(),
+        );
+    }
+}
+
+/// the gates a circuit gets built from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GateSet {
+    #[default]
+    AndOrNot,
+    Nand,
+    Nor,
+}
+
+impl GateSet {
+    pub const ALL: [GateSet; 3] = [GateSet::AndOrNot, GateSet::Nand, GateSet::Nor];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GateSet::AndOrNot => "and, or, not",
+            GateSet::Nand => "nand only",
+            GateSet::Nor => "nor only",
+        }
+    }
+}
+
+/// reads `name = expression` lines. `&`, `*`, `·` and `&&` are and, `|`, `+` and `||` are or,
+/// `!` and `~` in front or `'` behind are not, and `0` and `1` are constants.
+/// not binds tightest, then and, then or
+pub fn parse_equations(text: &str) -> Result<Vec<(String, Expr)>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            let (name, expr) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: no `=`", n + 1))?;
+            let name = name.trim();
+            if !is_name(name) {
+                bail!("line {}: {name:?} is not a name", n + 1);
+            }
+            let expr = parse_expr(expr).map_err(|e| anyhow!("line {}: {e}", n + 1))?;
+            Ok((name.to_string(), expr))
+        })
+        .collect()
+}
+
+fn is_name(s: &str) -> bool {
+    s.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Const(bool),
+    And,
+    Or,
+    Not,
+    /// a `'` after what it negates
+    Prime,
+    Open,
+    Close,
+}
+
+fn tokens(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '&' | '*' | '·' => {
+                chars.next_if_eq(&'&');
+                Token::And
+            }
+            '|' | '+' => {
+                chars.next_if_eq(&'|');
+                Token::Or
+            }
+            '!' | '~' => Token::Not,
+            '\'' => Token::Prime,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '0' => Token::Const(false),
+            '1' => Token::Const(true),
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                Token::Name(name)
+            }
+            c => bail!("unexpected {c:?}"),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+pub fn parse_expr(text: &str) -> Result<Expr> {
+    let tokens = tokens(text)?;
+    let mut at = 0;
+    let expr = parse_or(&tokens, &mut at)?;
+    match tokens.get(at) {
+        None => Ok(expr),
+        Some(t) => bail!("unexpected {t:?}"),
+    }
+}
+
+fn parse_or(tokens: &[Token], at: &mut usize) -> Result<Expr> {
+    let mut terms = vec![parse_and(tokens, at)?];
+    while tokens.get(*at) == Some(&Token::Or) {
+        *at += 1;
+        terms.push(parse_and(tokens, at)?);
+    }
+    Ok(match terms.len() {
+        1 => terms.remove(0),
+        _ => Expr::Or(terms),
+    })
+}
+
+fn parse_and(tokens: &[Token], at: &mut usize) -> Result<Expr> {
+    let mut terms = vec![parse_not(tokens, at)?];
+    loop {
+        match tokens.get(*at) {
+            Some(Token::And) => *at += 1,
+            // `ab` and `a(b + c)` are products too
+            Some(Token::Name(_) | Token::Const(_) | Token::Open | Token::Not) => {}
+            _ => break,
+        }
+        terms.push(parse_not(tokens, at)?);
+    }
+    Ok(match terms.len() {
+        1 => terms.remove(0),
+        _ => Expr::And(terms),
+    })
+}
+
+fn parse_not(tokens: &[Token], at: &mut usize) -> Result<Expr> {
+    if tokens.get(*at) == Some(&Token::Not) {
+        *at += 1;
+        return Ok(!parse_not(tokens, at)?);
+    }
+    let mut expr = match tokens.get(*at) {
+        Some(Token::Name(name)) => Expr::Var(name.clone()),
+        Some(Token::Const(b)) => Expr::Const(*b),
+        Some(Token::Open) => {
+            *at += 1;
+            let expr = parse_or(tokens, at)?;
+            if tokens.get(*at) != Some(&Token::Close) {
+                bail!("missing `)`");
+            }
+            expr
+        }
+        Some(t) => bail!("unexpected {t:?}"),
+        None => bail!("the expression ends too soon"),
+    };
+    *at += 1;
+    while tokens.get(*at) == Some(&Token::Prime) {
+        *at += 1;
+        expr = !expr;
+    }
+    Ok(expr)
+}
+
+/// the sum of the rows where `ones` is set, one product of every input per row.
+/// `inputs` are the bits of the row number, the first being the most significant
+pub fn sum_of_minterms(inputs: &[String], ones: &[bool]) -> Expr {
+    let n = inputs.len();
+    let products = ones
+        .iter()
+        .enumerate()
+        .filter(|(_, &one)| one)
+        .map(|(row, _)| {
+            let literals = inputs
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let var = Expr::Var(name.clone());
+                    if row >> (n - 1 - i) & 1 == 1 {
+                        var
+                    } else {
+                        !var
+                    }
+                })
+                .collect::<Vec<_>>();
+            match literals.len() {
+                1 => literals[0].clone(),
+                _ => Expr::And(literals),
+            }
+        })
+        .collect::<Vec<_>>();
+    match products.len() {
+        0 => Expr::Const(false),
+        1 => products[0].clone(),
+        _ => Expr::Or(products),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Node {
+    Input(String),
+    Gate(Gate, Vec<usize>),
+}
+
+/// a network of gates with every shared part built once
+#[derive(Default)]
+struct Network {
+    set: GateSet,
+    nodes: Vec<Node>,
+    index: HashMap<Node, usize>,
+    /// pairs of nodes that are the inverse of each other
+    inverse: HashMap<usize, usize>,
+}
+
+impl Network {
+    fn node(&mut self, node: Node) -> usize {
+        if let Some(&i) = self.index.get(&node) {
+            return i;
+        }
+        self.nodes.push(node.clone());
+        self.index.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn not(&mut self, x: usize) -> usize {
+        if let Some(&y) = self.inverse.get(&x) {
+            return y;
+        }
+        let y = match self.set {
+            GateSet::AndOrNot => self.node(Node::Gate(Gate::Not, vec![x])),
+            GateSet::Nand => self.node(Node::Gate(Gate::Nand, vec![x, x])),
+            GateSet::Nor => self.node(Node::Gate(Gate::Nor, vec![x, x])),
+        };
+        self.inverse.insert(x, y);
+        self.inverse.insert(y, x);
+        y
+    }
+
+    /// an and of `xs` when `and`, an or otherwise. wide ones become a tree of gates
+    fn combine(&mut self, and: bool, mut xs: Vec<usize>) -> usize {
+        let most = *GATE_INPUTS.end() as usize;
+        while xs.len() > most {
+            xs = xs
+                .chunks(most)
+                .map(|chunk| self.combine(and, chunk.to_vec()))
+                .collect();
+        }
+        if xs.len() == 1 {
+            return xs[0];
+        }
+        match (self.set, and) {
+            (GateSet::AndOrNot, true) => self.node(Node::Gate(Gate::And, xs)),
+            (GateSet::AndOrNot, false) => self.node(Node::Gate(Gate::Or, xs)),
+            (GateSet::Nand, true) | (GateSet::Nor, false) => {
+                let g = if and { Gate::Nand } else { Gate::Nor };
+                let inverted = self.node(Node::Gate(g, xs));
+                self.not(inverted)
+            }
+            // de morgan: a + b is (a'b')', ab is (a' + b')'
+            (GateSet::Nand, false) | (GateSet::Nor, true) => {
+                let g = if and { Gate::Nor } else { Gate::Nand };
+                let xs = xs.into_iter().map(|x| self.not(x)).collect();
+                self.node(Node::Gate(g, xs))
+            }
+        }
+    }
+
+    /// there is no part that is always 0 or 1, so a constant is `x·x'` or `x + x'` of the first input
+    fn constant(&mut self, value: bool) -> Result<usize> {
+        let x = match self.nodes.iter().position(|n| matches!(n, Node::Input(_))) {
+            Some(x) => x,
+            None => bail!("a constant is made from an input, and there are none"),
+        };
+        let inverse = self.not(x);
+        Ok(self.combine(!value, vec![x, inverse]))
+    }
+
+    fn add(&mut self, e: &Expr) -> Result<usize> {
+        Ok(match e {
+            Expr::Var(name) => self.node(Node::Input(name.clone())),
+            Expr::Const(value) => self.constant(*value)?,
+            Expr::Not(e) => {
+                let x = self.add(e)?;
+                self.not(x)
+            }
+            Expr::And(es) | Expr::Or(es) => {
+                let xs = es.iter().map(|e| self.add(e)).collect::<Result<Vec<_>>>()?;
+                self.combine(matches!(e, Expr::And(_)), xs)
+            }
+        })
+    }
+}
+
+/// room between two columns of gates for each wire going down the gap
+const CHANNEL: f32 = 10.0;
+/// space between the pins of one column and the wires of the next gap
+const MARGIN: f32 = 20.0;
+/// how far the pins of a gate stick out from its centre
+const PIN_REACH: f32 = 25.0;
+/// gates each get a row of their own, so no wire runs through another gate's pins
+const ROW: f32 = 60.0;
+
+/// the names `e` uses, in the order they first appear
+fn variables(e: &Expr, names: &mut Vec<String>) {
+    match e {
+        Expr::Const(_) => {}
+        Expr::Var(name) => {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        Expr::Not(e) => variables(e, names),
+        Expr::And(es) | Expr::Or(es) => es.iter().for_each(|e| variables(e, names)),
+    }
+}
+
+/// a circuit made of `set` gates with an output port for each equation, simplified first.
+/// `inputs` get a port each, used or not, ahead of the other names the equations use.
+/// it is laid out in columns by depth with its top left corner at `origin`
+pub fn synthesize(
+    inputs: &[String],
+    equations: &[(String, Expr)],
+    set: GateSet,
+    origin: Vec2,
+) -> Result<CircuitDoc> {
+    let mut net = Network {
+        set,
+        ..Default::default()
+    };
+    // names that simplify away, like `a` in `a & 0`, still get their port
+    let mut names = inputs.to_vec();
+    equations.iter().for_each(|(_, e)| variables(e, &mut names));
+    for name in names {
+        net.node(Node::Input(name));
+    }
+    let outputs = equations
+        .iter()
+        .map(|(name, e)| Ok((name.clone(), net.add(&e.simplify())?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // an inverter made on the way goes unused when the inverse it would give was there already
+    let mut used = vec![false; net.nodes.len()];
+    let mut stack = outputs.iter().map(|(_, x)| *x).collect::<Vec<_>>();
+    while let Some(i) = stack.pop() {
+        if !used[i] {
+            used[i] = true;
+            if let Node::Gate(_, xs) = &net.nodes[i] {
+                stack.extend(xs);
+            }
+        }
+    }
+    let kept = |i: usize| used[i] || matches!(net.nodes[i], Node::Input(_));
+
+    // inputs first, then the gates in the order they were made, which has inputs before use
+    let mut order = (0..net.nodes.len())
+        .filter(|&i| kept(i))
+        .collect::<Vec<_>>();
+    order.sort_by_key(|&i| !matches!(net.nodes[i], Node::Input(_)));
+    let mut column = vec![0; net.nodes.len()];
+    for &i in order.iter() {
+        if let Node::Gate(_, xs) = &net.nodes[i] {
+            column[i] = 1 + xs.iter().map(|&x| column[x]).max().unwrap_or(0);
+        }
+    }
+    let last = column.iter().max().copied().unwrap_or(0) + 1;
+
+    let mut doc = CircuitDoc::default();
+    let mut gate_of = vec![0; net.nodes.len()];
+    let mut columns = vec![vec![]; last + 1];
+    let mut place = |doc: &mut CircuitDoc, gate: Gate, params: Params, col: usize, name: &str| {
+        let row = doc.gates.len();
+        doc.gates.push(GateDoc {
+            gate,
+            pos: [0.0, origin.y - row as f32 * ROW],
+            orientation: Orientation::default(),
+            def: None,
+            params,
+            memory: vec![],
+        });
+        if !name.is_empty() {
+            let size = gate.size(&params);
+            doc.labels.push(LabelDoc {
+                text: name.to_string(),
+                pos: gate_label_offset(size).into(),
+                on: LabelOn::Gate(row),
+            });
+        }
+        columns[col].push(row);
+        row
+    };
+    for &i in order.iter() {
+        gate_of[i] = match &net.nodes[i] {
+            Node::Input(name) => place(&mut doc, Gate::Input, Params::default(), 0, name),
+            Node::Gate(g, xs) => {
+                let params = Params {
+                    inputs: xs.len().max(2) as u8,
+                    ..Default::default()
+                };
+                place(&mut doc, *g, params, column[i], "")
+            }
+        };
+    }
+    let ports = outputs
+        .iter()
+        .map(|(name, _)| place(&mut doc, Gate::Output, Params::default(), last, name))
+        .collect::<Vec<_>>();
+
+    // wire from the output pin of `from` to input pin `pin` of `to`
+    let mut wires: Vec<Vec<(usize, usize, usize)>> = vec![vec![]; last + 1];
+    for (i, node) in net.nodes.iter().enumerate().filter(|&(i, _)| kept(i)) {
+        if let Node::Gate(_, xs) = node {
+            for (pin, &x) in xs.iter().enumerate() {
+                wires[column[i]].push((gate_of[x], gate_of[i], pin));
+            }
+        }
+    }
+    for ((_, x), &port) in outputs.iter().zip(ports.iter()) {
+        wires[last].push((gate_of[*x], port, 0));
+    }
+
+    // each column goes far enough right to leave a channel for every wire in the gap before it
+    let mut x = origin.x;
+    let mut gap_start = vec![0.0; last + 1];
+    for col in 0..=last {
+        if col > 0 {
+            gap_start[col] = x + PIN_REACH + MARGIN;
+            x = gap_start[col] + CHANNEL * wires[col].len() as f32 + MARGIN + PIN_REACH;
+        }
+        for &g in columns[col].iter() {
+            doc.gates[g].pos[0] = x;
+        }
+    }
+
+    let pin = |doc: &CircuitDoc, g: usize, dir: PinDir, n: usize| {
+        let gate = &doc.gates[g];
+        let specs = gate.gate.pins(&gate.params);
+        let spec = specs.iter().filter(|s| s.dir == dir).nth(n).copied();
+        spec.map(|s| pin_pos(gate, &s))
+    };
+    for (col, these) in wires.iter().enumerate() {
+        for (k, &(from, to, n)) in these.iter().enumerate() {
+            let (a, b) = match (
+                pin(&doc, from, PinDir::Out, 0),
+                pin(&doc, to, PinDir::In, n),
+            ) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            let channel = gap_start[col] + CHANNEL * k as f32;
+            let nodes = if a.y == b.y {
+                vec![a, b]
+            } else {
+                vec![a, Vec2::new(channel, a.y), Vec2::new(channel, b.y), b]
+            };
+            doc.wires.push(WireDoc {
+                nodes: nodes.into_iter().map(Into::into).collect(),
+            });
+        }
+    }
+    Ok(doc)
+}
+
+/// where the circuit comes from
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum Source {
+    #[default]
+    Equations,
+    Table,
+}
+
+struct SynthesisWindow {
+    source: Source,
+    equations: String,
+    /// names of the truth table inputs, separated by spaces or commas
+    inputs: String,
+    output: String,
+    ones: Vec<bool>,
+    set: GateSet,
+    error: Option<String>,
+}
+
+impl Default for SynthesisWindow {
+    fn default() -> Self {
+        Self {
+            source: Source::Equations,
+            equations: "out = (a & b) | !c".to_string(),
+            inputs: "a b".to_string(),
+            output: "out".to_string(),
+            ones: vec![],
+            set: GateSet::AndOrNot,
+            error: None,
+        }
+    }
+}
+
+/// the most truth table inputs that can be filled in by hand
+const MAX_TABLE_INPUTS: usize = 6;
+
+/// builds a circuit next to what is on the canvas
+fn synthesis_ui(
+    mut c: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<SynthesisWindow>,
+    assets: Res<Assets>,
+    nets: Res<CircuitNets>,
+) {
+    let window = &mut *window;
+    let mut build = false;
+    egui::Window::new("synthesis").show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut window.source, Source::Equations, "equations");
+            ui.selectable_value(&mut window.source, Source::Table, "truth table");
+        });
+        match window.source {
+            Source::Equations => {
+                ui.text_edit_multiline(&mut window.equations);
+                ui.label("one `name = expression` per line, with & | ! and parentheses");
+            }
+            Source::Table => {
+                ui.horizontal(|ui| {
+                    ui.label("inputs");
+                    ui.text_edit_singleline(&mut window.inputs);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("output");
+                    ui.text_edit_singleline(&mut window.output);
+                });
+                let inputs = table_inputs(&window.inputs);
+                window
+                    .ones
+                    .resize(1 << inputs.len().min(MAX_TABLE_INPUTS), false);
+                if inputs.len() > MAX_TABLE_INPUTS {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("at most {MAX_TABLE_INPUTS} inputs"),
+                    );
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("synthesis table")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for name in inputs.iter() {
+                                    ui.strong(name);
+                                }
+                                ui.strong(&window.output);
+                                ui.end_row();
+                                let n = inputs.len();
+                                for (row, one) in window.ones.iter_mut().enumerate() {
+                                    for i in 0..n {
+                                        ui.monospace(format!("{}", row >> (n - 1 - i) & 1));
+                                    }
+                                    ui.checkbox(one, "");
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            }
+        }
+        egui::ComboBox::from_label("gates")
+            .selected_text(window.set.name())
+            .show_ui(ui, |ui| {
+                for set in GateSet::ALL {
+                    ui.selectable_value(&mut window.set, set, set.name());
+                }
+            });
+        build = ui.button("build").clicked();
+        if let Some(e) = &window.error {
+            ui.colored_label(egui::Color32::YELLOW, e);
+        }
+    });
+    if !build {
+        return;
+    }
+    let inputs = match window.source {
+        Source::Equations => vec![],
+        Source::Table => table_inputs(&window.inputs),
+    };
+    let equations = match window.source {
+        Source::Equations => parse_equations(&window.equations),
+        Source::Table => {
+            let output = window.output.trim().to_string();
+            if is_name(&output) {
+                Ok(vec![(output, sum_of_minterms(&inputs, &window.ones))])
+            } else {
+                Err(anyhow!("{output:?} is not a name"))
+            }
+        }
+    };
+    // to the right of everything on the canvas
+    let right = nets
+        .doc
+        .gates
+        .iter()
+        .map(|g| g.pos[0])
+        .fold(f32::NEG_INFINITY, f32::max);
+    let top = nets
+        .doc
+        .gates
+        .iter()
+        .map(|g| g.pos[1])
+        .fold(f32::NEG_INFINITY, f32::max);
+    let origin = if right.is_finite() {
+        Vec2::new(right + 100.0, top)
+    } else {
+        Vec2::ZERO
+    };
+    match equations.and_then(|eqs| synthesize(&inputs, &eqs, window.set, origin)) {
+        Ok(doc) => {
+            window.error = None;
+            doc.spawn(&mut c, &assets);
+        }
+        Err(e) => window.error = Some(e.to_string()),
+    }
+}
+
+fn table_inputs(text: &str) -> Vec<String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        drc::check,
+        expr::Notation,
+        sim::{FlatCircuit, Sim},
+        subcircuit::Library,
+        truth::{port_columns, TruthTable},
+    };
+
+    fn var(name: &str) -> Expr {
+        Expr::Var(name.to_string())
+    }
+
+    /// the output of a one output circuit for every row of its inputs
+    fn outputs(doc: &CircuitDoc) -> Vec<u64> {
+        let sim = Sim::new(FlatCircuit::flatten(doc, &Library::default()));
+        let (inputs, outputs) = port_columns(&sim.circuit);
+        TruthTable::build(&sim, &inputs, &outputs)
+            .rows
+            .iter()
+            .map(|r| r.outputs[0].unwrap().bits)
+            .collect()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            parse_expr("a | b & !c").unwrap(),
+            Expr::Or(vec![var("a"), Expr::And(vec![var("b"), !var("c")])])
+        );
+        assert_eq!(
+            parse_expr("(a + b)' c").unwrap(),
+            Expr::And(vec![!Expr::Or(vec![var("a"), var("b")]), var("c")])
+        );
+        assert_eq!(parse_expr("~~x1''").unwrap(), !!!!var("x1"));
+        assert_eq!(
+            parse_expr("a && 1 || 0").unwrap(),
+            Expr::Or(vec![
+                Expr::And(vec![var("a"), Expr::Const(true)]),
+                Expr::Const(false)
+            ])
+        );
+    }
+
+    #[test]
+    fn printed_expressions_read_back() {
+        let exprs = [
+            Expr::Or(vec![
+                Expr::And(vec![var("a"), !var("b")]),
+                !Expr::Or(vec![var("c"), var("d")]),
+            ]),
+            Expr::And(vec![
+                Expr::Or(vec![var("a"), Expr::Const(true)]),
+                !!var("b"),
+                !Expr::And(vec![var("c"), var("d")]),
+            ]),
+            !var("a_b"),
+        ];
+        for e in exprs {
+            for notation in [Notation::Algebraic, Notation::C] {
+                let text = e.to_string(notation);
+                assert_eq!(parse_expr(&text).unwrap(), e, "{text}");
+            }
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        for text in ["a &", "(a | b", "a $ b", "a b)", ""] {
+            assert!(parse_expr(text).is_err(), "{text:?}");
+        }
+        let e = parse_equations("y = a\nz a").unwrap_err().to_string();
+        assert!(e.starts_with("line 2"), "{e}");
+        assert!(parse_equations("1y = a").is_err());
+        assert_eq!(
+            parse_equations("\ny = a\n").unwrap(),
+            vec![("y".into(), var("a"))]
+        );
+    }
+
+    #[test]
+    fn every_gate_set_builds_the_function() {
+        let equations = parse_equations("y = (a & b) | !c").unwrap();
+        let expected = (0..8)
+            .map(|row| (row >> 2 & row >> 1 & 1 | !row & 1) as u64)
+            .collect::<Vec<_>>();
+        for set in GateSet::ALL {
+            let doc = synthesize(&[], &equations, set, Vec2::ZERO).unwrap();
+            assert_eq!(outputs(&doc), expected, "{}", set.name());
+            assert_eq!(check(&doc, &Library::default()), vec![], "{}", set.name());
+        }
+    }
+
+    #[test]
+    fn constant_outputs() {
+        let inputs = ["a".to_string(), "b".to_string()];
+        for set in GateSet::ALL {
+            for value in [false, true] {
+                let ones = vec![value; 4];
+                let equations = vec![("y".to_string(), sum_of_minterms(&inputs, &ones))];
+                let doc = synthesize(&inputs, &equations, set, Vec2::ZERO).unwrap();
+                assert_eq!(outputs(&doc), vec![value as u64; 4], "{}", set.name());
+            }
+            // `a & 1` is just `a`, and `a & 0` keeps its input
+            let equations = parse_equations("y = a & 1").unwrap();
+            let doc = synthesize(&[], &equations, set, Vec2::ZERO).unwrap();
+            assert_eq!(outputs(&doc), vec![0, 1]);
+            let equations = parse_equations("y = a & 0").unwrap();
+            let doc = synthesize(&[], &equations, set, Vec2::ZERO).unwrap();
+            assert_eq!(outputs(&doc), vec![0, 0]);
+        }
+        let equations = parse_equations("y = 1").unwrap();
+        assert!(synthesize(&[], &equations, GateSet::Nand, Vec2::ZERO).is_err());
+    }
+}
@@ -0,0 +1,506 @@
+use anyhow::{bail, Result};
+use bevy::{
+    prelude::{
+        App, Color, Commands, Component, DespawnRecursiveExt, Entity, Local, Plugin, Query, Res,
+        ResMut, Transform, Vec2, With,
+    },
+    sprite::{Sprite, SpriteBundle},
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    netlist::CircuitNets,
+    pin::PinDir,
+    run::{Assets, GameState, Gate},
+    sim::{CanvasScope, FlatCircuit, FlatPart, Simulation},
+};
+
+pub struct TimingPlugin;
+
+impl Plugin for TimingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(timing_ui)
+                .into(),
+        );
+    }
+}
+
+/// a chain of parts, each driving the next through a net
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Path {
+    /// flat parts from where the path starts to where it ends
+    pub parts: Vec<usize>,
+    /// the flat net between each part and the next
+    pub nets: Vec<usize>,
+    /// nanoseconds from the start of the path to the end
+    pub delay: u32,
+}
+
+/// the result of static timing analysis
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timing {
+    /// the slowest path from an input port or a part with state to an output port or a part with state
+    pub critical: Option<Path>,
+    /// the slowest path ending at a part with state, which bounds the clock period.
+    /// `None` for circuits without state
+    pub clock: Option<Path>,
+}
+
+impl Timing {
+    /// the fastest clock every part with state still sees settled inputs at, in MHz
+    pub fn max_frequency(&self) -> Option<f64> {
+        self.clock
+            .as_ref()
+            .filter(|p| p.delay > 0)
+            .map(|p| 1000.0 / p.delay as f64)
+    }
+}
+
+/// how long a part takes, in nanoseconds
+pub fn part_delay(part: &FlatPart) -> u32 {
+    if part.gate.param_kinds().delay {
+        part.params.delay as u32
+    } else {
+        0
+    }
+}
+
+/// the inputs a change goes straight through to the outputs on. a part with state only changes
+/// its outputs on the clock, except a ram, whose address also picks the word it reads
+fn through_inputs(part: &FlatPart) -> Vec<usize> {
+    let inputs = inputs(part);
+    match part.gate {
+        Gate::Ram => inputs.into_iter().take(1).collect(),
+        g if g.has_state() => vec![],
+        _ => inputs,
+    }
+}
+
+fn inputs(part: &FlatPart) -> Vec<usize> {
+    part.pins
+        .iter()
+        .zip(part.dirs.iter())
+        .filter(|(_, &d)| d == PinDir::In)
+        .map(|(&n, _)| n)
+        .collect()
+}
+
+/// the inputs where paths end: every input of an output port, and every input of a part with
+/// state other than its clock, which always comes last
+fn path_ends(part: &FlatPart) -> Vec<usize> {
+    let mut inputs = inputs(part);
+    match part.gate {
+        Gate::Output => inputs,
+        g if g.has_state() => {
+            inputs.pop();
+            inputs
+        }
+        _ => vec![],
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    Fresh,
+    Visiting,
+    /// when the outputs of the part settle, and the input net the latest change came in on
+    Done(u32, Option<usize>),
+}
+
+struct Arrivals<'a> {
+    circuit: &'a FlatCircuit,
+    delay: fn(&FlatPart) -> u32,
+    drivers: std::collections::HashMap<usize, Vec<usize>>,
+    parts: Vec<Visit>,
+}
+
+impl Arrivals<'_> {
+    /// when the last change reaches a net, and the part that brings it
+    fn net(&mut self, net: usize) -> Result<(u32, Option<usize>)> {
+        let mut latest = (0, None);
+        for d in self.drivers.get(&net).cloned().unwrap_or_default() {
+            let t = self.part(d)?;
+            if latest.1.is_none() || t > latest.0 {
+                latest = (t, Some(d));
+            }
+        }
+        Ok(latest)
+    }
+
+    fn part(&mut self, part: usize) -> Result<u32> {
+        match self.parts[part] {
+            Visit::Done(t, _) => return Ok(t),
+            Visit::Visiting => bail!(
+                "the circuit has a loop without a register in it, through a {}",
+                self.circuit.parts[part].gate.title()
+            ),
+            Visit::Fresh => {}
+        }
+        self.parts[part] = Visit::Visiting;
+        let p = &self.circuit.parts[part];
+        let delay = (self.delay)(p);
+        let mut latest = (0, None);
+        for n in through_inputs(p) {
+            let (t, _) = self.net(n)?;
+            if latest.1.is_none() || t > latest.0 {
+                latest = (t, Some(n));
+            }
+        }
+        self.parts[part] = Visit::Done(latest.0 + delay, latest.1);
+        Ok(latest.0 + delay)
+    }
+
+    /// follows the latest changes back from a net to where they start
+    fn path_to(&mut self, end: usize, net: usize) -> Result<Path> {
+        let (delay, _) = self.net(net)?;
+        let mut parts = vec![end];
+        let mut nets = vec![];
+        let mut next = Some(net);
+        while let Some(n) = next {
+            let driver = match self.net(n)? {
+                (_, Some(d)) => d,
+                // nothing drives the net, so the path starts here
+                (_, None) => break,
+            };
+            nets.push(n);
+            parts.push(driver);
+            next = match self.parts[driver] {
+                Visit::Done(_, through) => through,
+                _ => None,
+            };
+        }
+        parts.reverse();
+        nets.reverse();
+        Ok(Path { parts, nets, delay })
+    }
+}
+
+/// finds the slowest paths through the circuit from the delay of each part
+pub fn analyze(circuit: &FlatCircuit) -> Result<Timing> {
+    longest_paths(circuit, part_delay)
+}
+
+/// the longest paths when each part adds `delay` to the paths through it
+pub fn longest_paths(circuit: &FlatCircuit, delay: fn(&FlatPart) -> u32) -> Result<Timing> {
+    let mut arrivals = Arrivals {
+        circuit,
+        delay,
+        drivers: circuit.drivers(),
+        parts: vec![Visit::Fresh; circuit.parts.len()],
+    };
+    let mut timing = Timing::default();
+    for (i, p) in circuit.parts.iter().enumerate() {
+        arrivals.part(i)?;
+        for n in path_ends(p) {
+            let path = arrivals.path_to(i, n)?;
+            let slower =
+                |current: &Option<Path>| current.as_ref().is_none_or(|c| path.delay > c.delay);
+            if p.gate.has_state() && slower(&timing.clock) {
+                timing.clock = Some(path.clone());
+            }
+            if slower(&timing.critical) {
+                timing.critical = Some(path);
+            }
+        }
+    }
+    Ok(timing)
+}
+
+/// what a part on a path is called, by port name at the top level
+pub fn part_name(circuit: &FlatCircuit, part: usize) -> String {
+    let port = circuit
+        .inputs
+        .iter()
+        .chain(circuit.outputs.iter())
+        .find(|(_, p)| *p == part);
+    match port {
+        Some((name, _)) => name.clone(),
+        None => circuit.parts[part].gate.title(),
+    }
+}
+
+/// marks the sprites drawn over the canvas for the critical path
+#[derive(Component)]
+struct PathMarker;
+
+const PATH_COLOR: Color = Color::rgba(1.0, 0.45, 0.1, 0.5);
+const PATH_TEXT_COLOR: Color = Color::rgb(1.0, 0.45, 0.1);
+
+#[derive(Default)]
+struct TimingWindow {
+    highlight: bool,
+    circuit: FlatCircuit,
+    timing: Option<Result<Timing>>,
+    /// the gates and wires of the canvas circuit the markers were drawn over
+    drawn: (Vec<usize>, Vec<usize>),
+}
+
+/// the critical path of the simulated circuit and the fastest clock it allows.
+/// the path can be drawn over the parts and wires of the canvas that are on it
+#[allow(clippy::too_many_arguments)]
+fn timing_ui(
+    mut c: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<TimingWindow>,
+    simulation: Res<Simulation>,
+    nets: Res<CircuitNets>,
+    canvas_scope: Res<CanvasScope>,
+    assets: Res<Assets>,
+    markers: Query<Entity, With<PathMarker>>,
+) {
+    let window = &mut *window;
+    if window.timing.is_none() || window.circuit != simulation.sim.circuit {
+        window.circuit = simulation.sim.circuit.clone();
+        window.timing = Some(analyze(&window.circuit));
+    }
+    let circuit = &window.circuit;
+    let timing = match &window.timing {
+        Some(Ok(t)) => Some(t),
+        _ => None,
+    };
+    egui::Window::new("timing").show(egui_context.ctx_mut(), |ui| {
+        if let Some(Err(e)) = &window.timing {
+            ui.colored_label(egui::Color32::YELLOW, e.to_string());
+        }
+        match timing.and_then(|t| t.critical.as_ref()) {
+            Some(path) => {
+                ui.label(format!("critical path: {} ns", path.delay));
+                let names = path
+                    .parts
+                    .iter()
+                    .map(|&p| part_name(circuit, p))
+                    .collect::<Vec<_>>();
+                ui.label(names.join(" → "));
+                ui.checkbox(&mut window.highlight, "show on the canvas");
+            }
+            None if timing.is_some() => {
+                ui.label("no paths from inputs to outputs");
+            }
+            None => {}
+        }
+        if let Some(path) = timing.and_then(|t| t.clock.as_ref()) {
+            ui.separator();
+            ui.label(format!("slowest path into a register: {} ns", path.delay));
+            match timing.and_then(Timing::max_frequency) {
+                Some(f) => ui.label(format!("maximum clock: {f:.1} MHz")),
+                None => ui.label("maximum clock: unlimited"),
+            };
+        }
+    });
+
+    let path = timing
+        .and_then(|t| t.critical.as_ref())
+        .filter(|_| window.highlight);
+    let on_canvas = match (path, canvas_scope.0) {
+        (Some(path), Some(scope)) => canvas_targets(circuit, path, scope, &nets),
+        _ => (vec![], vec![]),
+    };
+    // the parts may have moved even when the path has not changed
+    if on_canvas == window.drawn && !nets.is_changed() {
+        return;
+    }
+    markers.iter().for_each(|e| c.entity(e).despawn_recursive());
+    let (gates, wires) = &on_canvas;
+    for &gi in gates {
+        let g = &nets.doc.gates[gi];
+        let size = g.gate.size(&g.params) + Vec2::splat(6.0);
+        c.spawn((marker(Vec2::from(g.pos), size), PathMarker));
+    }
+    for &wi in wires {
+        for seg in nets.doc.wires[wi].nodes.windows(2) {
+            let (a, b) = (Vec2::from(seg[0]), Vec2::from(seg[1]));
+            let size = (b - a).abs() + Vec2::splat(3.0);
+            c.spawn((marker((a + b) / 2.0, size), PathMarker));
+        }
+    }
+    if let (Some(path), Some(&last)) = (path, gates.last()) {
+        let g = &nets.doc.gates[last];
+        let above = Vec2::new(0.0, g.gate.size(&g.params).y / 2.0 + 10.0);
+        c.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("{} ns", path.delay),
+                    TextStyle {
+                        font: assets.font.clone(),
+                        font_size: 14.0,
+                        color: PATH_TEXT_COLOR,
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER),
+                transform: Transform::from_translation((Vec2::from(g.pos) + above).extend(2.0)),
+                ..Default::default()
+            },
+            PathMarker,
+        ));
+    }
+    window.drawn = on_canvas;
+}
+
+fn marker(pos: Vec2, size: Vec2) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: PATH_COLOR,
+            custom_size: Some(size),
+            ..Default::default()
+        },
+        transform: Transform::from_translation(pos.extend(1.5)),
+        ..Default::default()
+    }
+}
+
+/// the gates and wires of the canvas circuit a path goes through, in order along the path.
+/// a path through the insides of an instance lights up the instance
+fn canvas_targets(
+    circuit: &FlatCircuit,
+    path: &Path,
+    scope: usize,
+    nets: &CircuitNets,
+) -> (Vec<usize>, Vec<usize>) {
+    let here = &circuit.scopes[scope];
+    let mut gates = vec![];
+    for &p in path.parts.iter() {
+        let part = &circuit.parts[p];
+        let inner = &circuit.scopes[part.scope].path;
+        let gate = if part.scope == scope {
+            Some(part.gate_index)
+        } else if inner.starts_with(&here.path) {
+            inner.get(here.path.len()).copied()
+        } else {
+            None
+        };
+        if let Some(gi) = gate.filter(|gi| !gates.contains(gi)) {
+            gates.push(gi);
+        }
+    }
+    let mut wires = vec![];
+    for &n in path.nets.iter() {
+        for (wi, local) in nets.netlist.wire_nets.iter().enumerate() {
+            if here.nets.get(*local) == Some(&n) && !wires.contains(&wi) {
+                wires.push(wi);
+            }
+        }
+    }
+    (gates, wires)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{params::Params, save::CircuitDoc, subcircuit::Library};
+
+    fn delay(delay: u8) -> Params {
+        Params {
+            delay,
+            ..Default::default()
+        }
+    }
+
+    /// the parts of a path by name
+    fn names(circuit: &FlatCircuit, path: &Path) -> Vec<String> {
+        path.parts.iter().map(|&p| part_name(circuit, p)).collect()
+    }
+
+    #[test]
+    fn chain_of_gates() {
+        let mut doc = CircuitDoc::default();
+        let a = doc.add_gate(Gate::Input, delay(0), [0.0, 0.0], "a");
+        let b = doc.add_gate(Gate::Input, delay(0), [0.0, -200.0], "b");
+        let not = doc.add_gate(Gate::Not, delay(3), [150.0, 10.0], "");
+        let and = doc.add_gate(Gate::And, delay(2), [320.0, -80.0], "");
+        let y = doc.add_gate(Gate::Output, delay(0), [500.0, -30.0], "y");
+        doc.connect((a, 0), (not, 0));
+        doc.connect((not, 0), (and, 0));
+        doc.connect((b, 0), (and, 1));
+        doc.connect((and, 0), (y, 0));
+        let circuit = FlatCircuit::flatten(&doc, &Library::default());
+        let timing = analyze(&circuit).unwrap();
+        let critical = timing.critical.unwrap();
+        assert_eq!(critical.delay, 5);
+        assert_eq!(
+            names(&circuit, &critical),
+            ["a", "Not Gate", "And Gate", "y"]
+        );
+        assert_eq!(critical.nets.len(), 3);
+        // nothing with state, so no clock
+        assert_eq!(timing.clock, None);
+        // with every part taking 1 the input port counts too, while the output port ends the path
+        let timing = longest_paths(&circuit, |_| 1).unwrap();
+        assert_eq!(timing.critical.unwrap().delay, 3);
+    }
+
+    #[test]
+    fn register_to_register() {
+        let mut doc = CircuitDoc::default();
+        let d = doc.add_gate(Gate::Input, delay(0), [0.0, 0.0], "d");
+        let clk = doc.add_gate(Gate::Input, delay(0), [0.0, -300.0], "clk");
+        let first = doc.add_gate(Gate::Register, delay(2), [150.0, 0.0], "");
+        let not = doc.add_gate(Gate::Not, delay(4), [330.0, 40.0], "");
+        let second = doc.add_gate(Gate::Register, delay(2), [500.0, -20.0], "");
+        let q = doc.add_gate(Gate::Output, delay(0), [680.0, 30.0], "q");
+        doc.connect((d, 0), (first, 0));
+        doc.connect((clk, 0), (first, 1));
+        doc.connect((first, 0), (not, 0));
+        doc.connect((not, 0), (second, 0));
+        doc.connect((clk, 0), (second, 1));
+        doc.connect((second, 0), (q, 0));
+        let circuit = FlatCircuit::flatten(&doc, &Library::default());
+        let timing = analyze(&circuit).unwrap();
+        let clock = timing.clock.clone().unwrap();
+        assert_eq!(clock.delay, 6);
+        assert_eq!(
+            names(&circuit, &clock),
+            ["Register", "Not Gate", "Register"]
+        );
+        assert_eq!(timing.critical, Some(clock));
+        let mhz = timing.max_frequency().unwrap();
+        assert!((mhz - 1000.0 / 6.0).abs() < 1e-9, "{mhz}");
+    }
+
+    #[test]
+    fn loops_need_a_register() {
+        let mut doc = CircuitDoc::default();
+        let first = doc.add_gate(Gate::Not, delay(1), [0.0, 0.0], "");
+        let second = doc.add_gate(Gate::Not, delay(1), [200.0, -90.0], "");
+        doc.connect((first, 0), (second, 0));
+        doc.connect((second, 0), (first, 0));
+        let circuit = FlatCircuit::flatten(&doc, &Library::default());
+        let e = analyze(&circuit).unwrap_err().to_string();
+        assert!(e.contains("loop without a register"), "{e}");
+    }
+
+    #[test]
+    fn ram_address_goes_through() {
+        // the word read follows the address at once, but data is only taken on the clock
+        let ram_with_feedback = |to_address: bool| {
+            let mut doc = CircuitDoc::default();
+            let addr = doc.add_gate(Gate::Input, delay(0), [0.0, 0.0], "addr");
+            let ram = doc.add_gate(Gate::Ram, delay(5), [200.0, -60.0], "");
+            let not = doc.add_gate(Gate::Not, delay(1), [430.0, 150.0], "");
+            let y = doc.add_gate(Gate::Output, delay(0), [450.0, -120.0], "y");
+            doc.connect((ram, 0), (y, 0));
+            doc.connect((ram, 0), (not, 0));
+            if to_address {
+                doc.connect((not, 0), (ram, 0));
+            } else {
+                doc.connect((addr, 0), (ram, 0));
+                doc.connect((not, 0), (ram, 1));
+            }
+            FlatCircuit::flatten(&doc, &Library::default())
+        };
+        let circuit = ram_with_feedback(false);
+        let timing = analyze(&circuit).unwrap();
+        let critical = timing.critical.unwrap();
+        assert_eq!(critical.delay, 6);
+        assert_eq!(
+            names(&circuit, &critical),
+            ["addr", "Ram", "Not Gate", "Ram"]
+        );
+        assert_eq!(timing.clock.unwrap().delay, 6);
+        assert!(analyze(&ram_with_feedback(true)).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::{
+        App, BuildChildren, ButtonBundle, Camera, Changed, Children, Color, Commands, Component,
+        DespawnRecursiveExt, Entity, EventReader, Input, KeyCode, MouseButton, Name,
+        OrthographicProjection, Plugin, Query, Res, ResMut, Resource, TextBundle, Transform, With,
+        Without,
+    },
+    text::{Text, TextStyle},
+    ui::{
+        AlignItems, BackgroundColor, FlexDirection, FocusPolicy, Interaction, JustifyContent,
+        PositionType, Size, Style, UiRect, Val,
+    },
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use bevy_rapier2d::prelude::{QueryFilter, RapierContext};
+use iyes_loopless::prelude::{AppLooplessStateExt, ConditionSet, CurrentState, NextState};
+
+use crate::{
+    label::keyboard_free,
+    netlist::{CircuitNets, Net},
+    orientation::Orientation,
+    pin::Pin,
+    run::{Assets, CanvasCursor, GameState, Gate, Selected, Wire, WireSprite},
+};
+
+pub struct ToolPlugin;
+
+impl Plugin for ToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_loopless_state(Tool::Select)
+            .insert_resource(Probed(None))
+            .add_enter_system(GameState::Playing, spawn_toolbar)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_if(keyboard_free)
+                    .with_system(tool_hotkeys)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(toolbar_buttons)
+                    .with_system(highlight_tool)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_in_state(Tool::Select)
+                    .run_if(keyboard_free)
+                    .with_system(delete_selected)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_in_state(Tool::Pan)
+                    .with_system(pan_camera)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_in_state(Tool::Probe)
+                    .with_system(probe)
+                    .with_system(probe_ui)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_in_state(Tool::Delete)
+                    .with_system(delete_clicked)
+                    .into(),
+            );
+    }
+}
+
+/// what a click on the canvas does. every canvas system runs in exactly one of these,
+/// so the primary button alone is enough to drive the editor
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum Tool {
+    Select,
+    Place,
+    Wire,
+    Pan,
+    Probe,
+    Delete,
+    Label,
+    Poke,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 8] = [
+        Tool::Select,
+        Tool::Place,
+        Tool::Wire,
+        Tool::Pan,
+        Tool::Probe,
+        Tool::Delete,
+        Tool::Label,
+        Tool::Poke,
+    ];
+
+    pub fn hotkey(&self) -> KeyCode {
+        match self {
+            Tool::Select => KeyCode::Key1,
+            Tool::Place => KeyCode::Key2,
+            Tool::Wire => KeyCode::Key3,
+            Tool::Pan => KeyCode::Key4,
+            Tool::Probe => KeyCode::Key5,
+            Tool::Delete => KeyCode::Key6,
+            Tool::Label => KeyCode::Key7,
+            Tool::Poke => KeyCode::Key8,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Toolbar;
+
+#[derive(Component)]
+pub struct ToolButton(pub Tool);
+
+/// the entity the probe tool last clicked on
+#[derive(Resource)]
+pub struct Probed(pub Option<Entity>);
+
+fn spawn_toolbar(mut c: Commands, assets: Res<Assets>) {
+    c.spawn((
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(5.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                flex_direction: FlexDirection::Row,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Percent(1.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::BLACK),
+            ..Default::default()
+        },
+        Toolbar,
+    ))
+    .with_children(|p| {
+        for (i, tool) in Tool::ALL.into_iter().enumerate() {
+            p.spawn((
+                ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Auto, Val::Percent(100.0)),
+                        padding: UiRect::horizontal(Val::Px(5.0)),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..Default::default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..Default::default()
+                },
+                ToolButton(tool),
+            ))
+            .with_children(|p| {
+                p.spawn(TextBundle {
+                    text: Text::from_section(
+                        format!("{} {tool:?}", i + 1),
+                        TextStyle {
+                            font: assets.font.clone(),
+                            color: Color::rgb(0.6, 0.5, 0.4),
+                            font_size: 10.0,
+                        },
+                    ),
+                    focus_policy: FocusPolicy::Pass,
+                    ..Default::default()
+                });
+            });
+        }
+    });
+}
+
+fn tool_hotkeys(mut c: Commands, keys: Res<Input<KeyCode>>, current: Res<CurrentState<Tool>>) {
+    if keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+    for tool in Tool::ALL {
+        if keys.just_pressed(tool.hotkey()) && current.0 != tool {
+            c.insert_resource(NextState(tool));
+        }
+    }
+}
+
+fn toolbar_buttons(
+    mut c: Commands,
+    buttons: Query<(&Interaction, &ToolButton), Changed<Interaction>>,
+) {
+    for (i, b) in buttons.iter() {
+        if *i == Interaction::Clicked {
+            c.insert_resource(NextState(b.0));
+        }
+    }
+}
+
+fn highlight_tool(
+    current: Res<CurrentState<Tool>>,
+    mut buttons: Query<(&ToolButton, &mut BackgroundColor)>,
+) {
+    if !current.is_changed() {
+        return;
+    }
+    for (b, mut bg) in buttons.iter_mut() {
+        *bg = if b.0 == current.0 {
+            Color::rgb(0.2, 0.25, 0.2).into()
+        } else {
+            Color::NONE.into()
+        };
+    }
+}
+
+fn pan_camera(
+    mou: Res<Input<MouseButton>>,
+    cursor: CanvasCursor,
+    mut motion: EventReader<MouseMotion>,
+    mut q_camera: Query<(&mut Transform, &OrthographicProjection), With<Camera>>,
+) {
+    let delta = motion.iter().map(|m| m.delta).sum::<bevy::prelude::Vec2>();
+    if !mou.pressed(MouseButton::Left) || cursor.over_ui() {
+        return;
+    }
+    let (mut t, proj) = q_camera.single_mut();
+    // screen y points down, world y points up
+    t.translation.x -= delta.x * proj.scale;
+    t.translation.y += delta.y * proj.scale;
+}
+
+pub fn entity_under_cursor(
+    rapier_context: &RapierContext,
+    cursor: &CanvasCursor,
+) -> Option<Entity> {
+    let world_pos = cursor.world_pos()?;
+    let mut hit = None;
+    rapier_context.intersections_with_point(world_pos, QueryFilter::default(), |e| {
+        hit = Some(e);
+        false
+    });
+    hit
+}
+
+fn probe(
+    mou: Res<Input<MouseButton>>,
+    rapier_context: Res<RapierContext>,
+    cursor: CanvasCursor,
+    mut probed: ResMut<Probed>,
+) {
+    if !mou.just_pressed(MouseButton::Left) || cursor.over_ui() {
+        return;
+    }
+    probed.0 = entity_under_cursor(&rapier_context, &cursor);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn probe_ui(
+    mut egui_context: ResMut<EguiContext>,
+    probed: Res<Probed>,
+    gates: Query<(&Gate, &Transform, &Orientation, &Children)>,
+    pins: Query<(&Pin, &bevy::prelude::GlobalTransform)>,
+    wire_sprites: Query<&WireSprite>,
+    wires: Query<&Wire>,
+    nodes: Query<&Transform, Without<Gate>>,
+    names: Query<&Name>,
+    nets: Res<CircuitNets>,
+) {
+    let net_text = |net: Option<&Net>| match net {
+        Some(n) => format!("net {}", n.name().unwrap_or("(unnamed)")),
+        None => "no net".to_string(),
+    };
+    let e = match probed.0 {
+        Some(e) => e,
+        None => return,
+    };
+    egui::Window::new("probe").show(egui_context.ctx_mut(), |ui| {
+        if let Ok(name) = names.get(e) {
+            ui.label(format!("{name}"));
+        }
+        if let Ok((g, t, o, children)) = gates.get(e) {
+            ui.label(format!("{g:?} gate at {:?}", t.translation.truncate()));
+            ui.label(format!("turns: {} mirrored: {}", o.turns, o.mirrored));
+            for &child in children.iter() {
+                if let Ok((pin, pt)) = pins.get(child) {
+                    ui.label(format!(
+                        "pin {} ({:?}) at {:?}, {}",
+                        pin.index,
+                        pin.dir,
+                        pt.translation().truncate(),
+                        net_text(nets.pin_net(e, pin.index)),
+                    ));
+                }
+            }
+        } else if let Ok(ws) = wire_sprites.get(e) {
+            ui.label(net_text(nets.wire_net(ws.wire)));
+            for &n in wires
+                .get(ws.wire)
+                .map(|w| w.nodes.as_slice())
+                .unwrap_or_default()
+            {
+                if let Ok(t) = nodes.get(n) {
+                    ui.label(format!("node at {:?}", t.translation.truncate()));
+                }
+            }
+        } else {
+            ui.label("nothing to probe here");
+        }
+    });
+}
+
+/// removes a gate with its pins, or a wire with its nodes and sprite
+fn delete_entity(
+    c: &mut Commands,
+    e: Entity,
+    wire_sprites: &Query<&WireSprite>,
+    wires: &Query<&Wire>,
+) {
+    if let Ok(ws) = wire_sprites.get(e) {
+        if let Ok(w) = wires.get(ws.wire) {
+            w.nodes.iter().for_each(|&n| c.entity(n).despawn());
+        }
+        c.entity(ws.wire).despawn();
+    }
+    c.entity(e).despawn_recursive();
+}
+
+fn delete_clicked(
+    mut c: Commands,
+    mou: Res<Input<MouseButton>>,
+    rapier_context: Res<RapierContext>,
+    cursor: CanvasCursor,
+    wire_sprites: Query<&WireSprite>,
+    wires: Query<&Wire>,
+) {
+    if !mou.just_pressed(MouseButton::Left) || cursor.over_ui() {
+        return;
+    }
+    if let Some(e) = entity_under_cursor(&rapier_context, &cursor) {
+        delete_entity(&mut c, e, &wire_sprites, &wires);
+    }
+}
+
+fn delete_selected(
+    mut c: Commands,
+    keys: Res<Input<KeyCode>>,
+    selected: Query<Entity, With<Selected>>,
+    wire_sprites: Query<&WireSprite>,
+    wires: Query<&Wire>,
+) {
+    if !keys.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        return;
+    }
+    for e in selected.iter() {
+        delete_entity(&mut c, e, &wire_sprites, &wires);
+    }
+}
@@ -0,0 +1,382 @@
+use std::{collections::HashSet, fmt::Write, path::Path};
+
+use anyhow::Result;
+use bevy::prelude::{App, Local, Plugin, Res, ResMut};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    equiv::EXHAUSTIVE_BITS,
+    run::GameState,
+    sim::{FlatCircuit, Sim, Simulation, Value},
+};
+
+pub struct TruthPlugin;
+
+impl Plugin for TruthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(truth_table_ui)
+                .into(),
+        );
+    }
+}
+
+/// tables with more input bits than this take a while and get a warning
+pub const WARN_BITS: u32 = 10;
+/// tables with more input bits than this are not made at all. a table is made in one go, running
+/// the circuit once a row, so it stops where the equivalence check stops trying every input
+pub const MAX_BITS: u32 = EXHAUSTIVE_BITS;
+
+/// a port of the top level circuit that makes a column
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    pub width: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub inputs: Vec<u64>,
+    /// none where the circuit did not settle
+    pub outputs: Vec<Option<Value>>,
+}
+
+/// every combination of some inputs of a circuit, with what some of its outputs become
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TruthTable {
+    pub inputs: Vec<Column>,
+    pub outputs: Vec<Column>,
+    /// counting up, the first input being the most significant
+    pub rows: Vec<Row>,
+}
+
+/// the top level input and output ports of a circuit
+pub fn port_columns(circuit: &FlatCircuit) -> (Vec<Column>, Vec<Column>) {
+    let columns = |ports: &[(String, usize)]| {
+        ports
+            .iter()
+            .map(|(name, part)| Column {
+                name: name.clone(),
+                width: circuit.parts[*part].params.width,
+            })
+            .collect::<Vec<_>>()
+    };
+    (columns(&circuit.inputs), columns(&circuit.outputs))
+}
+
+/// the parts of a circuit that remember something, which a truth table cannot show
+pub fn stateful_parts(circuit: &FlatCircuit) -> Vec<String> {
+    let mut parts = circuit
+        .parts
+        .iter()
+        .filter(|p| p.gate.has_state())
+        .map(|p| p.gate.title())
+        .collect::<Vec<_>>();
+    parts.sort();
+    parts.dedup();
+    parts
+}
+
+impl TruthTable {
+    /// runs the simulation of `sim`'s circuit once for every combination of `inputs`.
+    /// the other inputs keep the value they have in `sim`
+    pub fn build(sim: &Sim, inputs: &[Column], outputs: &[Column]) -> Self {
+        let base = sim.rebuilt(sim.circuit.clone());
+        let bits = input_bits(inputs);
+        let max_steps = base.circuit.parts.len() + 2;
+        let rows = (0..1u64 << bits)
+            .map(|combination| {
+                let mut sim = base.clone();
+                let mut shift = bits;
+                let values = inputs
+                    .iter()
+                    .map(|col| {
+                        shift -= col.width as u32;
+                        let v = Value::new(combination >> shift, col.width);
+                        sim.set_input(&col.name, v);
+                        v.bits
+                    })
+                    .collect();
+                let settled = sim.settle(max_steps);
+                Row {
+                    inputs: values,
+                    outputs: outputs
+                        .iter()
+                        .map(|col| sim.output(&col.name).filter(|_| settled))
+                        .collect(),
+                }
+            })
+            .collect();
+        Self {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            rows,
+        }
+    }
+
+    fn header(&self) -> Vec<String> {
+        self.inputs
+            .iter()
+            .chain(self.outputs.iter())
+            .map(|c| c.name.clone())
+            .collect()
+    }
+
+    fn cells(&self, row: &Row) -> Vec<String> {
+        let inputs = self
+            .inputs
+            .iter()
+            .zip(row.inputs.iter())
+            .map(|(c, &bits)| binary(bits, c.width));
+        let outputs = self
+            .outputs
+            .iter()
+            .zip(row.outputs.iter())
+            .map(|(c, v)| output_cell(v.as_ref(), c.width));
+        inputs.chain(outputs).collect()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut s = String::new();
+        for line in std::iter::once(self.header()).chain(self.rows.iter().map(|r| self.cells(r))) {
+            let _ = writeln!(s, "{}", line.join(","));
+        }
+        s
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut s = String::new();
+        let header = self.header();
+        let _ = writeln!(s, "| {} |", header.join(" | "));
+        let _ = writeln!(s, "|{}", "---|".repeat(header.len()));
+        for row in self.rows.iter() {
+            let _ = writeln!(s, "| {} |", self.cells(row).join(" | "));
+        }
+        s
+    }
+
+    /// csv or markdown, going by the extension
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("md") => self.to_markdown(),
+            _ => self.to_csv(),
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+pub fn input_bits(inputs: &[Column]) -> u32 {
+    inputs.iter().map(|c| c.width as u32).sum()
+}
+
+fn binary(bits: u64, width: u8) -> String {
+    format!("{bits:0w$b}", w = width as usize)
+}
+
+/// `x` where the circuit did not settle, `!` where the output has several drivers
+/// and `z` where it has none
+fn output_cell(v: Option<&Value>, width: u8) -> String {
+    let w = width as usize;
+    match v {
+        None => "x".repeat(w),
+        Some(v) if v.conflict != 0 => "!".repeat(w),
+        Some(v) if v.is_floating() => "z".repeat(w),
+        Some(v) => binary(v.bits, width),
+    }
+}
+
+#[derive(Default)]
+struct TruthTableWindow {
+    /// ports left out of the table. new ports are in it
+    left_out: HashSet<String>,
+    table: Option<TruthTable>,
+    file: String,
+}
+
+/// picks the ports, makes the table and saves it
+fn truth_table_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<TruthTableWindow>,
+    simulation: Res<Simulation>,
+) {
+    let sim = &simulation.sim;
+    let window = &mut *window;
+    let (inputs, outputs) = port_columns(&sim.circuit);
+    let stateful = stateful_parts(&sim.circuit);
+    egui::Window::new("truth table").show(egui_context.ctx_mut(), |ui| {
+        let mut pick = |ui: &mut egui::Ui, columns: &[Column]| {
+            ui.horizontal_wrapped(|ui| {
+                for c in columns {
+                    let mut on = !window.left_out.contains(&c.name);
+                    if ui.checkbox(&mut on, &c.name).changed() {
+                        if on {
+                            window.left_out.remove(&c.name);
+                        } else {
+                            window.left_out.insert(c.name.clone());
+                        }
+                    }
+                }
+            });
+        };
+        ui.label("inputs");
+        pick(ui, &inputs);
+        ui.label("outputs");
+        pick(ui, &outputs);
+        let picked = |columns: &[Column]| {
+            columns
+                .iter()
+                .filter(|c| !window.left_out.contains(&c.name))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let (inputs, outputs) = (picked(&inputs), picked(&outputs));
+
+        let bits = input_bits(&inputs);
+        if !stateful.is_empty() {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!(
+                    "only circuits without memory have a truth table. this one has: {}",
+                    stateful.join(", ")
+                ),
+            );
+        } else if bits > MAX_BITS {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("{bits} input bits make too many rows, at most {MAX_BITS} can go in"),
+            );
+        } else if bits > WARN_BITS {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!("{bits} input bits make {} rows", 1u64 << bits),
+            );
+        }
+        let can_make = stateful.is_empty() && bits <= MAX_BITS && !outputs.is_empty();
+        if ui
+            .add_enabled(can_make, egui::Button::new("make table"))
+            .clicked()
+        {
+            window.table = Some(TruthTable::build(sim, &inputs, &outputs));
+        }
+
+        let table = match &window.table {
+            Some(table) => table,
+            None => return,
+        };
+        ui.separator();
+        let header = table.header();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical().max_height(300.0).show_rows(
+            ui,
+            row_height,
+            table.rows.len(),
+            |ui, range| {
+                egui::Grid::new("truth table rows")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for name in header.iter() {
+                            ui.strong(name);
+                        }
+                        ui.end_row();
+                        for row in &table.rows[range] {
+                            for cell in table.cells(row) {
+                                ui.monospace(cell);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            },
+        );
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut window.file);
+            if ui.button("save").clicked() {
+                match table.write(Path::new(&window.file)) {
+                    Ok(()) => bevy::prelude::info!("saved the truth table to {}", window.file),
+                    Err(e) => bevy::prelude::error!("could not save the truth table: {e:?}"),
+                }
+            }
+        });
+        ui.label("a .md file gets a markdown table, anything else csv");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{save::CircuitDoc, subcircuit::Library};
+
+    fn half_adder() -> TruthTable {
+        let doc = CircuitDoc::read("tests/circuits/half_adder.ron".as_ref()).unwrap();
+        let sim = Sim::new(FlatCircuit::flatten(&doc, &Library::default()));
+        let (inputs, outputs) = port_columns(&sim.circuit);
+        TruthTable::build(&sim, &inputs, &outputs)
+    }
+
+    /// a two bit input with outputs that did not settle, float and clash
+    fn odd_outputs() -> TruthTable {
+        let column = |name: &str, width| Column {
+            name: name.to_string(),
+            width,
+        };
+        let floating = Value {
+            float: 0b11,
+            ..Value::new(0, 2)
+        };
+        let clash = Value {
+            conflict: 0b01,
+            ..Value::new(0, 2)
+        };
+        TruthTable {
+            inputs: vec![column("d", 2)],
+            outputs: vec![column("q", 2)],
+            rows: vec![
+                Row {
+                    inputs: vec![1],
+                    outputs: vec![Some(Value::new(2, 2))],
+                },
+                Row {
+                    inputs: vec![2],
+                    outputs: vec![None],
+                },
+                Row {
+                    inputs: vec![3],
+                    outputs: vec![Some(floating)],
+                },
+                Row {
+                    inputs: vec![0],
+                    outputs: vec![Some(clash)],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn csv_export() {
+        assert_eq!(
+            half_adder().to_csv(),
+            "a,b,s,c\n0,0,0,0\n0,1,1,0\n1,0,1,0\n1,1,0,1\n"
+        );
+        assert_eq!(odd_outputs().to_csv(), "d,q\n01,10\n10,xx\n11,zz\n00,!!\n");
+    }
+
+    #[test]
+    fn markdown_export() {
+        assert_eq!(
+            half_adder().to_markdown(),
+            "| a | b | s | c |\n\
+             |---|---|---|---|\n\
+             | 0 | 0 | 0 | 0 |\n\
+             | 0 | 1 | 1 | 0 |\n\
+             | 1 | 0 | 1 | 0 |\n\
+             | 1 | 1 | 0 | 1 |\n"
+        );
+        assert_eq!(
+            odd_outputs().to_markdown(),
+            "| d | q |\n|---|---|\n| 01 | 10 |\n| 10 | xx |\n| 11 | zz |\n| 00 | !! |\n"
+        );
+    }
+}
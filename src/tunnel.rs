@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::{App, Color, Entity, Plugin, Query, Res},
+    sprite::Sprite,
+};
+use bevy_rapier2d::prelude::{QueryFilter, RapierContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    label::{Attached, Label, LabelOn},
+    netlist::{label_text, CircuitNets},
+    run::{CanvasCursor, GameState, Gate, Selected, SELECTED_TINT},
+};
+
+pub struct TunnelPlugin;
+
+impl Plugin for TunnelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(highlight_tunnels)
+                .into(),
+        );
+    }
+}
+
+const MATCHING_TINT: Color = Color::rgb(0.6, 0.9, 1.0);
+
+/// hovering a tunnel, or its name, lights up every tunnel it is connected to.
+/// a tunnel goes by the same label the netlist merges it by
+fn highlight_tunnels(
+    rapier_context: Res<RapierContext>,
+    cursor: CanvasCursor,
+    nets: Res<CircuitNets>,
+    labels: Query<(&Label, &Attached)>,
+    mut tunnels: Query<(Entity, &Gate, &mut Sprite, Option<&Selected>)>,
+) {
+    let names = nets
+        .gates
+        .iter()
+        .enumerate()
+        .filter_map(|(gi, &e)| Some((e, label_text(&nets.doc, LabelOn::Gate(gi))?)))
+        .collect::<HashMap<_, _>>();
+
+    let mut hovered = None;
+    if let Some(world_pos) = cursor.world_pos().filter(|_| !cursor.over_ui()) {
+        rapier_context.intersections_with_point(world_pos, QueryFilter::default(), |e| {
+            let tunnel = labels.get(e).map(|(_, a)| a.to).unwrap_or(e);
+            match tunnels.get(tunnel) {
+                Ok((_, Gate::Tunnel, ..)) => {
+                    hovered = names.get(&tunnel).copied();
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+
+    for (e, g, mut sprite, selected) in tunnels.iter_mut() {
+        if *g != Gate::Tunnel {
+            continue;
+        }
+        let color = if hovered.is_some() && names.get(&e).copied() == hovered {
+            MATCHING_TINT
+        } else if selected.is_some() {
+            SELECTED_TINT
+        } else {
+            Color::WHITE
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::{fmt::Write as _, path::Path};
+
+use anyhow::{bail, Result};
+use bevy::prelude::{App, Local, Plugin, Res, ResMut};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    run::GameState,
+    sim::{FlatCircuit, Sim, Simulation, Value},
+    subcircuit::{ports, Library},
+    wave::Probes,
+};
+
+pub struct VcdPlugin;
+
+impl Plugin for VcdPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(vcd_ui)
+                .into(),
+        );
+    }
+}
+
+/// the short code a vcd file refers to a variable by, out of the printable characters
+fn id_code(mut i: usize) -> String {
+    let mut code = String::new();
+    loop {
+        code.push((b'!' + (i % 94) as u8) as char);
+        i /= 94;
+        if i == 0 {
+            return code;
+        }
+        i -= 1;
+    }
+}
+
+/// a name without the spaces a vcd file separates words with
+fn identifier(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// a value as vcd writes it, `b` and the bits from the top for a bus, followed by the code
+fn vcd_value(v: Value, width: u8, code: &str) -> String {
+    let bit = |i: u8| {
+        if v.conflict >> i & 1 == 1 {
+            'x'
+        } else if v.float >> i & 1 == 1 {
+            'z'
+        } else if v.bits >> i & 1 == 1 {
+            '1'
+        } else {
+            '0'
+        }
+    };
+    if width <= 1 {
+        format!("{}{code}", bit(0))
+    } else {
+        let bits = (0..width).rev().map(bit).collect::<String>();
+        format!("b{bits} {code}")
+    }
+}
+
+/// the names of the nets of a scope: its ports, the labels on its wires and its tunnels.
+/// a net without any is called after its number in the scope
+fn scope_names(circuit: &FlatCircuit, library: &Library, scope: usize) -> Vec<(String, usize)> {
+    let s = &circuit.scopes[scope];
+    let mut names = vec![];
+    if scope == 0 {
+        for (name, part) in circuit.inputs.iter().chain(circuit.outputs.iter()) {
+            if let Some(&n) = circuit.parts[*part].pins.first() {
+                names.push((name.clone(), n));
+            }
+        }
+    } else if let Some(doc) = s.def.as_ref().and_then(|d| library.defs.get(d)) {
+        for port in ports(doc) {
+            let local = s.netlist.pin_nets.get(port.gate).and_then(|p| p.first());
+            if let Some(&n) = local.and_then(|&l| s.nets.get(l)) {
+                names.push((port.name, n));
+            }
+        }
+    }
+    for (local, net) in s.netlist.nets.iter().enumerate() {
+        let n = s.nets[local];
+        if net.names.is_empty() && !names.iter().any(|(_, m)| *m == n) {
+            names.push((format!("n{local}"), n));
+        }
+        for name in net.names.iter() {
+            names.push((name.clone(), n));
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// the width of a recorded net. a net reads as one bit until the simulation first steps
+fn trace_width(changes: &[(u64, Value)]) -> u8 {
+    changes
+        .iter()
+        .map(|(_, v)| v.width)
+        .max()
+        .unwrap_or(1)
+        .max(1)
+}
+
+fn write_scope(out: &mut String, sim: &Sim, library: &Library, scope: usize) -> Result<()> {
+    let (circuit, trace) = (&sim.circuit, &sim.trace);
+    let s = &circuit.scopes[scope];
+    let name = match (&s.def, s.path.last()) {
+        (Some(def), Some(gi)) => format!("{}_{gi}", identifier(def)),
+        _ => "top".to_string(),
+    };
+    writeln!(out, "$scope module {name} $end")?;
+    for (var, n) in scope_names(circuit, library, scope) {
+        if let Some(i) = trace.nets.iter().position(|&t| t == n) {
+            let width = trace_width(&trace.changes[i]);
+            let var = identifier(&var);
+            writeln!(out, "$var wire {width} {} {var} $end", id_code(i))?;
+        }
+    }
+    for &child in s.instances.iter().flatten() {
+        write_scope(out, sim, library, child)?;
+    }
+    writeln!(out, "$upscope $end")?;
+    Ok(())
+}
+
+/// writes what the simulation recorded as a value change dump, with a scope for every
+/// subcircuit instance. a step of the simulation is taken as one nanosecond
+pub fn to_vcd(sim: &Sim, library: &Library) -> Result<String> {
+    let trace = &sim.trace;
+    if trace.nets.is_empty() {
+        bail!("no nets are being recorded");
+    }
+    let mut out = String::new();
+    writeln!(out, "$version gatos $end")?;
+    writeln!(out, "$timescale 1 ns $end")?;
+    write_scope(&mut out, sim, library, 0)?;
+    writeln!(out, "$enddefinitions $end")?;
+
+    let widths = trace
+        .changes
+        .iter()
+        .map(|c| trace_width(c))
+        .collect::<Vec<_>>();
+    // a net recorded twice is only declared the first time
+    let first = |i: usize| trace.nets.iter().position(|&n| n == trace.nets[i]) == Some(i);
+    let mut changes = trace
+        .changes
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| first(i))
+        .flat_map(|(i, c)| c.iter().map(move |&(t, v)| (t, i, v)))
+        .collect::<Vec<_>>();
+    changes.sort_by_key(|&(t, i, _)| (t, i));
+    let start = changes.first().map_or(0, |&(t, _, _)| t);
+    writeln!(out, "#{start}")?;
+    writeln!(out, "$dumpvars")?;
+    let mut time = start;
+    for (t, i, v) in changes {
+        if t != time {
+            if time == start {
+                writeln!(out, "$end")?;
+            }
+            writeln!(out, "#{t}")?;
+            time = t;
+        }
+        writeln!(out, "{}", vcd_value(v, widths[i], &id_code(i)))?;
+    }
+    if time == start {
+        writeln!(out, "$end")?;
+    }
+    if sim.time > time {
+        writeln!(out, "#{}", sim.time)?;
+    }
+    Ok(out)
+}
+
+pub fn write_vcd(path: &Path, sim: &Sim, library: &Library) -> Result<()> {
+    std::fs::write(path, to_vcd(sim, library)?)?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct VcdWindow {
+    path: String,
+    status: Option<Result<String, String>>,
+}
+
+/// picks which nets get recorded and saves the recording for a waveform viewer like gtkwave
+fn vcd_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<VcdWindow>,
+    mut probes: ResMut<Probes>,
+    simulation: Res<Simulation>,
+    library: Res<Library>,
+) {
+    let window = &mut *window;
+    let mut record_all = probes.record_all;
+    egui::Window::new("vcd export").show(egui_context.ctx_mut(), |ui| {
+        ui.checkbox(&mut record_all, "record every net");
+        if !record_all {
+            ui.label("the nets in the waveform viewer are recorded");
+        }
+        ui.label(format!(
+            "{} nets recorded over {} steps",
+            simulation.sim.trace.nets.len(),
+            simulation.sim.time
+        ));
+        ui.horizontal(|ui| {
+            ui.label("file");
+            ui.text_edit_singleline(&mut window.path);
+        });
+        if ui.button("save").clicked() {
+            let path = Path::new(window.path.trim());
+            window.status = Some(
+                write_vcd(path, &simulation.sim, &library)
+                    .map(|_| format!("saved {}", path.display()))
+                    .map_err(|e| e.to_string()),
+            );
+        }
+        match &window.status {
+            Some(Ok(s)) => {
+                ui.label(s);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+    });
+    if record_all != probes.record_all {
+        probes.record_all = record_all;
+    }
+}
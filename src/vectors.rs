@@ -0,0 +1,438 @@
+use anyhow::{anyhow, bail, Context, Result};
+use bevy::prelude::{App, EventReader, EventWriter, Plugin, Res, ResMut, Resource};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    run::GameState,
+    sim::{mask, FlatCircuit, Sim, Simulation, Value},
+};
+
+pub struct VectorsPlugin;
+
+impl Plugin for VectorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TestVectors::default())
+            .insert_resource(TestResults::default())
+            .add_event::<RunTests>()
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(run_tests)
+                    .with_system(tests_ui)
+                    .into(),
+            );
+    }
+}
+
+/// what a test row does to an input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Drive {
+    Set(u64),
+    /// low, then high, letting the circuit settle after each
+    Clock,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VectorRow {
+    /// where the row is in the text, counting from 1
+    pub line: usize,
+    pub inputs: Vec<Drive>,
+    /// `None` where any value will do
+    pub outputs: Vec<Option<u64>>,
+}
+
+/// a table of values to put on the input ports and values the output ports should then show.
+/// written as a header of port names and one row per line, inputs and outputs split by `|`:
+///
+/// ```text
+/// # a counter
+/// rst clk | q
+/// 1   C   | 0
+/// 0   C   | 1
+/// 0   C   | 0x2
+/// ```
+///
+/// numbers are decimal or start with `0x` or `0b`, and must fit their port. `C` clocks an input,
+/// `x` is an output that is not checked, and `#` starts a comment
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Vectors {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub rows: Vec<VectorRow>,
+}
+
+fn number(s: &str) -> Result<u64> {
+    let parsed = if let Some(hex) = s.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2)
+    } else {
+        s.parse()
+    };
+    parsed.with_context(|| format!("{s} is not a number"))
+}
+
+impl Vectors {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.split('#').next().unwrap_or("").trim()))
+            .filter(|(_, l)| !l.is_empty());
+        let split = |line: usize, l: &str| -> Result<(Vec<String>, Vec<String>)> {
+            match l.split_once('|') {
+                Some((i, o)) => Ok((
+                    i.split_whitespace().map(str::to_string).collect(),
+                    o.split_whitespace().map(str::to_string).collect(),
+                )),
+                None => bail!("line {line}: inputs and outputs are split by |"),
+            }
+        };
+        let (inputs, outputs) = match lines.next() {
+            Some((line, l)) => split(line, l)?,
+            None => bail!("there is no header of port names"),
+        };
+        let mut rows = vec![];
+        for (line, l) in lines {
+            let (ins, outs) = split(line, l)?;
+            if ins.len() != inputs.len() || outs.len() != outputs.len() {
+                bail!(
+                    "line {line}: expected {} inputs and {} outputs",
+                    inputs.len(),
+                    outputs.len()
+                );
+            }
+            let row = VectorRow {
+                line,
+                inputs: ins
+                    .iter()
+                    .map(|s| match s.as_str() {
+                        "C" | "c" => Ok(Drive::Clock),
+                        s => number(s).map(Drive::Set),
+                    })
+                    .collect::<Result<_>>()
+                    .with_context(|| format!("line {line}"))?,
+                outputs: outs
+                    .iter()
+                    .map(|s| match s.as_str() {
+                        "x" | "X" | "-" => Ok(None),
+                        s => number(s).map(Some),
+                    })
+                    .collect::<Result<_>>()
+                    .with_context(|| format!("line {line}"))?,
+            };
+            rows.push(row);
+        }
+        Ok(Self {
+            inputs,
+            outputs,
+            rows,
+        })
+    }
+}
+
+/// how a row of a test went
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowResult {
+    pub line: usize,
+    pub pass: bool,
+    /// what the outputs showed, `None` where the circuit did not settle
+    pub actual: Vec<Option<Value>>,
+}
+
+/// whether a port shows `expected`, with every bit driven
+fn matches(actual: Option<Value>, expected: Option<u64>) -> bool {
+    match (actual, expected) {
+        (_, None) => true,
+        (Some(v), Some(e)) => v.float == 0 && v.conflict == 0 && v.bits == e,
+        (None, Some(_)) => false,
+    }
+}
+
+/// runs the rows one after the other on a fresh simulation of `circuit`, so what the parts
+/// with state hold carries from one row to the next
+pub fn run_vectors(circuit: &FlatCircuit, vectors: &Vectors) -> Result<Vec<RowResult>> {
+    let mut sim = Sim::new(circuit.clone());
+    let width = |ports: &[(String, usize)], name: &str| {
+        let &(_, part) = ports.iter().find(|(n, _)| n == name)?;
+        Some(circuit.parts[part].params.width)
+    };
+    let widths = vectors
+        .inputs
+        .iter()
+        .map(|name| {
+            width(&circuit.inputs, name).ok_or_else(|| anyhow!("there is no input port {name}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let output_widths = vectors
+        .outputs
+        .iter()
+        .map(|name| {
+            width(&circuit.outputs, name).ok_or_else(|| anyhow!("there is no output port {name}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // a number too big for its port would otherwise lose its high bits without a word
+    for row in vectors.rows.iter() {
+        let inputs = row.inputs.iter().map(|d| match d {
+            Drive::Set(bits) => Some(*bits),
+            Drive::Clock => None,
+        });
+        let values = inputs.chain(row.outputs.iter().copied());
+        let ports = vectors.inputs.iter().chain(vectors.outputs.iter());
+        let port_widths = widths.iter().chain(output_widths.iter());
+        for ((bits, name), &w) in values.zip(ports).zip(port_widths) {
+            if let Some(bits) = bits.filter(|&b| b & !mask(w) != 0) {
+                bail!(
+                    "line {}: {bits} does not fit the {w} bit port {name}",
+                    row.line
+                );
+            }
+        }
+    }
+    let max_steps = circuit.parts.len() + 2;
+    sim.settle(max_steps);
+    let mut results = vec![];
+    for row in vectors.rows.iter() {
+        let inputs = vectors
+            .inputs
+            .iter()
+            .zip(row.inputs.iter())
+            .zip(widths.iter());
+        let mut clocks = vec![];
+        for ((name, drive), &width) in inputs {
+            match drive {
+                Drive::Set(bits) => {
+                    sim.set_input(name, Value::new(*bits, width));
+                }
+                Drive::Clock => {
+                    sim.set_input(name, Value::new(0, width));
+                    clocks.push((name, width));
+                }
+            }
+        }
+        let mut settled = sim.settle(max_steps);
+        if !clocks.is_empty() {
+            for &(name, width) in clocks.iter() {
+                sim.set_input(name, Value::new(1, width));
+            }
+            settled &= sim.settle(max_steps);
+        }
+        let actual = vectors
+            .outputs
+            .iter()
+            .map(|name| sim.output(name).filter(|_| settled))
+            .collect::<Vec<_>>();
+        let pass = actual
+            .iter()
+            .zip(row.outputs.iter())
+            .all(|(&a, &e)| matches(a, e));
+        results.push(RowResult {
+            line: row.line,
+            pass,
+            actual,
+        });
+    }
+    Ok(results)
+}
+
+/// the test vectors of the circuit, saved along with it
+#[derive(Resource, Default)]
+pub struct TestVectors(pub String);
+
+/// the outcome of the last run of the test vectors
+#[derive(Resource, Default)]
+pub struct TestResults(pub Option<Result<(Vectors, Vec<RowResult>), String>>);
+
+/// asks for the test vectors to be run on the simulated circuit
+pub struct RunTests;
+
+pub fn run_tests(
+    mut events: EventReader<RunTests>,
+    vectors: Res<TestVectors>,
+    simulation: Res<Simulation>,
+    mut results: ResMut<TestResults>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    let outcome = Vectors::parse(&vectors.0).and_then(|v| {
+        let rows = run_vectors(&simulation.sim.circuit, &v)?;
+        Ok((v, rows))
+    });
+    results.0 = Some(outcome.map_err(|e| format!("{e:#}")));
+}
+
+/// editing the test vectors of the circuit, and the result of every row
+fn tests_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut vectors: ResMut<TestVectors>,
+    results: Res<TestResults>,
+    mut run: EventWriter<RunTests>,
+) {
+    let mut text = vectors.0.clone();
+    egui::Window::new("tests").show(egui_context.ctx_mut(), |ui| {
+        ui.add(
+            egui::TextEdit::multiline(&mut text)
+                .code_editor()
+                .desired_rows(6)
+                .hint_text("a b | y\n0 1 | 1"),
+        );
+        if ui.button("run").clicked() {
+            run.send(RunTests);
+        }
+        let (v, rows) = match &results.0 {
+            Some(Ok(r)) => r,
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+                return;
+            }
+            None => return,
+        };
+        let passed = rows.iter().filter(|r| r.pass).count();
+        ui.label(format!("{passed} of {} rows pass", rows.len()));
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("test results")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("line");
+                        for name in v.outputs.iter() {
+                            ui.label(name);
+                        }
+                        ui.end_row();
+                        for (row, result) in v.rows.iter().zip(rows.iter()) {
+                            let color = if result.pass {
+                                egui::Color32::GREEN
+                            } else {
+                                egui::Color32::RED
+                            };
+                            ui.colored_label(color, row.line.to_string());
+                            for (&a, &e) in result.actual.iter().zip(row.outputs.iter()) {
+                                let shown = match a {
+                                    Some(a) => format!("{:#x}", a.bits),
+                                    None => "unsettled".to_string(),
+                                };
+                                if matches(a, e) {
+                                    ui.label(shown);
+                                } else {
+                                    let expected = e.map(|e| format!("{e:#x}")).unwrap_or_default();
+                                    ui.colored_label(color, format!("{shown}, not {expected}"));
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+    });
+    if text != vectors.0 {
+        vectors.0 = text;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bevy::prelude::{App, Events, MinimalPlugins};
+
+    use super::*;
+    use crate::{save::CircuitDoc, subcircuit::Library};
+
+    /// the circuits in `tests/circuits`, each with test vectors that have to pass
+    const REFERENCE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/circuits");
+
+    /// an app with no window that simulates `doc` and runs its test vectors once
+    fn run_headless(doc: &CircuitDoc) -> Option<Result<(Vectors, Vec<RowResult>), String>> {
+        let library = Library {
+            defs: doc.subcircuits.clone(),
+            imported: doc.libraries.clone(),
+        };
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(Simulation {
+                sim: Sim::new(FlatCircuit::flatten(doc, &library)),
+                ..Default::default()
+            })
+            .insert_resource(TestVectors(doc.tests.clone()))
+            .insert_resource(TestResults::default())
+            .add_event::<RunTests>()
+            .add_system(run_tests);
+        app.world.resource_mut::<Events<RunTests>>().send(RunTests);
+        app.update();
+        app.world.resource_mut::<TestResults>().0.take()
+    }
+
+    #[test]
+    fn reference_circuits_pass() {
+        let mut files = std::fs::read_dir(REFERENCE)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().is_some_and(|e| e == "ron"))
+            .collect::<Vec<_>>();
+        files.sort();
+        assert!(!files.is_empty());
+        for path in files {
+            let doc = CircuitDoc::read(&path).unwrap();
+            let (vectors, rows) = match run_headless(&doc) {
+                Some(Ok(r)) => r,
+                other => panic!("{}: {other:?}", path.display()),
+            };
+            assert_eq!(rows.len(), vectors.rows.len());
+            for row in rows {
+                assert!(
+                    row.pass,
+                    "{} line {}: {:?}",
+                    path.display(),
+                    row.line,
+                    row.actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn failing_rows_are_reported() {
+        let path = Path::new(REFERENCE).join("half_adder.ron");
+        let mut doc = CircuitDoc::read(&path).unwrap();
+        doc.tests = "a b | s c\n1 1 | 1 x\n1 1 | 0 1".to_string();
+        let (_, rows) = run_headless(&doc).unwrap().unwrap();
+        assert_eq!(
+            rows.iter().map(|r| (r.line, r.pass)).collect::<Vec<_>>(),
+            vec![(2, false), (3, true)]
+        );
+    }
+
+    #[test]
+    fn numbers_must_fit_their_ports() {
+        let path = Path::new(REFERENCE).join("half_adder.ron");
+        let mut doc = CircuitDoc::read(&path).unwrap();
+        doc.tests = "a b | s c\n1 0 | 1 0\n1 1 | 0x10 1".to_string();
+        let e = run_headless(&doc).unwrap().unwrap_err();
+        assert_eq!(e, "line 3: 16 does not fit the 1 bit port s");
+        doc.tests = "a b | s c\n2 0 | 0 0".to_string();
+        let e = run_headless(&doc).unwrap().unwrap_err();
+        assert_eq!(e, "line 2: 2 does not fit the 1 bit port a");
+        doc.tests = "a b | s c\n1 0 | 0b1 0x0".to_string();
+        let (_, rows) = run_headless(&doc).unwrap().unwrap();
+        assert!(rows[0].pass);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Vectors::parse("").is_err());
+        assert!(Vectors::parse("a b y").is_err());
+        assert!(Vectors::parse("a | y\n1 1 | 0").is_err());
+        assert!(Vectors::parse("a | y\n2x | 0").is_err());
+        let v = Vectors::parse("# comment\nclk d | q\nC 0b1 | x # row\n").unwrap();
+        assert_eq!(
+            v.rows,
+            vec![VectorRow {
+                line: 3,
+                inputs: vec![Drive::Clock, Drive::Set(1)],
+                outputs: vec![None],
+            }]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use bevy::prelude::{
+    App, Camera, Commands, CoreStage, DespawnRecursiveExt, Entity, Input, Local, MouseButton,
+    Plugin, Query, Res, ResMut, Resource, Time, Transform, Vec3, With,
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use bevy_rapier2d::prelude::RapierContext;
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    netlist::CircuitNets,
+    run::{Assets, CanvasCursor, GameState},
+    save::{CircuitDoc, OnCanvas},
+    sim::CanvasScope,
+    subcircuit::{Instance, Library},
+    tool::{entity_under_cursor, Tool},
+};
+
+pub struct ViewPlugin;
+
+impl Plugin for ViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(View::default())
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .with_system(view_ui)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::Playing)
+                    .run_in_state(Tool::Select)
+                    .with_system(enter_instance)
+                    .into(),
+            )
+            // after the update stage, so nothing still has commands queued for the entities that go away
+            .add_system_to_stage(CoreStage::PostUpdate, change_view);
+    }
+}
+
+/// an instance that was gone into, with what the canvas showed before
+#[derive(Clone, Debug)]
+pub struct ViewLevel {
+    pub doc: CircuitDoc,
+    /// the gate index of the instance in `doc`
+    pub gate: usize,
+    pub def: String,
+    pub camera: Vec3,
+}
+
+/// where in the hierarchy the canvas is. empty levels is the top level circuit
+#[derive(Resource, Default, Debug)]
+pub struct View {
+    pub levels: Vec<ViewLevel>,
+    pub go: Option<Go>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Go {
+    /// into the instance with this gate index on the canvas
+    Into { gate: usize, def: String },
+    /// back up until this many levels are left
+    Out(usize),
+}
+
+impl View {
+    /// gate indices of the instances leading to what is on the canvas, like [`crate::sim::Scope::path`]
+    pub fn path(&self) -> Vec<usize> {
+        self.levels.iter().map(|l| l.gate).collect()
+    }
+
+    /// the definition on the canvas, none at the top level
+    pub fn def(&self) -> Option<&str> {
+        self.levels.last().map(|l| l.def.as_str())
+    }
+
+    /// the top level circuit, which is `canvas` unless an instance is open
+    pub fn root<'a>(&'a self, canvas: &'a CircuitDoc) -> &'a CircuitDoc {
+        self.levels.first().map(|l| &l.doc).unwrap_or(canvas)
+    }
+}
+
+const DOUBLE_CLICK_SECS: f64 = 0.4;
+
+/// double clicking an instance shows its definition on the canvas
+#[allow(clippy::too_many_arguments)]
+fn enter_instance(
+    mou: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    cursor: CanvasCursor,
+    nets: Res<CircuitNets>,
+    canvas_scope: Res<CanvasScope>,
+    instances: Query<&Instance>,
+    mut view: ResMut<View>,
+    mut last_click: Local<Option<(Entity, f64)>>,
+) {
+    if !mou.just_pressed(MouseButton::Left) || cursor.over_ui() {
+        return;
+    }
+    let now = time.elapsed_seconds_f64();
+    let e = match entity_under_cursor(&rapier_context, &cursor) {
+        Some(e) => e,
+        None => {
+            *last_click = None;
+            return;
+        }
+    };
+    let double = matches!(*last_click, Some((f, t)) if f == e && now - t < DOUBLE_CLICK_SECS);
+    *last_click = if double { None } else { Some((e, now)) };
+    if !double {
+        return;
+    }
+    let instance = match instances.get(e) {
+        Ok(i) => i,
+        Err(_) => return,
+    };
+    if canvas_scope.0.is_none() {
+        bevy::prelude::warn!("save or drop the changes to {:?} first", view.def());
+        return;
+    }
+    if let Some(gate) = nets.gates.iter().position(|&g| g == e) {
+        view.go = Some(Go::Into {
+            gate,
+            def: instance.def.clone(),
+        });
+    }
+}
+
+fn change_view(
+    mut c: Commands,
+    assets: Res<Assets>,
+    library: Res<Library>,
+    nets: Res<CircuitNets>,
+    mut view: ResMut<View>,
+    mut q_camera: Query<&mut Transform, With<Camera>>,
+    old: Query<Entity, OnCanvas>,
+) {
+    if view.go.is_none() {
+        return;
+    }
+    let mut camera = q_camera.single_mut();
+    let doc = match view.go.take() {
+        Some(Go::Into { gate, def }) => match library.defs.get(&def) {
+            Some(doc) => {
+                view.levels.push(ViewLevel {
+                    doc: nets.doc.clone(),
+                    gate,
+                    def,
+                    camera: camera.translation,
+                });
+                doc.clone()
+            }
+            None => return,
+        },
+        Some(Go::Out(depth)) if depth < view.levels.len() => {
+            let level = view.levels.split_off(depth).remove(0);
+            camera.translation = level.camera;
+            level.doc
+        }
+        _ => return,
+    };
+    old.iter().for_each(|e| c.entity(e).despawn_recursive());
+    doc.spawn(&mut c, &assets);
+}
+
+/// where the canvas is, and the way back up
+fn view_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut view: ResMut<View>,
+    mut library: ResMut<Library>,
+    nets: Res<CircuitNets>,
+    canvas_scope: Res<CanvasScope>,
+) {
+    let def = match view.def() {
+        Some(def) => def.to_string(),
+        None => return,
+    };
+    let mut go = None;
+    let mut save = false;
+    egui::Window::new("view").show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("top").clicked() {
+                go = Some(Go::Out(0));
+            }
+            for (i, level) in view.levels.iter().enumerate() {
+                ui.label(">");
+                if i + 1 < view.levels.len() {
+                    if ui.button(&level.def).clicked() {
+                        go = Some(Go::Out(i + 1));
+                    }
+                } else {
+                    ui.label(&level.def);
+                }
+            }
+        });
+        if canvas_scope.0.is_none() {
+            ui.label("edited. saving changes every instance, leaving drops the changes");
+            if library.reaches(&nets.doc, &def) {
+                ui.colored_label(egui::Color32::RED, format!("{def} can not contain itself"));
+            } else {
+                save = ui.button(format!("save to {def}")).clicked();
+            }
+        }
+    });
+    if save {
+        library.defs.insert(def, nets.doc.clone());
+    }
+    if go.is_some() {
+        view.go = go;
+    }
+}
@@ -0,0 +1,479 @@
+use bevy::prelude::{App, Entity, Local, Plugin, Query, Res, ResMut, Resource, With};
+use bevy_inspector_egui::bevy_egui::{
+    egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Vec2},
+    EguiContext,
+};
+use egui_dock::{DockArea, NodeIndex, Style, TabViewer, Tree};
+use iyes_loopless::prelude::ConditionSet;
+
+use crate::{
+    netlist::CircuitNets,
+    run::{GameState, Gate, Selected, WireSprite},
+    sim::{CanvasScope, FlatCircuit, Sim, Simulation, Value},
+    subcircuit::ports,
+};
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Probes::default()).add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .with_system(waveform_ui)
+                .into(),
+        );
+    }
+}
+
+/// where a probe sits in the circuit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProbeAt {
+    /// a port of the top level, by name
+    Port(String),
+    /// a net of the netlist of the scope reached through the instances on `path`
+    Net { path: Vec<usize>, net: usize },
+}
+
+/// a net shown in the waveform viewer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Probe {
+    pub name: String,
+    pub at: ProbeAt,
+}
+
+impl Probe {
+    /// the flat net of the probe, if it is still in the circuit
+    pub fn flat_net(&self, circuit: &FlatCircuit) -> Option<usize> {
+        match &self.at {
+            ProbeAt::Port(name) => {
+                let &(_, part) = circuit
+                    .inputs
+                    .iter()
+                    .chain(circuit.outputs.iter())
+                    .find(|(n, _)| n == name)?;
+                circuit.parts[part].pins.first().copied()
+            }
+            ProbeAt::Net { path, net } => {
+                let scope = circuit.scope_at(path)?;
+                circuit.scopes[scope].nets.get(*net).copied()
+            }
+        }
+    }
+}
+
+/// the nets being recorded
+#[derive(Resource, Default)]
+pub struct Probes {
+    /// the nets in the viewer, in the order they are shown
+    pub shown: Vec<Probe>,
+    /// record every net, not only the shown ones
+    pub record_all: bool,
+}
+
+impl Probes {
+    /// the flat nets to trace, one for every probe still in the circuit
+    pub fn flat_nets(&self, circuit: &FlatCircuit) -> Vec<usize> {
+        if self.record_all {
+            return (0..circuit.net_count).collect();
+        }
+        self.shown
+            .iter()
+            .filter_map(|p| p.flat_net(circuit))
+            .collect()
+    }
+}
+
+/// the value a recorded net had at `time`
+pub fn value_at(changes: &[(u64, Value)], time: u64) -> Option<Value> {
+    let k = changes.partition_point(|&(t, _)| t <= time);
+    k.checked_sub(1).map(|k| changes[k].1)
+}
+
+/// a value as the hex digits of a bus, `z` while nothing drives it and `x` while it is shorted
+pub fn hex(v: Value) -> String {
+    if v.conflict != 0 {
+        "x".to_string()
+    } else if v.is_floating() {
+        "z".to_string()
+    } else {
+        format!("{:x}", v.bits)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tab {
+    Waveforms,
+    Signals,
+}
+
+const ROW: f32 = 22.0;
+const RULER: f32 = 16.0;
+const NAMES: f32 = 140.0;
+const MIN_SCALE: f32 = 0.01;
+const MAX_SCALE: f32 = 200.0;
+const CURSOR_COLORS: [Color32; 2] = [Color32::LIGHT_BLUE, Color32::GOLD];
+
+/// which part of the recording is in view
+struct WaveView {
+    /// the time at the left edge
+    start: f64,
+    /// pixels per step
+    scale: f32,
+    /// keep the latest time in view while the simulation runs
+    follow: bool,
+    /// set with the left and right mouse buttons
+    cursors: [Option<u64>; 2],
+}
+
+impl Default for WaveView {
+    fn default() -> Self {
+        Self {
+            start: 0.0,
+            scale: 8.0,
+            follow: true,
+            cursors: [None; 2],
+        }
+    }
+}
+
+struct WaveWindow {
+    tree: Tree<Tab>,
+    view: WaveView,
+}
+
+impl Default for WaveWindow {
+    fn default() -> Self {
+        let mut tree = Tree::new(vec![Tab::Waveforms]);
+        tree.split_right(NodeIndex::root(), 0.8, vec![Tab::Signals]);
+        Self {
+            tree,
+            view: WaveView::default(),
+        }
+    }
+}
+
+struct Viewer<'a> {
+    view: &'a mut WaveView,
+    probes: &'a mut Vec<Probe>,
+    sim: &'a Sim,
+    add_selected: bool,
+}
+
+impl TabViewer for Viewer<'_> {
+    type Tab = Tab;
+
+    fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Tab) {
+        match tab {
+            Tab::Waveforms => self.waveforms(ui),
+            Tab::Signals => self.signals(ui),
+        }
+    }
+
+    fn title(&mut self, tab: &mut Tab) -> egui::WidgetText {
+        match tab {
+            Tab::Waveforms => "waveforms".into(),
+            Tab::Signals => "signals".into(),
+        }
+    }
+}
+
+impl Viewer<'_> {
+    /// the ports to pick from, the probes there are, and adding the selection
+    fn signals(&mut self, ui: &mut egui::Ui) {
+        if ui.button("add selected wires and ports").clicked() {
+            self.add_selected = true;
+        }
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            let circuit = &self.sim.circuit;
+            for (name, _) in circuit.inputs.iter().chain(circuit.outputs.iter()) {
+                let probe = Probe {
+                    name: name.clone(),
+                    at: ProbeAt::Port(name.clone()),
+                };
+                let mut shown = self.probes.contains(&probe);
+                if ui.checkbox(&mut shown, name).changed() {
+                    if shown {
+                        self.probes.push(probe);
+                    } else {
+                        self.probes.retain(|p| *p != probe);
+                    }
+                }
+            }
+            ui.separator();
+            let mut remove = None;
+            for (i, probe) in self.probes.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("x").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.label(&probe.name);
+                });
+            }
+            if let Some(i) = remove {
+                self.probes.remove(i);
+            }
+        });
+    }
+
+    fn waveforms(&mut self, ui: &mut egui::Ui) {
+        let view = &mut *self.view;
+        let sim = self.sim;
+        ui.horizontal(|ui| {
+            if ui.button("-").clicked() {
+                view.scale = (view.scale / 2.0).max(MIN_SCALE);
+            }
+            if ui.button("+").clicked() {
+                view.scale = (view.scale * 2.0).min(MAX_SCALE);
+            }
+            ui.checkbox(&mut view.follow, "follow");
+            for (i, cursor) in view.cursors.iter().enumerate() {
+                if let Some(t) = cursor {
+                    ui.colored_label(CURSOR_COLORS[i], format!("t = {t}"));
+                }
+            }
+            if let [Some(a), Some(b)] = view.cursors {
+                ui.label(format!("Δt = {} steps", a.abs_diff(b)));
+            }
+        });
+        ui.label("drag to scroll, scroll to zoom, left and right click to place the cursors");
+
+        let rows = self.probes.len();
+        let size = Vec2::new(ui.available_width(), RULER + ROW * rows as f32 + 4.0);
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            let (response, painter) = ui.allocate_painter(size, Sense::click_and_drag());
+            let rect = response.rect;
+            let traces = Rect::from_min_max(Pos2::new(rect.left() + NAMES, rect.top()), rect.max);
+            let span = traces.width() as f64 / view.scale as f64;
+            if view.follow {
+                view.start = (sim.time as f64 - span * 0.9).max(0.0);
+            }
+            let time_at =
+                |x: f32, view: &WaveView| view.start + ((x - traces.left()) / view.scale) as f64;
+
+            if response.dragged() {
+                view.follow = false;
+                view.start = (view.start - (response.drag_delta().x / view.scale) as f64).max(0.0);
+            }
+            if let Some(pos) = response.hover_pos().filter(|p| traces.contains(*p)) {
+                let scroll = ui.input().scroll_delta.y;
+                if scroll != 0.0 {
+                    // zoom around the time under the pointer
+                    let t = time_at(pos.x, view);
+                    view.scale = (view.scale * (scroll * 0.01).exp()).clamp(MIN_SCALE, MAX_SCALE);
+                    view.start = (t - ((pos.x - traces.left()) / view.scale) as f64).max(0.0);
+                }
+            }
+            if let Some(pos) = response
+                .interact_pointer_pos()
+                .filter(|p| traces.contains(*p))
+            {
+                let t = time_at(pos.x, view).round().max(0.0) as u64;
+                if response.clicked() {
+                    view.cursors[0] = Some(t);
+                } else if response.secondary_clicked() {
+                    view.cursors[1] = Some(t);
+                }
+            }
+
+            let x = |t: f64| traces.left() + ((t - view.start) * view.scale as f64) as f32;
+            let text = |pos: Pos2, align: Align2, s: String, color: Color32| {
+                painter.text(pos, align, s, FontId::monospace(11.0), color);
+            };
+            let faint = Stroke::new(1.0, Color32::from_gray(60));
+
+            // the ruler, with ticks at least 60 pixels apart
+            let tick = (0..)
+                .map(|i| [1, 2, 5][i % 3] * 10u64.pow(i as u32 / 3))
+                .find(|&t| t as f32 * view.scale >= 60.0)
+                .unwrap_or(1);
+            let first = (view.start as u64).div_ceil(tick) * tick;
+            let last = (view.start + span) as u64;
+            for t in (first..=last).step_by(tick as usize) {
+                let tx = x(t as f64);
+                painter.line_segment(
+                    [Pos2::new(tx, rect.top()), Pos2::new(tx, rect.bottom())],
+                    faint,
+                );
+                text(
+                    Pos2::new(tx + 2.0, rect.top()),
+                    Align2::LEFT_TOP,
+                    t.to_string(),
+                    Color32::GRAY,
+                );
+            }
+
+            for (row, probe) in self.probes.iter().enumerate() {
+                let top = rect.top() + RULER + ROW * row as f32 + 3.0;
+                let (high, low) = (top, top + ROW - 6.0);
+                let mid = (high + low) / 2.0;
+                let changes = probe
+                    .flat_net(&sim.circuit)
+                    .and_then(|n| sim.trace.nets.iter().position(|&t| t == n))
+                    .map(|i| sim.trace.changes[i].as_slice())
+                    .unwrap_or(&[]);
+                let now = view.cursors[0].unwrap_or(sim.time);
+                let shown = value_at(changes, now).map(hex).unwrap_or_default();
+                text(
+                    Pos2::new(rect.left(), mid),
+                    Align2::LEFT_CENTER,
+                    format!("{} {}", probe.name, shown),
+                    Color32::LIGHT_GRAY,
+                );
+
+                for (k, &(t, v)) in changes.iter().enumerate() {
+                    let end = changes.get(k + 1).map(|c| c.0).unwrap_or(sim.time);
+                    if (end as f64) < view.start || t as f64 > view.start + span {
+                        continue;
+                    }
+                    let (xa, xb) = (
+                        x(t as f64).max(traces.left()),
+                        x(end as f64).min(traces.right()),
+                    );
+                    let color = if v.conflict != 0 {
+                        Color32::RED
+                    } else if v.is_floating() {
+                        Color32::YELLOW
+                    } else {
+                        Color32::LIGHT_GREEN
+                    };
+                    let stroke = Stroke::new(1.5, color);
+                    if v.width == 1 && v.conflict == 0 && !v.is_floating() {
+                        let y = if v.is_high() { high } else { low };
+                        painter.line_segment([Pos2::new(xa, y), Pos2::new(xb, y)], stroke);
+                        if k > 0 && x(t as f64) >= traces.left() {
+                            painter.line_segment([Pos2::new(xa, high), Pos2::new(xa, low)], stroke);
+                        }
+                    } else if v.width == 1 {
+                        painter.line_segment([Pos2::new(xa, mid), Pos2::new(xb, mid)], stroke);
+                    } else {
+                        // a bus is drawn as a stretched hexagon for every value it holds
+                        let slant = ((xb - xa) / 2.0).min(3.0);
+                        let points = [
+                            Pos2::new(xa, mid),
+                            Pos2::new(xa + slant, high),
+                            Pos2::new(xb - slant, high),
+                            Pos2::new(xb, mid),
+                            Pos2::new(xb - slant, low),
+                            Pos2::new(xa + slant, low),
+                            Pos2::new(xa, mid),
+                        ];
+                        points
+                            .windows(2)
+                            .for_each(|p| painter.line_segment([p[0], p[1]], stroke));
+                        let s = hex(v);
+                        if (xb - xa) > 7.0 * s.len() as f32 + 8.0 {
+                            text(
+                                Pos2::new((xa + xb) / 2.0, mid),
+                                Align2::CENTER_CENTER,
+                                s,
+                                color,
+                            );
+                        }
+                    }
+                }
+            }
+
+            for (cursor, color) in view.cursors.iter().zip(CURSOR_COLORS) {
+                if let Some(t) = cursor.filter(|&t| traces.x_range().contains(&x(t as f64))) {
+                    let cx = x(t as f64);
+                    painter.line_segment(
+                        [Pos2::new(cx, rect.top()), Pos2::new(cx, rect.bottom())],
+                        Stroke::new(1.0, color),
+                    );
+                }
+            }
+        });
+    }
+}
+
+/// a panel along the bottom of the window with the recorded nets over simulation time,
+/// and a tab to pick which nets those are
+#[allow(clippy::too_many_arguments)]
+fn waveform_ui(
+    mut egui_context: ResMut<EguiContext>,
+    mut window: Local<WaveWindow>,
+    mut probes: ResMut<Probes>,
+    mut simulation: ResMut<Simulation>,
+    nets: Res<CircuitNets>,
+    canvas_scope: Res<CanvasScope>,
+    selected_wires: Query<&WireSprite, With<Selected>>,
+    selected_gates: Query<(Entity, &Gate), With<Selected>>,
+) {
+    let wanted = probes.flat_nets(&simulation.sim.circuit);
+    if simulation.sim.trace.nets != wanted {
+        simulation.sim.trace(wanted);
+    }
+
+    let window = &mut *window;
+    let mut edited = probes.shown.clone();
+    let mut viewer = Viewer {
+        view: &mut window.view,
+        probes: &mut edited,
+        sim: &simulation.sim,
+        add_selected: false,
+    };
+    let ctx = egui_context.ctx_mut();
+    egui::TopBottomPanel::bottom("waveforms")
+        .resizable(true)
+        .default_height(180.0)
+        .show(ctx, |ui| {
+            DockArea::new(&mut window.tree)
+                .style(Style::from_egui(ui.style()))
+                .show_inside(ui, &mut viewer);
+        });
+    let add_selected = viewer.add_selected;
+
+    let scope = canvas_scope
+        .0
+        .and_then(|s| simulation.sim.circuit.scopes.get(s));
+    if let Some(scope) = scope.filter(|_| add_selected) {
+        let mut add = |name: String, at: ProbeAt| {
+            let probe = Probe { name, at };
+            if !edited.contains(&probe) {
+                edited.push(probe);
+            }
+        };
+        let net_name = |net: usize| match nets.netlist.nets[net].names.first() {
+            Some(name) => name.clone(),
+            None => format!("net {net}"),
+        };
+        for ws in selected_wires.iter() {
+            if let Some(wi) = nets.wires.iter().position(|&w| w == ws.wire) {
+                let net = nets.netlist.wire_nets[wi];
+                let at = ProbeAt::Net {
+                    path: scope.path.clone(),
+                    net,
+                };
+                add(net_name(net), at);
+            }
+        }
+        let doc_ports = ports(&nets.doc);
+        for (e, _) in selected_gates
+            .iter()
+            .filter(|(_, g)| matches!(g, Gate::Input | Gate::Output))
+        {
+            let gi = match nets.gates.iter().position(|&g| g == e) {
+                Some(gi) => gi,
+                None => continue,
+            };
+            let name = match doc_ports.iter().find(|p| p.gate == gi) {
+                Some(p) => p.name.clone(),
+                None => continue,
+            };
+            let at = if scope.path.is_empty() {
+                ProbeAt::Port(name.clone())
+            } else {
+                ProbeAt::Net {
+                    path: scope.path.clone(),
+                    net: nets.netlist.pin_nets[gi][0],
+                }
+            };
+            add(name, at);
+        }
+    }
+    if edited != probes.shown {
+        probes.shown = edited;
+    }
+}